@@ -0,0 +1,269 @@
+use crate::{
+    datastructures::Entailment,
+    error::Error,
+    limits::Limits,
+    parser::parse,
+    prover::{prove_with, Statistics, Verdict},
+    trace::{Trace, Verbosity},
+};
+use std::{
+    fmt, io,
+    time::{Duration, Instant},
+};
+
+/// Starts a comment that runs until the end of the line
+pub const COMMENT: char = '#';
+/// Marks a comment as the expected verdict of the entailment on the same line, e.g. `# expect valid`
+pub const EXPECT: &str = "expect";
+
+/// The verdict a regression suite expects for an entailment
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Expected {
+    Valid,
+    Invalid,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Valid => write!(f, "valid"),
+            Expected::Invalid => write!(f, "invalid"),
+        }
+    }
+}
+
+/// A single entailment of a batch file together with its position and annotation
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Case {
+    pub line: usize,
+    pub input: String,
+    /// The annotated verdict, or why the annotation could not be read
+    pub expected: Result<Option<Expected>, Error>,
+}
+
+/// The outcome of checking a single case
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub case: Case,
+    pub outcome: Result<Expected, Error>,
+    pub time: Duration,
+}
+
+impl Report {
+    /// Errors never pass, verdicts pass if they match the annotation (if there is one)
+    pub fn passed(&self) -> bool {
+        match (&self.case.expected, &self.outcome) {
+            (Err(_), _) | (_, Err(_)) => false,
+            (Ok(None), Ok(_)) => true,
+            (Ok(Some(expected)), Ok(verdict)) => expected == verdict,
+        }
+    }
+}
+
+/// Reads one entailment per line. Empty lines and lines that only contain a comment are skipped.
+/// A malformed annotation only affects the case on its line.
+pub fn read_cases(input: &str) -> Vec<Case> {
+    let mut cases = Vec::new();
+    for (index, text) in input.lines().enumerate() {
+        let (entailment, comment) = match text.find(COMMENT) {
+            Some(start) => (&text[..start], Some(start)),
+            None => (text, None),
+        };
+        if entailment.trim().is_empty() {
+            continue;
+        }
+        let expected = match comment {
+            Some(start) => read_expected(&text[start + 1..]).map_err(|message| Error::Parse {
+                line: index as i32 + 1,
+                column: text[..start].chars().count() as i32 + 2,
+                message,
+            }),
+            None => Ok(None),
+        };
+        cases.push(Case {
+            line: index + 1,
+            input: entailment.to_string(),
+            expected,
+        });
+    }
+    cases
+}
+
+fn read_expected(comment: &str) -> Result<Option<Expected>, String> {
+    let mut words = comment.split_whitespace();
+    if words.next() != Some(EXPECT) {
+        return Ok(None);
+    }
+    match (words.next(), words.next()) {
+        (Some("valid"), None) => Ok(Some(Expected::Valid)),
+        (Some("invalid"), None) => Ok(Some(Expected::Invalid)),
+        _ => Err(format!(
+            "Expected `{} valid` or `{} invalid` but found `{}`",
+            EXPECT,
+            EXPECT,
+            comment.trim()
+        )),
+    }
+}
+
+/// Parses and decides a single case within the limits. Parse errors are reported at the line of the case.
+pub fn check(case: Case, limits: &Limits) -> Report {
+    let mut sink = io::sink();
+    let mut quiet = Trace::new(Verbosity::Quiet, false, &mut sink);
+    check_with(case, |entailment| {
+        prove_with(entailment, limits, &mut Statistics::default(), &mut quiet)
+    })
+}
+
+/// Like [`check`], but decides the case with the given procedure, e.g. the graph based one. Cases
+/// with a malformed annotation are not decided at all.
+pub fn check_with(case: Case, prove: impl FnOnce(&Entailment) -> Result<Verdict, Error>) -> Report {
+    let start = Instant::now();
+    let outcome = case
+        .expected
+        .clone()
+        .and_then(|_| parse(&case.input))
+        .map_err(|error| match error {
+            Error::Parse {
+                column, message, ..
+            } => Error::Parse {
+                line: case.line as i32,
+                column,
+                message,
+            },
+            other => other,
+        })
+        .and_then(|entailment| prove(&entailment))
+        .and_then(|verdict| match verdict {
+            Verdict::Valid(_) => Ok(Expected::Valid),
+            Verdict::Invalid(_, _) => Ok(Expected::Invalid),
+            Verdict::Unknown(reason) => Err(reason.into()),
+        });
+    Report {
+        case,
+        outcome,
+        time: start.elapsed(),
+    }
+}
+
+/// A table with one row per case followed by the pass/fail counts and the total time
+pub struct Summary<'a>(pub &'a [Report]);
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>6}  {:<8}  {:<8}  {:>10}  result",
+            "line", "expected", "verdict", "time"
+        )?;
+        for report in self.0 {
+            let expected = match &report.case.expected {
+                Ok(Some(expected)) => expected.to_string(),
+                Ok(None) => "-".to_string(),
+                Err(_) => "?".to_string(),
+            };
+            let verdict = match &report.outcome {
+                Ok(verdict) => verdict.to_string(),
+                Err(_) => "error".to_string(),
+            };
+            write!(
+                f,
+                "{:>6}  {:<8}  {:<8}  {:>10.3?}  {}",
+                report.case.line,
+                expected,
+                verdict,
+                report.time,
+                if report.passed() { "ok" } else { "FAIL" }
+            )?;
+            if let Err(error) = &report.outcome {
+                write!(f, " ({})", error)?;
+            }
+            writeln!(f)?;
+        }
+        let passed = self.0.iter().filter(|report| report.passed()).count();
+        let total: Duration = self.0.iter().map(|report| report.time).sum();
+        write!(
+            f,
+            "{} passed, {} failed, {:.3?} in total",
+            passed,
+            self.0.len() - passed,
+            total
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check, check_with, read_cases, Case, Expected};
+    use crate::error::Error;
+    use crate::graph;
+    use crate::limits::Limits;
+
+    const SUITE: &str = "# a small suite
+True|SepConj[x->Nil] |- True|SepConj[x->Nil] # expect valid
+
+True|SepConj[x->y] |- True|Emp # expect valid
+True|SepConj[x->y] |- True|Emp # not annotated
+  True|Sepconj[x->y] |- True|Emp
+True|Emp |- True|Emp # expect maybe
+True|Emp |- True|Emp # expect valid
+";
+
+    #[test]
+    fn test_read_cases() {
+        let cases = read_cases(SUITE);
+        let lines: Vec<usize> = cases.iter().map(|case| case.line).collect();
+        assert_eq!(vec![2, 4, 5, 6, 7, 8], lines);
+        assert_eq!(
+            vec![
+                Some(Expected::Valid),
+                Some(Expected::Valid),
+                None,
+                None,
+                None,
+                Some(Expected::Valid)
+            ],
+            cases
+                .iter()
+                .map(|case| case.expected.clone().unwrap_or(None))
+                .collect::<Vec<_>>()
+        );
+        match &cases[4].expected {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((7, 23), (*line, *column)),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let reports: Vec<_> = read_cases(SUITE)
+            .into_iter()
+            .map(|case| check(case, &Limits::default()))
+            .collect();
+        let passed: Vec<bool> = reports.iter().map(|report| report.passed()).collect();
+        assert_eq!(vec![true, false, true, false, false, true], passed);
+        assert_eq!(Ok(Expected::Invalid), reports[1].outcome);
+        match &reports[3].outcome {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((6, 8), (*line, *column)),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+        // The malformed annotation fails its own line, the following line is still checked
+        match &reports[4].outcome {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((7, 23), (*line, *column)),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+
+        let unannotated = Case {
+            line: 1,
+            input: "True|Emp |- True|Emp".to_string(),
+            expected: Ok(None),
+        };
+        assert!(check(unannotated.clone(), &Limits::default()).passed());
+        assert!(check_with(unannotated.clone(), graph::prove).passed());
+        let no_steps = Limits {
+            max_steps: Some(0),
+            ..Limits::default()
+        };
+        assert!(!check(unannotated, &no_steps).passed());
+    }
+}
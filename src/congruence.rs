@@ -0,0 +1,155 @@
+use crate::datastructures::{
+    Entailment, Expr,
+    Op::{AtomEq, AtomNeq},
+    Pure,
+};
+use std::collections::{HashMap, HashSet};
+
+/// The (dis)equalities of a pure formula, saturated once with a union-find over its expressions.
+/// Afterwards every query is a hash lookup.
+#[derive(Debug, Clone, Default)]
+pub struct Congruence {
+    /// The equivalence class of every expression of the formula
+    class: HashMap<Expr, usize>,
+    /// The representative of every class: nil if it belongs to the class, else the smallest variable
+    representatives: Vec<Expr>,
+    /// The pairs of distinct classes, in both orders
+    distinct: HashSet<(usize, usize)>,
+    contradictory: bool,
+}
+
+impl Congruence {
+    pub fn new(pure: &Pure) -> Self {
+        let ops = match pure {
+            Pure::And(ops) => ops.as_slice(),
+            Pure::True => &[],
+        };
+        let mut index: HashMap<&Expr, usize> = HashMap::new();
+        let mut exprs: Vec<&Expr> = Vec::new();
+        for op in ops {
+            let (AtomEq(l, r) | AtomNeq(l, r)) = op;
+            for expr in [l, r] {
+                index.entry(expr).or_insert_with(|| {
+                    exprs.push(expr);
+                    exprs.len() - 1
+                });
+            }
+        }
+
+        let mut parent: Vec<usize> = (0..exprs.len()).collect();
+        fn find(parent: &mut [usize], mut node: usize) -> usize {
+            while parent[node] != node {
+                parent[node] = parent[parent[node]];
+                node = parent[node];
+            }
+            node
+        }
+        for op in ops {
+            if let AtomEq(l, r) = op {
+                let (l, r) = (find(&mut parent, index[l]), find(&mut parent, index[r]));
+                parent[l] = r;
+            }
+        }
+
+        let mut congruence = Congruence::default();
+        let mut class_of_root = HashMap::new();
+        for (node, &expr) in exprs.iter().enumerate() {
+            let root = find(&mut parent, node);
+            let class = *class_of_root.entry(root).or_insert_with(|| {
+                congruence.representatives.push(expr.clone());
+                congruence.representatives.len() - 1
+            });
+            let representative = &mut congruence.representatives[class];
+            // Nil is the smallest expression
+            if expr < representative {
+                *representative = expr.clone();
+            }
+            congruence.class.insert(expr.clone(), class);
+        }
+        for op in ops {
+            if let AtomNeq(l, r) = op {
+                let (l, r) = (congruence.class[l], congruence.class[r]);
+                congruence.contradictory |= l == r;
+                congruence.distinct.insert((l, r));
+                congruence.distinct.insert((r, l));
+            }
+        }
+        congruence
+    }
+
+    /// Saturates the pure part of the antecedent of the goal, as the rules expect it
+    pub fn of(goal: &Entailment) -> Self {
+        Self::new(goal.antecedent.get_pure())
+    }
+
+    /// Whether the formula implies E1=E2
+    pub fn equal(&self, l: &Expr, r: &Expr) -> bool {
+        l == r
+            || match (self.class.get(l), self.class.get(r)) {
+                (Some(l), Some(r)) => l == r,
+                _ => false,
+            }
+    }
+
+    /// Whether the formula contains E1'!=E2' for some E1'=E1 and E2'=E2
+    pub fn distinct(&self, l: &Expr, r: &Expr) -> bool {
+        match (self.class.get(l), self.class.get(r)) {
+            (Some(&l), Some(&r)) => self.distinct.contains(&(l, r)),
+            _ => false,
+        }
+    }
+
+    /// Whether the formula implies E!=E for some E, i.e. it is unsatisfiable
+    pub fn is_contradictory(&self) -> bool {
+        self.contradictory
+    }
+
+    /// The expression every expression equal to the given one can be replaced by
+    pub fn representative(&self, expr: &Expr) -> Expr {
+        match self.class.get(expr) {
+            Some(&class) => self.representatives[class].clone(),
+            None => expr.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Congruence;
+    use crate::datastructures::{
+        Expr,
+        Expr::Nil,
+        Op::{AtomEq, AtomNeq},
+        Pure::{And, True},
+    };
+
+    #[test]
+    fn test_congruence() {
+        let var = Expr::new_var;
+        let pure = And(vec![
+            AtomEq(var("x"), var("y")),
+            AtomNeq(var("z"), var("y")),
+            AtomEq(var("z"), Nil),
+            AtomEq(var("w"), var("x")),
+        ]);
+        let facts = Congruence::new(&pure);
+        assert!(facts.equal(&var("w"), &var("y")));
+        assert!(facts.equal(&var("v"), &var("v")));
+        assert!(!facts.equal(&var("x"), &var("z")));
+        assert!(facts.distinct(&var("w"), &Nil));
+        assert!(facts.distinct(&Nil, &var("x")));
+        assert!(!facts.distinct(&var("w"), &var("v")));
+        assert!(!facts.is_contradictory());
+        assert_eq!(var("w"), facts.representative(&var("y")));
+        assert_eq!(Nil, facts.representative(&var("z")));
+        assert_eq!(var("v"), facts.representative(&var("v")));
+
+        let mut ops = match pure {
+            And(ops) => ops,
+            True => unreachable!(),
+        };
+        ops.push(AtomNeq(var("x"), var("w")));
+        assert!(Congruence::new(&And(ops)).is_contradictory());
+        assert!(!Congruence::new(&True).is_contradictory());
+    }
+}
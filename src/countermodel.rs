@@ -0,0 +1,350 @@
+use crate::datastructures::{
+    AtomSpatial::{PointsTo, Pred, Tree, DLS, LS},
+    Entailment, Expr, Field, Formula,
+    Op::AtomEq,
+    Pure::And,
+    Spatial::SepConj,
+    Variable,
+};
+use crate::semantics::{eval, eval_record, satisfies};
+pub use crate::semantics::{Cell, Heap, Stack, Value};
+use std::{collections::BTreeMap, fmt};
+
+/// A concrete state that satisfies the antecedent but not the consequent of an entailment
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Countermodel {
+    pub stack: Stack,
+    pub heap: Heap,
+}
+
+/// Turns the goal on which the proof search got stuck into a concrete countermodel.
+/// The stuck goal is in normal form, so its antecedent only consists of explicit points-to facts,
+/// trees with an allocated root and disequalities. The variables are therefore partitioned into aliasing classes (starting
+/// with all of them being distinct) until a partition is found that falsifies the consequent.
+pub fn countermodel(stuck: &Entailment) -> Option<Countermodel> {
+    let vars = stuck.get_vars();
+    let mut assignment = Vec::with_capacity(vars.len());
+    search(stuck, &vars, &mut assignment, 0)
+}
+
+/// Enumerates the assignments of variables to nil or location classes as restricted growth strings.
+fn search(
+    stuck: &Entailment,
+    vars: &[Variable],
+    assignment: &mut Vec<Value>,
+    classes: usize,
+) -> Option<Countermodel> {
+    if assignment.len() == vars.len() {
+        let stack: Stack = vars
+            .iter()
+            .cloned()
+            .zip(assignment.iter().cloned())
+            .collect();
+        return check(stuck, stack);
+    }
+
+    let mut candidates = vec![Value::Loc(classes + 1)];
+    candidates.extend((1..=classes).map(Value::Loc));
+    candidates.push(Value::Nil);
+
+    for candidate in candidates {
+        assignment.push(candidate);
+        let new_classes = if candidate == Value::Loc(classes + 1) {
+            classes + 1
+        } else {
+            classes
+        };
+        let result = search(stuck, vars, assignment, new_classes);
+        assignment.pop();
+        if result.is_some() {
+            return result;
+        }
+    }
+    None
+}
+
+fn check(stuck: &Entailment, stack: Stack) -> Option<Countermodel> {
+    let model = Countermodel {
+        heap: build_heap(&stuck.antecedent, &stack)?,
+        stack,
+    };
+    if model.falsifies(stuck) {
+        Some(model)
+    } else {
+        None
+    }
+}
+
+/// Builds the smallest heap described by the antecedent. Remaining list segments are
+/// collapsed to a single cell, doubly linked ones only if their first and last cell coincide.
+/// Nonempty trees get a root with two leaves at locations no variable points to.
+/// User-defined predicates are unfolded by the prover and are therefore not handled.
+fn build_heap(antecedent: &Formula, stack: &Stack) -> Option<Heap> {
+    let mut heap = Heap::new();
+    let mut fresh = (stack.values())
+        .filter_map(|value| match value {
+            Value::Loc(loc) => Some(*loc),
+            Value::Nil => None,
+        })
+        .max()
+        .unwrap_or(0);
+    if let SepConj(atoms) = antecedent.get_spatial() {
+        for atom in atoms {
+            let (l, cell) = match atom {
+                PointsTo(l, r) => (eval(l, stack)?, eval_record(r, stack)?),
+                LS(field, l, r) => {
+                    let (l, r) = (eval(l, stack)?, eval(r, stack)?);
+                    if l == r {
+                        continue;
+                    }
+                    (l, Cell(BTreeMap::from([(field.clone(), r)])))
+                }
+                DLS(first, last, prev, next) => {
+                    let (first, last) = (eval(first, stack)?, eval(last, stack)?);
+                    let (prev, next) = (eval(prev, stack)?, eval(next, stack)?);
+                    if first == next && last == prev {
+                        continue;
+                    }
+                    if first != last {
+                        return None;
+                    }
+                    let cell = [(Field::next(), next), (Field::prev(), prev)];
+                    (first, Cell(BTreeMap::from(cell)))
+                }
+                // A single cell could also be described by a points-to fact or a list segment
+                Tree(root) => {
+                    let root = eval(root, stack)?;
+                    if root == Value::Nil {
+                        continue;
+                    }
+                    let leaf = [(Field::left(), Value::Nil), (Field::right(), Value::Nil)];
+                    let children = [Field::left(), Field::right()].map(|field| {
+                        fresh += 1;
+                        heap.insert(fresh, Cell(BTreeMap::from(leaf.clone())));
+                        (field, Value::Loc(fresh))
+                    });
+                    (root, Cell(BTreeMap::from(children)))
+                }
+                Pred(..) => return None,
+            };
+            match l {
+                Value::Loc(loc) if !heap.contains_key(&loc) => {
+                    heap.insert(loc, cell);
+                }
+                _ => return None,
+            }
+        }
+    }
+    Some(heap)
+}
+
+impl Countermodel {
+    /// Checks with the model checker that the state satisfies the antecedent but not the consequent
+    pub fn falsifies(&self, goal: &Entailment) -> bool {
+        satisfies(&goal.antecedent, &self.stack, &self.heap)
+            && !satisfies(&goal.consequent, &self.stack, &self.heap)
+    }
+
+    /// Extends a countermodel of a premiss to the goal a rule derived it from. Variables that the
+    /// rule eliminated get the value of what replaced them, i.e. of an expression they equal in the
+    /// antecedent, or otherwise a new location. Cells that the rule took away from the antecedent
+    /// are put back, and the variables it introduced are dropped. Since the rules are invertible,
+    /// the result falsifies the goal, which [`Countermodel::falsifies`] can confirm.
+    pub fn lift(&self, goal: &Entailment) -> Option<Countermodel> {
+        let vars = goal.get_vars();
+        let mut stack: Stack = (vars.iter())
+            .filter_map(|var| Some((var.clone(), *self.stack.get(var)?)))
+            .collect();
+        let mut fresh = (self.stack.values())
+            .chain(self.heap.values().flat_map(|cell| cell.0.values()))
+            .filter_map(|value| match value {
+                Value::Loc(loc) => Some(*loc),
+                Value::Nil => None,
+            })
+            .chain(self.heap.keys().cloned())
+            .max()
+            .unwrap_or(0);
+        let equalities: Vec<(&Expr, &Expr)> = match goal.antecedent.get_pure() {
+            And(ops) => (ops.iter())
+                .filter_map(|op| match op {
+                    AtomEq(l, r) => Some((l, r)),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        while let Some(missing) = vars.iter().find(|var| !stack.contains_key(var)) {
+            let known =
+                equalities
+                    .iter()
+                    .find_map(|(l, r)| match (eval(l, &stack), eval(r, &stack)) {
+                        (None, Some(value)) => Some((*l, value)),
+                        (Some(value), None) => Some((*r, value)),
+                        _ => None,
+                    });
+            match known {
+                Some((Expr::Var(var), value)) => stack.insert(var.clone(), value),
+                _ => {
+                    fresh += 1;
+                    stack.insert(missing.clone(), Value::Loc(fresh))
+                }
+            };
+        }
+
+        let mut heap = self.heap.clone();
+        if let SepConj(atoms) = goal.antecedent.get_spatial() {
+            for atom in atoms {
+                let (root, cell) = match atom {
+                    PointsTo(l, r) => (eval(l, &stack)?, eval_record(r, &stack)?),
+                    // A tree of a single cell, which the rule matched as a whole
+                    Tree(root) => {
+                        let leaf = [(Field::left(), Value::Nil), (Field::right(), Value::Nil)];
+                        (eval(root, &stack)?, Cell(BTreeMap::from(leaf)))
+                    }
+                    // The premisses describe the cells of the other atoms themselves
+                    LS(..) | DLS(..) | Pred(..) => continue,
+                };
+                if let Value::Loc(loc) = root {
+                    heap.entry(loc).or_insert(cell);
+                }
+            }
+        }
+        Some(Countermodel { stack, heap })
+    }
+}
+
+impl fmt::Display for Countermodel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stack = self
+            .stack
+            .iter()
+            .map(|(var, value)| format!("{} = {}", var.0, value))
+            .collect::<Vec<String>>();
+        let heap = self
+            .heap
+            .iter()
+            .map(|(loc, value)| format!("{} -> {}", Value::Loc(*loc), value))
+            .collect::<Vec<String>>();
+        write!(
+            f,
+            "Stack: [{}] Heap: [{}]",
+            stack.join(", "),
+            heap.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{countermodel, Cell, Countermodel, Heap, Stack, Value};
+    use crate::datastructures::{
+        AtomSpatial::{PointsTo, LS},
+        Entailment, Expr,
+        Expr::Nil,
+        Field, Formula,
+        Op::{AtomEq, AtomNeq},
+        Pure::{And, True},
+        Record,
+        Spatial::{Emp, SepConj},
+        Variable,
+    };
+
+    #[test]
+    fn test_countermodel() {
+        let stuck = Entailment {
+            antecedent: Formula(
+                And(vec![
+                    AtomNeq(Expr::new_var("x"), Nil),
+                    AtomNeq(Expr::new_var("y"), Nil),
+                    AtomNeq(Expr::new_var("x"), Expr::new_var("y")),
+                ]),
+                SepConj(vec![PointsTo(Expr::new_var("x"), Record::next(Nil))]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                And(vec![AtomEq(Expr::new_var("x"), Expr::new_var("y"))]),
+                Emp,
+                Vec::new(),
+            ),
+        };
+        let mut stack = Stack::new();
+        stack.insert(Variable("x".to_string()), Value::Loc(1));
+        stack.insert(Variable("y".to_string()), Value::Loc(2));
+        let mut heap = Heap::new();
+        heap.insert(1, Cell::next(Value::Nil));
+        assert_eq!(Some(Countermodel { stack, heap }), countermodel(&stuck));
+
+        let aliasing = Entailment {
+            antecedent: Formula(
+                And(vec![AtomNeq(Expr::new_var("x"), Nil)]),
+                SepConj(vec![PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y")),
+                )]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![LS(
+                    Field::next(),
+                    Expr::new_var("x"),
+                    Expr::new_var("y"),
+                )]),
+                Vec::new(),
+            ),
+        };
+        let mut stack = Stack::new();
+        stack.insert(Variable("x".to_string()), Value::Loc(1));
+        stack.insert(Variable("y".to_string()), Value::Loc(1));
+        let mut heap = Heap::new();
+        heap.insert(1, Cell::next(Value::Loc(1)));
+        assert_eq!(Some(Countermodel { stack, heap }), countermodel(&aliasing));
+
+        let valid = Entailment {
+            antecedent: Formula(
+                And(vec![AtomNeq(Expr::new_var("x"), Nil)]),
+                SepConj(vec![PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y")),
+                )]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y")),
+                )]),
+                Vec::new(),
+            ),
+        };
+        assert_eq!(None, countermodel(&valid));
+    }
+
+    #[test]
+    fn test_lift() -> Result<(), crate::error::Error> {
+        // A premiss of the goal after substituting y and framing away the cell of z
+        let var = |name: &str| Variable(name.to_string());
+        let mut stack = Stack::new();
+        stack.insert(var("x"), Value::Loc(1));
+        stack.insert(var("x'1"), Value::Loc(1));
+        let mut heap = Heap::new();
+        heap.insert(1, Cell::next(Value::Nil));
+        let premiss = Countermodel { stack, heap };
+
+        let goal = crate::parser::parse(
+            "And[Eq(y,x)]|SepConj[x->Nil,z->y] |- True|SepConj[x->Nil,z->Nil]",
+        )?;
+        let mut stack = Stack::new();
+        stack.insert(var("x"), Value::Loc(1));
+        stack.insert(var("y"), Value::Loc(1));
+        stack.insert(var("z"), Value::Loc(2));
+        let mut heap = Heap::new();
+        heap.insert(1, Cell::next(Value::Nil));
+        heap.insert(2, Cell::next(Value::Loc(1)));
+        let lifted = premiss.lift(&goal);
+        assert_eq!(Some(Countermodel { stack, heap }), lifted);
+        assert!(lifted.is_some_and(|model| model.falsifies(&goal)));
+        Ok(())
+    }
+}
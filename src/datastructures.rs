@@ -1,48 +1,115 @@
-#[derive(PartialEq, Eq, Clone, Debug)]
+use crate::congruence::Congruence;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
 pub struct Variable(pub String);
 
-#[derive(PartialEq, Eq, Clone, Debug)]
+/// Separates the name of a variable introduced by a rule from its numeric suffix, e.g. `x'1`.
+/// The parser accepts such names so that printed goals can be read again, but the rules only
+/// introduce names that occur nowhere in the goal, so they never clash with the input's variables.
+pub const FRESH_SEPARATOR: char = '\'';
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
 pub enum Expr {
     Nil,
     Var(Variable),
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone)]
 pub enum Op {
     AtomEq(Expr, Expr),
     AtomNeq(Expr, Expr),
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum Pure {
     And(Vec<Op>),
     True,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+/// The name of a field of a heap cell
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug)]
+pub struct Field(pub String);
+
+/// The field of `x->y` and `ls(x,y)`, which do not name their field
+pub const NEXT: &str = "next";
+
+/// The backward link of `dls`, whose forward link is [`NEXT`]
+pub const PREV: &str = "prev";
+
+/// The children of the cells of `tree`
+pub const LEFT: &str = "left";
+pub const RIGHT: &str = "right";
+
+/// The values of some fields of a heap cell. The cell may have further fields with arbitrary values.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug, Default)]
+pub struct Record(pub BTreeMap<Field, Expr>);
+
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone)]
 pub enum AtomSpatial {
-    PointsTo(Expr, Expr),
-    LS(Expr, Expr),
+    PointsTo(Expr, Record),
+    /// A list segment linked by the given field
+    LS(Field, Expr, Expr),
+    /// `dls(E1,E2,E3,E4)`, a doubly linked list segment from its first cell E1 to its last cell E2,
+    /// where E3 is the `prev` of E1 and E4 the `next` of E2
+    DLS(Expr, Expr, Expr, Expr),
+    /// `tree(E)`, a binary tree rooted at E whose cells link their subtrees by `left` and `right`
+    Tree(Expr),
+    /// An occurrence of a user-defined predicate with its arguments
+    Pred(Arc<Predicate>, Vec<Expr>),
+}
+
+/// A user-defined inductive predicate of the form
+/// `pred P(x1,...,xn) := emp & base | exists z1,...,zk. x1->record * P(call) & step`.
+/// The pure parts of both cases exclude each other and every zi is the value of a field of the record,
+/// so like `ls` the predicate describes at most one subheap for the given arguments.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Predicate {
+    pub name: String,
+    pub params: Vec<Variable>,
+    /// The pure part of the empty case
+    pub base: Vec<Op>,
+    pub existentials: Vec<Variable>,
+    /// The cell of the first parameter in the nonempty case
+    pub record: Record,
+    /// The arguments of the recursive occurrence
+    pub call: Vec<Expr>,
+    /// The pure part of the nonempty case
+    pub step: Vec<Op>,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum Spatial {
     SepConj(Vec<AtomSpatial>),
     Emp,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
-pub struct Formula(pub Pure, pub Spatial);
+/// `exists z1,...,zk. Π | Σ`, only the consequent of an entailment quantifies variables.
+/// The existentials do not occur in the antecedent, so rules may rewrite the consequent
+/// with the facts of the antecedent without capturing them.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Formula(pub Pure, pub Spatial, pub Vec<Variable>);
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct Entailment {
     pub antecedent: Formula,
     pub consequent: Formula,
 }
 
+/// A rule of the decision procedure. The facts are the pure part of the antecedent of the goal,
+/// which the proof search saturates once per goal and hands to every rule it tries.
 pub trait Rule {
-    fn predicate(&self, goal: &Entailment) -> bool;
-    fn premisses(&self, goal: Entailment) -> Option<Vec<Entailment>>;
+    fn name(&self) -> &'static str;
+    fn predicate(&self, goal: &Entailment, facts: &Congruence) -> bool;
+    /// Whether the predicate actually restricts the goals the rule applies to, most rules just
+    /// return `true` and check their premisses instead
+    fn guarded(&self) -> bool {
+        false
+    }
+    fn premisses(&self, goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>>;
 }
 
 impl Op {
@@ -52,6 +119,14 @@ impl Op {
             Op::AtomNeq(_, _) => false,
         }
     }
+
+    /// Applies the function to both operands
+    pub fn map(&self, f: impl Fn(&Expr) -> Expr) -> Op {
+        match self {
+            Op::AtomEq(l, r) => Op::AtomEq(f(l), f(r)),
+            Op::AtomNeq(l, r) => Op::AtomNeq(f(l), f(r)),
+        }
+    }
 }
 
 impl Expr {
@@ -67,6 +142,248 @@ impl Expr {
     }
 }
 
+impl Field {
+    pub fn next() -> Self {
+        Field(NEXT.to_string())
+    }
+
+    pub fn prev() -> Self {
+        Field(PREV.to_string())
+    }
+
+    pub fn left() -> Self {
+        Field(LEFT.to_string())
+    }
+
+    pub fn right() -> Self {
+        Field(RIGHT.to_string())
+    }
+}
+
+impl Record {
+    /// The record of `E1->E2`, whose only known field is `next`
+    pub fn next(value: Expr) -> Self {
+        Record(BTreeMap::from([(Field::next(), value)]))
+    }
+
+    /// The value of `next` if it is the only known field, i.e. the record of `E1->E2`
+    pub fn next_only(&self) -> Option<&Expr> {
+        match self.0.iter().next() {
+            Some((field, value)) if self.0.len() == 1 && field.0 == NEXT => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, field: &Field) -> Option<&Expr> {
+        self.0.get(field)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Expr> {
+        self.0.values()
+    }
+
+    /// Applies the function to the value of every field
+    pub fn map(&self, f: impl Fn(&Expr) -> Expr) -> Record {
+        Record(
+            self.0
+                .iter()
+                .map(|(field, value)| (field.clone(), f(value)))
+                .collect(),
+        )
+    }
+
+    /// Whether every field of `other` has the same value in this record
+    pub fn contains(&self, other: &Record) -> bool {
+        other
+            .0
+            .iter()
+            .all(|(field, value)| self.get(field) == Some(value))
+    }
+
+    /// Whether a cell described by this record may also be described by `other` for some values
+    /// of the existentials, since they and the fields this record does not know can be anything
+    pub fn may_contain(&self, other: &Record, existentials: &[Variable]) -> bool {
+        other
+            .0
+            .iter()
+            .all(|(field, value)| match (self.get(field), value) {
+                (None, _) => true,
+                (Some(_), Expr::Var(var)) if existentials.contains(var) => true,
+                (Some(known), _) => known == value,
+            })
+    }
+}
+
+/// The names of the built-in predicates, which cannot be redefined
+pub const BUILTIN_PREDICATES: [&str; 3] = ["ls", "dls", "tree"];
+
+impl Predicate {
+    /// Checks the restrictions of the supported definitions, except that the cell belongs to the
+    /// first parameter, which the parser ensures
+    pub fn check(&self) -> Result<(), String> {
+        if BUILTIN_PREDICATES.contains(&self.name.as_str()) {
+            return Err(format!("{} is a built-in predicate", self.name));
+        }
+        if self.params.is_empty() {
+            return Err(format!("{} needs a parameter", self.name));
+        }
+        let mut bound = BTreeSet::new();
+        if let Some(var) = (self.params.iter())
+            .chain(&self.existentials)
+            .find(|&var| !bound.insert(var))
+        {
+            return Err(format!("{} is bound twice in {}", var.0, self.name));
+        }
+        fn operands(ops: &[Op]) -> impl Iterator<Item = &Expr> {
+            ops.iter().flat_map(|op| match op {
+                Op::AtomEq(l, r) | Op::AtomNeq(l, r) => vec![l, r],
+            })
+        }
+        // The empty case can only refer to the parameters
+        let params: BTreeSet<_> = self.params.iter().collect();
+        let nonempty = (operands(&self.step))
+            .chain(&self.call)
+            .chain(self.record.values());
+        let unbound = (operands(&self.base).map(|expr| (expr, &params)))
+            .chain(nonempty.map(|expr| (expr, &bound)))
+            .find_map(|(expr, bound)| match expr {
+                Expr::Var(var) if !bound.contains(var) => Some(var),
+                _ => None,
+            });
+        if let Some(var) = unbound {
+            return Err(format!("{} is not bound in {}", var.0, self.name));
+        }
+        if self.call.len() != self.params.len() {
+            return Err(format!(
+                "{} expects {} arguments",
+                self.name,
+                self.params.len()
+            ));
+        }
+        let values: Vec<_> = self.record.values().collect();
+        if let Some(var) =
+            (self.existentials.iter()).find(|&var| !values.contains(&&Expr::Var(var.clone())))
+        {
+            return Err(format!("{} is not the value of a field", var.0));
+        }
+        let negated = |op: &Op| match op {
+            Op::AtomEq(l, r) => [
+                Op::AtomNeq(l.clone(), r.clone()),
+                Op::AtomNeq(r.clone(), l.clone()),
+            ],
+            Op::AtomNeq(l, r) => [
+                Op::AtomEq(l.clone(), r.clone()),
+                Op::AtomEq(r.clone(), l.clone()),
+            ],
+        };
+        if !(self.base.iter()).any(|op| negated(op).iter().any(|op| self.step.contains(op))) {
+            return Err(format!(
+                "the cases of {} have to exclude each other by an (in)equality",
+                self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replaces the parameters by the arguments and the existentials by the given expressions
+    fn instantiate<'a>(
+        &'a self,
+        args: &'a [Expr],
+        existentials: &'a [Expr],
+    ) -> impl Fn(&Expr) -> Expr + 'a {
+        move |expr| match expr {
+            Expr::Var(var) => (self.params.iter().zip(args))
+                .chain(self.existentials.iter().zip(existentials))
+                .find_map(|(bound, value)| {
+                    if bound == var {
+                        Some(value.clone())
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or_else(|| expr.clone()),
+            Expr::Nil => Expr::Nil,
+        }
+    }
+
+    /// The pure part of the empty case for the given arguments
+    pub fn base_case(&self, args: &[Expr]) -> Vec<Op> {
+        let instantiate = self.instantiate(args, &[]);
+        self.base.iter().map(|op| op.map(&instantiate)).collect()
+    }
+
+    /// The cell, the arguments of the recursive occurrence and the pure part of the nonempty case
+    /// for the given arguments and values of the existentials
+    pub fn step_case(&self, args: &[Expr], existentials: &[Expr]) -> (Record, Vec<Expr>, Vec<Op>) {
+        let instantiate = self.instantiate(args, existentials);
+        (
+            self.record.map(&instantiate),
+            self.call.iter().map(&instantiate).collect(),
+            self.step.iter().map(|op| op.map(&instantiate)).collect(),
+        )
+    }
+
+    /// The values of the existentials if the record has the form of the cell of the nonempty case
+    pub fn match_record(&self, args: &[Expr], record: &Record) -> Option<Vec<Expr>> {
+        let instantiate = self.instantiate(args, &[]);
+        let mut existentials: Vec<Option<&Expr>> = vec![None; self.existentials.len()];
+        for (field, pattern) in &self.record.0 {
+            let value = record.get(field)?;
+            match (self.existentials.iter()).position(|var| Expr::Var(var.clone()) == *pattern) {
+                Some(i) => match existentials[i] {
+                    Some(bound) if bound != value => return None,
+                    _ => existentials[i] = Some(value),
+                },
+                None if instantiate(pattern) != *value => return None,
+                None => {}
+            }
+        }
+        existentials
+            .into_iter()
+            .map(|value| value.cloned())
+            .collect()
+    }
+
+    /// The definition of the built-in `ls` linked by the field,
+    /// `ls(x,y) := emp & x=y | exists z. x->[field: z] * ls(z,y) & x!=y`
+    pub fn ls(field: &Field) -> Predicate {
+        let (x, y, z) = (Expr::new_var("x"), Expr::new_var("y"), Expr::new_var("z"));
+        Predicate {
+            name: "ls".to_string(),
+            params: vec![Variable("x".to_string()), Variable("y".to_string())],
+            base: vec![Op::AtomEq(x.clone(), y.clone())],
+            existentials: vec![Variable("z".to_string())],
+            record: Record(BTreeMap::from([(field.clone(), z.clone())])),
+            call: vec![z, y.clone()],
+            step: vec![Op::AtomNeq(x, y)],
+        }
+    }
+
+    /// The definition of the built-in `dls`,
+    /// `dls(x,y,p,n) := emp & x=n & y=p | exists u. x->[next: u, prev: p] * dls(u,y,x,n) & x!=n & y!=p`
+    pub fn dls() -> Predicate {
+        let [x, y, p, n, u] = ["x", "y", "p", "n", "u"].map(Expr::new_var);
+        Predicate {
+            name: "dls".to_string(),
+            params: ["x", "y", "p", "n"]
+                .iter()
+                .map(|name| Variable(name.to_string()))
+                .collect(),
+            base: vec![
+                Op::AtomEq(x.clone(), n.clone()),
+                Op::AtomEq(y.clone(), p.clone()),
+            ],
+            existentials: vec![Variable("u".to_string())],
+            record: Record(BTreeMap::from([
+                (Field::next(), u.clone()),
+                (Field::prev(), p.clone()),
+            ])),
+            call: vec![u, y.clone(), x.clone(), n.clone()],
+            step: vec![Op::AtomNeq(x, n), Op::AtomNeq(y, p)],
+        }
+    }
+}
+
 impl Formula {
     pub fn get_pure_vars(&self) -> Option<Vec<Variable>> {
         if let Pure::And(pure_vec) = &self.0 {
@@ -92,16 +409,7 @@ impl Formula {
         if let Spatial::SepConj(spatial_vec) = &self.1 {
             let mut var_vec: Vec<Variable> = Vec::with_capacity(spatial_vec.len());
             for atom in spatial_vec {
-                let (l, r) = match atom {
-                    AtomSpatial::LS(l, r) => (l, r),
-                    AtomSpatial::PointsTo(l, r) => (l, r),
-                };
-                if let Some(v) = l.get_var_opt() {
-                    var_vec.push(v);
-                }
-                if let Some(v) = r.get_var_opt() {
-                    var_vec.push(v);
-                }
+                var_vec.extend(atom.exprs().filter_map(Expr::get_var_opt));
             }
             return Some(var_vec);
         }
@@ -124,9 +432,131 @@ impl Formula {
         &self.1
     }
 
+    pub fn get_existentials(&self) -> &[Variable] {
+        &self.2
+    }
+
+    pub fn get_existentials_mut(&mut self) -> &mut Vec<Variable> {
+        &mut self.2
+    }
+
+    /// The pure and the spatial part, the existentials are dropped
     pub fn destroy(self) -> (Pure, Spatial) {
         (self.0, self.1)
     }
+
+    /// Applies the renaming to every variable of the formula
+    pub fn map_vars(&self, f: &dyn Fn(&Variable) -> Variable) -> Formula {
+        let mut formula = self.map_exprs(&|e| match e {
+            Expr::Var(v) => Expr::Var(f(v)),
+            Expr::Nil => Expr::Nil,
+        });
+        formula.2 = self.2.iter().map(f).collect();
+        formula
+    }
+
+    /// Applies the function to every expression of the formula, but not to the existentials
+    pub fn map_exprs(&self, expr: &dyn Fn(&Expr) -> Expr) -> Formula {
+        let pure = match &self.0 {
+            Pure::And(ops) => Pure::And(
+                ops.iter()
+                    .map(|op| match op {
+                        Op::AtomEq(l, r) => Op::AtomEq(expr(l), expr(r)),
+                        Op::AtomNeq(l, r) => Op::AtomNeq(expr(l), expr(r)),
+                    })
+                    .collect(),
+            ),
+            Pure::True => Pure::True,
+        };
+        let spatial = match &self.1 {
+            Spatial::SepConj(atoms) => Spatial::SepConj(
+                atoms
+                    .iter()
+                    .map(|atom| match atom {
+                        AtomSpatial::PointsTo(l, r) => AtomSpatial::PointsTo(expr(l), r.map(expr)),
+                        AtomSpatial::LS(field, l, r) => {
+                            AtomSpatial::LS(field.clone(), expr(l), expr(r))
+                        }
+                        AtomSpatial::DLS(a, b, c, d) => {
+                            AtomSpatial::DLS(expr(a), expr(b), expr(c), expr(d))
+                        }
+                        AtomSpatial::Tree(root) => AtomSpatial::Tree(expr(root)),
+                        AtomSpatial::Pred(definition, args) => {
+                            AtomSpatial::Pred(definition.clone(), args.iter().map(expr).collect())
+                        }
+                    })
+                    .collect(),
+            ),
+            Spatial::Emp => Spatial::Emp,
+        };
+        Formula(pure, spatial, self.2.clone())
+    }
+
+    pub fn is_existential(&self, expr: &Expr) -> bool {
+        matches!(expr, Expr::Var(var) if self.2.contains(var))
+    }
+
+    /// Replaces the existential by its witness, which the formula then no longer quantifies
+    pub fn instantiate(&self, var: &Variable, witness: &Expr) -> Formula {
+        let mut formula = self.map_exprs(&|e| match e {
+            Expr::Var(v) if v == var => witness.clone(),
+            _ => e.clone(),
+        });
+        formula.2.retain(|v| v != var);
+        formula
+    }
+
+    /// The atoms in a fixed order: operands of (dis)equalities and atoms are ordered
+    /// first by their shape (ignoring variable names) and then by the names.
+    /// Duplicate pure atoms are removed, duplicate spatial atoms have to stay.
+    fn sorted_atoms(&self) -> (Vec<Op>, Vec<AtomSpatial>) {
+        let shape = |e: &Expr| matches!(e, Expr::Var(_));
+        let expr_key = |e: &Expr| (shape(e), e.clone());
+        let orient = |l: &Expr, r: &Expr| {
+            if expr_key(l) <= expr_key(r) {
+                (l.clone(), r.clone())
+            } else {
+                (r.clone(), l.clone())
+            }
+        };
+        let mut ops: Vec<Op> = match &self.0 {
+            Pure::And(ops) => ops
+                .iter()
+                .map(|op| match op {
+                    Op::AtomEq(l, r) => {
+                        let (l, r) = orient(l, r);
+                        Op::AtomEq(l, r)
+                    }
+                    Op::AtomNeq(l, r) => {
+                        let (l, r) = orient(l, r);
+                        Op::AtomNeq(l, r)
+                    }
+                })
+                .collect(),
+            Pure::True => Vec::new(),
+        };
+        ops.sort_by_key(|op| match op {
+            Op::AtomEq(l, r) => (false, shape(l), shape(r), op.clone()),
+            Op::AtomNeq(l, r) => (true, shape(l), shape(r), op.clone()),
+        });
+        ops.dedup();
+        let mut atoms = match &self.1 {
+            Spatial::SepConj(atoms) => atoms.clone(),
+            Spatial::Emp => Vec::new(),
+        };
+        atoms.sort_by_key(|atom| {
+            let shapes: Vec<bool> = atom.exprs().map(shape).collect();
+            (
+                atom.is_ls(),
+                atom.is_dls(),
+                atom.is_tree(),
+                atom.is_pred(),
+                shapes,
+                atom.clone(),
+            )
+        });
+        (ops, atoms)
+    }
 }
 
 impl Entailment {
@@ -134,10 +564,122 @@ impl Entailment {
         (self.antecedent, self.consequent)
     }
 
+    /// All free variables of the entailment, sorted and without duplicates
+    pub fn get_vars(&self) -> Vec<Variable> {
+        let mut vars = BTreeSet::new();
+        for formula in &[&self.antecedent, &self.consequent] {
+            if let Some(pure_vars) = formula.get_pure_vars() {
+                vars.extend(pure_vars);
+            }
+            if let Some(spatial_vars) = formula.get_spatial_vars() {
+                vars.extend(spatial_vars);
+            }
+        }
+        for var in self.consequent.get_existentials() {
+            vars.remove(var);
+        }
+        vars.into_iter().collect()
+    }
+
+    /// The free and the bound variables of the entailment
+    fn used_vars(&self) -> Vec<Variable> {
+        let mut vars = self.get_vars();
+        vars.extend(self.consequent.get_existentials().iter().cloned());
+        vars
+    }
+
+    /// A variable named after `base` that does not occur anywhere in the entailment, not even bound.
+    /// Fresh variables live in their own namespace (see [`FRESH_SEPARATOR`]) and are numbered
+    /// per base name, so that repeated rule applications yield `x'1`, `x'2`, ...
+    pub fn fresh_var(&self, base: &Variable) -> Variable {
+        let root = base.0.split(FRESH_SEPARATOR).next().unwrap_or_default();
+        let vars = self.used_vars();
+        (1..)
+            .map(|i| Variable(format!("{}{}{}", root, FRESH_SEPARATOR, i)))
+            .find(|var| !vars.contains(var))
+            .unwrap()
+    }
+
+    /// `n` variables that do not occur in the entailment, named after `base`
+    pub fn fresh_vars(&self, base: &Variable, n: usize) -> Vec<Variable> {
+        let root = base.0.split(FRESH_SEPARATOR).next().unwrap_or_default();
+        let vars = self.used_vars();
+        (1..)
+            .map(|i| Variable(format!("{}{}{}", root, FRESH_SEPARATOR, i)))
+            .filter(|var| !vars.contains(var))
+            .take(n)
+            .collect()
+    }
+
+    /// The user-defined predicates occurring in the entailment, sorted by name
+    pub fn predicates(&self) -> Vec<&Predicate> {
+        let mut predicates = BTreeMap::new();
+        for formula in &[&self.antecedent, &self.consequent] {
+            if let Spatial::SepConj(atoms) = formula.get_spatial() {
+                for atom in atoms {
+                    if let AtomSpatial::Pred(definition, _) = atom {
+                        predicates.insert(&definition.name, definition.as_ref());
+                    }
+                }
+            }
+        }
+        predicates.into_values().collect()
+    }
+
+    /// Applies the renaming to every variable of the entailment
+    pub fn map_vars(&self, f: &dyn Fn(&Variable) -> Variable) -> Entailment {
+        Entailment {
+            antecedent: self.antecedent.map_vars(f),
+            consequent: self.consequent.map_vars(f),
+        }
+    }
+
+    /// A representative of the entailments that only differ in the order of their atoms,
+    /// the orientation of (dis)equalities, duplicate pure atoms, True/Emp versus empty vectors,
+    /// the order of the existentials and the names of the variables. All of these have the same validity.
+    /// Some of these entailments may still get different representatives, since ties between
+    /// atoms of the same shape are broken by the original names.
+    pub fn canonical(&self) -> Entailment {
+        // Variables are named in the order of their first occurrence after sorting by shape,
+        // spatial atoms first, since they tell variables apart better than (dis)equalities
+        let mut order: Vec<Variable> = Vec::new();
+        for formula in &[&self.antecedent, &self.consequent] {
+            let (ops, atoms) = formula.sorted_atoms();
+            let operands = ops.iter().flat_map(|op| match op {
+                Op::AtomEq(l, r) | Op::AtomNeq(l, r) => vec![l, r],
+            });
+            let args = atoms.iter().flat_map(AtomSpatial::exprs);
+            for var in args.chain(operands).filter_map(Expr::get_var_opt) {
+                if !order.contains(&var) {
+                    order.push(var);
+                }
+            }
+        }
+        let rename = |var: &Variable| {
+            let index = order.iter().position(|v| v == var).unwrap_or(order.len());
+            Variable(format!("v{}", index))
+        };
+        let sorted = |formula: &Formula| {
+            let renamed = formula.map_vars(&rename);
+            let (ops, atoms) = renamed.sorted_atoms();
+            let mut existentials = renamed.2;
+            existentials.sort();
+            existentials.dedup();
+            Formula(Pure::And(ops), Spatial::SepConj(atoms), existentials)
+        };
+        Entailment {
+            antecedent: sorted(&self.antecedent),
+            consequent: sorted(&self.consequent),
+        }
+    }
+
     pub fn is_normal_form(&self) -> bool {
         if let Spatial::SepConj(vec) = self.antecedent.get_spatial() {
-            if vec.iter().any(|x: &AtomSpatial| x.is_ls()) {
-                return false; //There are no LS allowed for normal form
+            if vec
+                .iter()
+                .any(|x: &AtomSpatial| !x.is_points_to() && !x.is_tree())
+            {
+                return false; //There are no LS or DLS allowed for normal form, trees are opaque
             }
         }
 
@@ -148,13 +690,16 @@ impl Entailment {
         if let Some(ref mut vec) = self.antecedent.get_spatial_vars() {
             vars.append(vec);
         }
+        vars.sort();
+        vars.dedup();
+        let is_var = |expr: &Expr, var: &Variable| matches!(expr, Expr::Var(v) if v == var);
 
         if let Pure::And(pures) = self.antecedent.get_pure() {
             'outer: for o_var in vars.as_slice() {
                 let found_inequality = pures.iter().any(|x| {
                     if let Op::AtomNeq(l, r) = x {
-                        (*l == Expr::Var(o_var.clone()) && *r == Expr::Nil)
-                            || (*r == Expr::Var(o_var.clone()) && *l == Expr::Nil)
+                        (is_var(l, o_var) && *r == Expr::Nil)
+                            || (is_var(r, o_var) && *l == Expr::Nil)
                     } else {
                         false //There are no AtomEqs allowed for normal form
                     }
@@ -168,9 +713,8 @@ impl Entailment {
                     }
                     let found_inequality = pures.iter().any(|x| {
                         if let Op::AtomNeq(l, r) = x {
-                            (*l == Expr::Var(o_var.clone()) && *r == Expr::Var(i_var.clone()))
-                                || (*r == Expr::Var(o_var.clone())
-                                    && *l == Expr::Var(i_var.clone()))
+                            (is_var(l, o_var) && is_var(r, i_var))
+                                || (is_var(r, o_var) && is_var(l, i_var))
                         } else {
                             false //There are no AtomEqs allowed for normal form
                         }
@@ -192,11 +736,69 @@ impl AtomSpatial {
     }
 
     pub fn is_ls(&self) -> bool {
-        matches!(self, AtomSpatial::LS(_, _))
+        matches!(self, AtomSpatial::LS(_, _, _))
+    }
+
+    pub fn is_dls(&self) -> bool {
+        matches!(self, AtomSpatial::DLS(_, _, _, _))
+    }
+
+    pub fn is_tree(&self) -> bool {
+        matches!(self, AtomSpatial::Tree(_))
+    }
+
+    pub fn is_pred(&self) -> bool {
+        matches!(self, AtomSpatial::Pred(_, _))
+    }
+
+    /// Whether the atom is an ls, a dls or an occurrence of a user-defined predicate
+    pub fn is_inductive(&self) -> bool {
+        self.is_ls() || self.is_dls() || self.is_pred()
+    }
+
+    /// The definition and the arguments of an inductive atom, where ls and dls have the built-in
+    /// definitions [`Predicate::ls`] and [`Predicate::dls`]
+    pub fn inductive(&self) -> Option<(Arc<Predicate>, Vec<Expr>)> {
+        match self {
+            AtomSpatial::LS(field, l, r) => {
+                Some((Arc::new(Predicate::ls(field)), vec![l.clone(), r.clone()]))
+            }
+            AtomSpatial::DLS(a, b, c, d) => Some((
+                Arc::new(Predicate::dls()),
+                vec![a.clone(), b.clone(), c.clone(), d.clone()],
+            )),
+            AtomSpatial::Pred(definition, args) => Some((definition.clone(), args.clone())),
+            _ => None,
+        }
+    }
+
+    /// The occurrence of the definition with the arguments, the inverse of [`AtomSpatial::inductive`].
+    /// User-defined predicates cannot be named like the built-in ones.
+    pub fn instance(definition: &Arc<Predicate>, args: Vec<Expr>) -> AtomSpatial {
+        let field = definition.record.0.keys().next();
+        match (definition.name.as_str(), field, args.as_slice()) {
+            ("ls", Some(field), [l, r]) => AtomSpatial::LS(field.clone(), l.clone(), r.clone()),
+            ("dls", _, [a, b, c, d]) => {
+                AtomSpatial::DLS(a.clone(), b.clone(), c.clone(), d.clone())
+            }
+            _ => AtomSpatial::Pred(definition.clone(), args),
+        }
+    }
+
+    /// The start of the atom followed by its other arguments
+    pub fn exprs(&self) -> Box<dyn Iterator<Item = &Expr> + '_> {
+        match self {
+            AtomSpatial::PointsTo(l, r) => Box::new(std::iter::once(l).chain(r.values())),
+            AtomSpatial::LS(_, l, r) => Box::new(vec![l, r].into_iter()),
+            AtomSpatial::DLS(a, b, c, d) => Box::new(vec![a, b, c, d].into_iter()),
+            AtomSpatial::Tree(root) => Box::new(std::iter::once(root)),
+            AtomSpatial::Pred(_, args) => Box::new(args.iter()),
+        }
     }
 }
 
 impl Spatial {
+    #[allow(clippy::should_implement_trait)]
     pub fn add(mut self, new: AtomSpatial) -> Self {
         match &mut self {
             Spatial::SepConj(vec) => {
@@ -214,25 +816,91 @@ mod test {
         AtomSpatial::{PointsTo, LS},
         Entailment, Expr,
         Expr::Nil,
-        Formula,
+        Field, Formula,
         Pure::{And, True},
+        Record,
         Spatial::{Emp, SepConj},
+        Variable,
     };
+    use crate::parser::parse;
+
+    #[test]
+    fn test_fresh_var() {
+        let goal = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![
+                    LS(Field::next(), Expr::new_var("x"), Nil),
+                    PointsTo(Expr::new_var("x'1"), Record::next(Expr::new_var("xx"))),
+                ]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![LS(Field::next(), Expr::new_var("y"), Nil)]),
+                Vec::new(),
+            ),
+        };
+        let var = |name: &str| Variable(name.to_string());
+        assert_eq!(var("x'2"), goal.fresh_var(&var("x")));
+        assert_eq!(var("x'2"), goal.fresh_var(&var("x'1")));
+        assert_eq!(var("y'1"), goal.fresh_var(&var("y")));
+        assert_eq!(
+            vec![var("x"), var("x'1"), var("xx"), var("y")],
+            goal.get_vars()
+        );
+    }
+
+    #[test]
+    fn test_canonical() -> Result<(), crate::error::Error> {
+        let canonical = |input: &str| parse(input).map(|goal| goal.canonical());
+        let goal =
+            canonical("And[Neq(x,Nil),Eq(y,x)]|SepConj[ls(y,z),x->y] |- True|SepConj[ls(x,z)]")?;
+        assert_eq!(
+            "And[Eq(v0,v1),Neq(Nil,v0)]|SepConj[v0->v1,ls(v1,v2)] |- And[]|SepConj[ls(v0,v2)]",
+            goal.to_string()
+        );
+        assert_eq!(
+            goal,
+            canonical(
+                "And[Eq(a,b),Neq(b,Nil),Neq(Nil,b)]|SepConj[b->a,ls(a,c)] |- True|SepConj[ls(b,c)]"
+            )?
+        );
+        assert_ne!(
+            goal,
+            canonical("And[Neq(x,Nil),Eq(y,x)]|SepConj[ls(y,z),x->y] |- True|SepConj[ls(y,z)]")?
+        );
+        // x->nil * x->nil is unsatisfiable, unlike x->nil
+        assert_ne!(
+            canonical("True|SepConj[x->Nil,x->Nil] |- True|Emp")?,
+            canonical("True|SepConj[x->Nil] |- True|Emp")?
+        );
+        assert_eq!(
+            canonical("True|Emp |- True|Emp")?,
+            canonical("And[]|SepConj[] |- And[]|SepConj[]")?
+        );
+        Ok(())
+    }
 
     #[test]
     fn test_is_nomal_form() {
         let not_normal1 = Entailment {
-            antecedent: Formula(True, SepConj(vec![LS(Expr::new_var("x"), Nil)])),
-            consequent: Formula(True, Emp),
+            antecedent: Formula(
+                True,
+                SepConj(vec![LS(Field::next(), Expr::new_var("x"), Nil)]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, Emp, Vec::new()),
         };
-        assert_eq!(false, not_normal1.is_normal_form());
+        assert!(!not_normal1.is_normal_form());
 
         let normal1 = Entailment {
             antecedent: Formula(
                 And(vec![super::Op::AtomNeq(Expr::new_var("x"), Nil)]),
-                SepConj(vec![PointsTo(Expr::new_var("x"), Nil)]),
+                SepConj(vec![PointsTo(Expr::new_var("x"), Record::next(Nil))]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
         assert!(normal1.is_normal_form());
     }
@@ -0,0 +1,60 @@
+use crate::datastructures::Entailment;
+use crate::limits::Limit;
+use std::fmt;
+
+/// Everything that can go wrong while reading or deciding an entailment
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Error {
+    /// The input is not a well-formed entailment
+    Parse {
+        line: i32,
+        column: i32,
+        message: String,
+    },
+    /// No rule could discharge the contained goal
+    Invalid(Box<Entailment>),
+    /// The proof search was stopped before it could decide the entailment
+    ResourceLimit(Limit),
+    /// The proof search got stuck on the contained goal, e.g. since no tried witness instantiates
+    /// its existentials, but found no countermodel either
+    Undecided(Box<Entailment>),
+    /// The decision procedure itself misbehaved, e.g. a rule made no progress
+    Internal(String),
+    /// The chosen decision procedure does not support the input
+    Unsupported(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Parse {
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Could not parse input at line {}, column {}: {}",
+                line, column, message
+            ),
+            Error::Invalid(goal) => write!(
+                f,
+                "Entailment is invalid! No rule applies to the goal {}",
+                goal
+            ),
+            Error::ResourceLimit(limit) => write!(
+                f,
+                "Resource limit exceeded! The proof search needed {}",
+                limit
+            ),
+            Error::Undecided(goal) => write!(
+                f,
+                "Entailment is undecided! The proof search got stuck on the goal {}, but found no countermodel either",
+                goal
+            ),
+            Error::Internal(message) => write!(f, "Internal error: {}", message),
+            Error::Unsupported(message) => write!(f, "Unsupported input: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
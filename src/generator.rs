@@ -0,0 +1,315 @@
+use crate::datastructures::{
+    AtomSpatial,
+    AtomSpatial::{PointsTo, Tree, DLS, LS},
+    Entailment, Expr,
+    Expr::Nil,
+    Field, Formula, Op,
+    Op::{AtomEq, AtomNeq},
+    Pure::{And, True},
+    Record,
+    Spatial::{Emp, SepConj},
+    Variable,
+};
+
+/// Upper bounds for the entailments produced by a [`Generator`].
+/// Every formula gets between zero and the given number of atoms of each kind.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct GeneratorConfig {
+    pub variables: usize,
+    pub points_to: usize,
+    pub segments: usize,
+    pub pure: usize,
+    /// The number of field names, the first one is `next`. With more than one, records get a
+    /// random subset of the fields and list segments a random link field.
+    pub fields: usize,
+    /// The maximum number of doubly linked segments. With any, records also get a `prev` field.
+    pub dls: usize,
+    /// The maximum number of trees. With any, records also get the fields `left` and `right`.
+    pub trees: usize,
+    /// The maximum number of existentials of the consequent. Each binds the occurrences of a
+    /// random variable in the consequent under a name of its own.
+    pub existentials: usize,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            variables: 3,
+            points_to: 2,
+            segments: 2,
+            pure: 2,
+            fields: 1,
+            dls: 0,
+            trees: 0,
+            existentials: 0,
+        }
+    }
+}
+
+/// Deterministic generator of well-formed random entailments, e.g. for property based tests
+pub struct Generator {
+    config: GeneratorConfig,
+    state: u64,
+}
+
+impl Generator {
+    pub fn new(config: GeneratorConfig, seed: u64) -> Self {
+        Generator {
+            config,
+            // xorshift gets stuck on zero
+            state: seed | 1,
+        }
+    }
+
+    /// xorshift64, good enough to generate test inputs
+    fn below(&mut self, n: usize) -> usize {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state % n as u64) as usize
+    }
+
+    /// The name of the i-th variable: a, b, ..., z, aa, ab, ...
+    pub fn variable_name(mut i: usize) -> String {
+        let mut name = Vec::new();
+        loop {
+            name.push((b'a' + (i % 26) as u8) as char);
+            if i < 26 {
+                break;
+            }
+            i = i / 26 - 1;
+        }
+        name.into_iter().rev().collect()
+    }
+
+    pub fn expr(&mut self) -> Expr {
+        match self.below(self.config.variables + 1) {
+            0 => Nil,
+            i => Expr::new_var(&Self::variable_name(i - 1)),
+        }
+    }
+
+    /// The name of the i-th field: next, fa, fb, ...
+    pub fn field_name(i: usize) -> String {
+        match i {
+            0 => Field::next().0,
+            i => format!("f{}", Self::variable_name(i - 1)),
+        }
+    }
+
+    pub fn field(&mut self) -> Field {
+        match self.config.fields {
+            0 | 1 => Field::next(),
+            n => Field(Self::field_name(self.below(n))),
+        }
+    }
+
+    pub fn record(&mut self) -> Record {
+        let mut record = if self.config.fields <= 1 {
+            Record::next(self.expr())
+        } else {
+            let mut record = Record::default();
+            for i in 0..self.config.fields {
+                if self.below(2) == 0 {
+                    record.0.insert(Field(Self::field_name(i)), self.expr());
+                }
+            }
+            record
+        };
+        if self.config.dls > 0 {
+            record.0.insert(Field::prev(), self.expr());
+        }
+        if self.config.trees > 0 {
+            record.0.insert(Field::left(), self.expr());
+            record.0.insert(Field::right(), self.expr());
+        }
+        record
+    }
+
+    pub fn formula(&mut self) -> Formula {
+        let pure_count = self.below(self.config.pure + 1);
+        let ops: Vec<Op> = (0..pure_count)
+            .map(|_| match self.below(2) {
+                0 => AtomEq(self.expr(), self.expr()),
+                _ => AtomNeq(self.expr(), self.expr()),
+            })
+            .collect();
+
+        let points_to_count = self.below(self.config.points_to + 1);
+        let segment_count = self.below(self.config.segments + 1);
+        let dls_count = match self.config.dls {
+            0 => 0,
+            n => self.below(n + 1),
+        };
+        let tree_count = match self.config.trees {
+            0 => 0,
+            n => self.below(n + 1),
+        };
+        let mut atoms: Vec<AtomSpatial> =
+            Vec::with_capacity(points_to_count + segment_count + dls_count + tree_count);
+        for _ in 0..points_to_count {
+            atoms.push(PointsTo(self.expr(), self.record()));
+        }
+        for _ in 0..segment_count {
+            atoms.push(LS(self.field(), self.expr(), self.expr()));
+        }
+        for _ in 0..dls_count {
+            atoms.push(DLS(self.expr(), self.expr(), self.expr(), self.expr()));
+        }
+        for _ in 0..tree_count {
+            atoms.push(Tree(self.expr()));
+        }
+        // Mix points-to facts and list segments
+        for i in (1..atoms.len()).rev() {
+            let j = self.below(i + 1);
+            atoms.swap(i, j);
+        }
+
+        Formula(
+            if ops.is_empty() { True } else { And(ops) },
+            if atoms.is_empty() {
+                Emp
+            } else {
+                SepConj(atoms)
+            },
+            Vec::new(),
+        )
+    }
+
+    pub fn entailment(&mut self) -> Entailment {
+        let antecedent = self.formula();
+        let mut consequent = self.formula();
+        if self.config.existentials > 0 {
+            for i in 0..self.below(self.config.existentials + 1) {
+                let free = Variable(Self::variable_name(self.below(self.config.variables)));
+                let bound = Variable(Self::variable_name(self.config.variables + i));
+                consequent = consequent.map_vars(&|var| {
+                    if *var == free {
+                        bound.clone()
+                    } else {
+                        var.clone()
+                    }
+                });
+                consequent.2.push(bound);
+            }
+        }
+        Entailment {
+            antecedent,
+            consequent,
+        }
+    }
+}
+
+impl Iterator for Generator {
+    type Item = Entailment;
+
+    fn next(&mut self) -> Option<Entailment> {
+        Some(self.entailment())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Generator, GeneratorConfig};
+    use crate::datastructures::{
+        AtomSpatial::LS, Entailment, Field, Op::AtomEq, Pure::And, Variable,
+    };
+    use crate::parser::parse;
+    use crate::prover::{prove, Verdict};
+
+    fn is_valid(goal: &Entailment) -> bool {
+        match prove(goal) {
+            Ok(Verdict::Valid(_)) => true,
+            Ok(Verdict::Invalid(_, _)) => false,
+            other => panic!("Could not decide {:?}: {:?}", goal, other),
+        }
+    }
+
+    #[test]
+    fn test_variable_name() {
+        assert_eq!("a", Generator::variable_name(0));
+        assert_eq!("z", Generator::variable_name(25));
+        assert_eq!("aa", Generator::variable_name(26));
+        assert_eq!("ba", Generator::variable_name(52));
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let first: Vec<Entailment> = Generator::new(GeneratorConfig::default(), 42)
+            .take(10)
+            .collect();
+        let second: Vec<Entailment> = Generator::new(GeneratorConfig::default(), 42)
+            .take(10)
+            .collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_renaming() {
+        let config = GeneratorConfig::default();
+        let variables = config.variables;
+        let permute = move |name: &str| {
+            let index = (0..variables)
+                .find(|&i| Generator::variable_name(i) == name)
+                .unwrap();
+            Generator::variable_name(variables - 1 - index)
+        };
+        let prefix = |name: &str| format!("renamed{}", name);
+
+        for goal in Generator::new(config, 7).take(300) {
+            let expected = is_valid(&goal);
+            assert_eq!(
+                expected,
+                is_valid(&goal.map_vars(&|var| Variable(permute(&var.0)))),
+                "{:?}",
+                goal
+            );
+            assert_eq!(
+                expected,
+                is_valid(&goal.map_vars(&|var| Variable(prefix(&var.0)))),
+                "{:?}",
+                goal
+            );
+        }
+    }
+
+    #[test]
+    fn test_emp_neutral_atoms() {
+        let mut generator = Generator::new(GeneratorConfig::default(), 11);
+        for _ in 0..300 {
+            let goal = generator.entailment();
+            let expected = is_valid(&goal);
+
+            let neutral = generator.expr();
+            let (mut antecedent, mut consequent) = goal.clone().destroy();
+            antecedent.1 = antecedent
+                .1
+                .add(LS(Field::next(), neutral.clone(), neutral.clone()));
+            consequent.1 = consequent
+                .1
+                .add(LS(Field::next(), neutral.clone(), neutral.clone()));
+            match consequent.get_pure_mut() {
+                And(ops) => ops.push(AtomEq(neutral.clone(), neutral)),
+                pure => *pure = And(vec![AtomEq(neutral.clone(), neutral)]),
+            }
+            let extended = Entailment {
+                antecedent,
+                consequent,
+            };
+            assert_eq!(expected, is_valid(&extended), "{:?}", goal);
+        }
+    }
+
+    #[test]
+    fn test_print_parse() {
+        let config = GeneratorConfig {
+            variables: 4,
+            ..GeneratorConfig::default()
+        };
+        for goal in Generator::new(config, 23).take(300) {
+            let reparsed = parse(&goal.to_string()).unwrap();
+            assert_eq!(goal, reparsed);
+            assert_eq!(is_valid(&goal), is_valid(&reparsed));
+        }
+    }
+}
@@ -0,0 +1,614 @@
+//! A graph based decision procedure following "Tractable Reasoning in a Fragment of Separation
+//! Logic" by Cook, Haase, Ouaknine, Parkinson and Worrell. It implements the steps of the paper in
+//! its own way, so the arguments that they are correct and take polynomial time are sketched here.
+//!
+//! The nodes of a formula are nil and its variables, its edges the spatial atoms. A model merges
+//! the nodes into parts, one per location, such that the equalities hold and the disequalities
+//! separate parts. A list segment is empty iff it stays inside a part, so it is determined by the
+//! parts. The model exists iff no part is left by two nonempty segments or by a nonempty segment
+//! and a cell, no cell is nil and no two cells are in the same part.
+//!
+//! Satisfiability: Nil and the cells are the anchors, they need parts of their own, which no
+//! segment leaves. Splitting a part whose nodes are not connected by its empty segments keeps a
+//! model, so it suffices to look for models whose parts are connected. Then every connected
+//! component of the segments has its own parts, which are left by at most one segment each, so
+//! they form a tree towards a single root part that no segment leaves, or a single cycle if there
+//! is no such part. The root part contains the anchor, if there is one, and there cannot be two.
+//! Otherwise every node is tried as the root, and every segment as the one leaving its part on the
+//! cycle, which then is the root of the others. Given the root, [`forced`] finds merges that every
+//! model has to make: the root's part has no segment leaving it, there are no cycles, and a part is
+//! left by only one segment. Merging until nothing is forced leaves the finest partition of the
+//! remaining choices, which is a model iff it respects the disequalities, since every other model
+//! merges more. Every choice takes at most one merge per node, so this takes polynomial time.
+//!
+//! Entailment: the antecedent implies an equality iff it is unsatisfiable together with the
+//! disequality, and a disequality iff it is unsatisfiable together with the equality. Modulo the
+//! implied equalities, every points-to atom of the consequent has to be a cell of the antecedent
+//! and every list segment a path of segments and cells of the antecedent that never passes its end.
+//! Together the atoms have to use every edge that is not necessarily empty exactly once. A path
+//! takes the edge to its end if it can: if another edge leaving the same node were nonempty, the
+//! direct one would be empty, so the path would start the other edge at its end. Otherwise it takes
+//! an edge from which its end can still be reached, without ever going back. That the choice
+//! between several such edges does not matter is not proven here; `test_cross_check` compares the
+//! procedure with the rules and with the reference decider.
+use crate::congruence::Congruence;
+use crate::datastructures::{
+    AtomSpatial::{PointsTo, Pred, Tree, DLS, LS},
+    Entailment, Expr, Field, Formula,
+    Op::{AtomEq, AtomNeq},
+    Pure::And,
+    Spatial::SepConj,
+    Variable,
+};
+use crate::error::Error;
+use crate::proof::ProofTree;
+use crate::prover::Verdict;
+use std::collections::{HashMap, HashSet};
+
+/// The rule of the single node of the proofs found by the graph based procedure
+pub const GRAPH: &str = "Graph";
+
+/// Nil is the first node of every graph, the variables follow in sorted order
+const NIL: usize = 0;
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+enum Kind {
+    /// A cell and the nodes some of its fields point to
+    PointsTo(Vec<(Field, usize)>),
+    /// A list segment along the field to the node
+    Segment(Field, usize),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct Edge {
+    kind: Kind,
+    from: usize,
+}
+
+impl Edge {
+    fn is_points_to(&self) -> bool {
+        matches!(self.kind, Kind::PointsTo(_))
+    }
+
+    /// The end of a segment
+    fn to(&self) -> Option<usize> {
+        match self.kind {
+            Kind::PointsTo(_) => None,
+            Kind::Segment(_, to) => Some(to),
+        }
+    }
+
+    /// The node reached in one step along the field, which only segments may take zero times
+    fn follow(&self, field: &Field) -> Option<usize> {
+        match &self.kind {
+            Kind::PointsTo(fields) => fields.iter().find(|(f, _)| f == field).map(|&(_, to)| to),
+            Kind::Segment(f, to) if f == field => Some(*to),
+            Kind::Segment(_, _) => None,
+        }
+    }
+}
+
+/// A formula as a graph whose nodes are nil and the variables and whose edges are the spatial atoms
+#[derive(Debug, Clone)]
+struct Graph {
+    /// The expression of every node
+    nodes: Vec<Expr>,
+    equal: Vec<(usize, usize)>,
+    distinct: Vec<(usize, usize)>,
+    edges: Vec<Edge>,
+}
+
+/// The smallest node equal to every node modulo the equalities, or None if they contradict one of
+/// the disequalities
+fn classes(
+    nodes: &[Expr],
+    equal: &[(usize, usize)],
+    distinct: &[(usize, usize)],
+) -> Option<Vec<usize>> {
+    let expr = |node: usize| nodes[node].clone();
+    let equalities = equal.iter().map(|&(a, b)| AtomEq(expr(a), expr(b)));
+    let disequalities = distinct.iter().map(|&(a, b)| AtomNeq(expr(a), expr(b)));
+    let facts = Congruence::new(&And(equalities.chain(disequalities).collect()));
+    if facts.is_contradictory() {
+        return None;
+    }
+    // The representative of a class is its smallest expression, and the nodes are sorted
+    let node = |expr: Expr| nodes.binary_search(&expr).expect("Unknown expression");
+    Some(
+        nodes
+            .iter()
+            .map(|expr| node(facts.representative(expr)))
+            .collect(),
+    )
+}
+
+/// The first merge of two nodes that every partition of a connected graph has to make if the part
+/// of the root has no edge leaving it and every other part at most one. Apart from the root's,
+/// every part is then left by exactly one edge, and following these edges ends in the root's part.
+fn forced(root: usize, edges: &[(usize, usize)]) -> Option<(usize, usize)> {
+    let mut successors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for &(from, to) in edges {
+        successors.entry(from).or_default().push(to);
+    }
+    let reaches = |from: usize, to: usize| {
+        let mut seen = HashSet::from([from]);
+        let mut stack = vec![from];
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return true;
+            }
+            for &next in successors.get(&node).into_iter().flatten() {
+                if seen.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        false
+    };
+
+    // The root's part contains the ends of its edges
+    if let Some(&(from, to)) = edges.iter().find(|&&(from, _)| from == root) {
+        return Some((from, to));
+    }
+    // There are no cycles
+    if let Some(&edge) = edges.iter().find(|&&(from, to)| reaches(to, from)) {
+        return Some(edge);
+    }
+    for (&from, targets) in &successors {
+        for (i, &to) in targets.iter().enumerate() {
+            for (j, &other) in targets.iter().enumerate() {
+                // If the part of from were left along the edge to to, it would contain the other
+                // end as well. So to would reach the part in a cycle, or the other end would reach
+                // the root, which would then join the part.
+                if i != j && (reaches(to, other) || reaches(other, root)) {
+                    return Some((from, to));
+                }
+            }
+        }
+    }
+    None
+}
+
+impl Graph {
+    fn new(formula: &Formula, vars: &[Variable]) -> Self {
+        let node = |expr: &Expr| match expr {
+            Expr::Nil => NIL,
+            Expr::Var(var) => 1 + vars.binary_search(var).expect("Unknown variable"),
+        };
+        let mut graph = Graph {
+            nodes: std::iter::once(Expr::Nil)
+                .chain(vars.iter().cloned().map(Expr::Var))
+                .collect(),
+            equal: Vec::new(),
+            distinct: Vec::new(),
+            edges: Vec::new(),
+        };
+        if let And(ops) = formula.get_pure() {
+            for op in ops {
+                match op {
+                    AtomEq(l, r) => graph.equal.push((node(l), node(r))),
+                    AtomNeq(l, r) => graph.distinct.push((node(l), node(r))),
+                }
+            }
+        }
+        if let SepConj(atoms) = formula.get_spatial() {
+            for atom in atoms {
+                let (kind, l) = match atom {
+                    PointsTo(l, r) => {
+                        let fields = r.0.iter().map(|(f, e)| (f.clone(), node(e)));
+                        (Kind::PointsTo(fields.collect()), l)
+                    }
+                    LS(f, l, r) => (Kind::Segment(f.clone(), node(r)), l),
+                    DLS(..) | Tree(_) | Pred(..) => {
+                        unreachable!("The graph is only built for points-to facts and ls")
+                    }
+                };
+                graph.edges.push(Edge {
+                    kind,
+                    from: node(l),
+                });
+            }
+        }
+        graph
+    }
+
+    fn with_equal(&self, a: usize, b: usize) -> Self {
+        let mut graph = self.clone();
+        graph.equal.push((a, b));
+        graph
+    }
+
+    fn with_distinct(&self, a: usize, b: usize) -> Self {
+        let mut graph = self.clone();
+        graph.distinct.push((a, b));
+        graph
+    }
+
+    /// The graph together with a cell at the node, which is hence neither nil nor allocated by the graph
+    fn with_cell(&self, node: usize) -> Self {
+        let mut graph = self.clone();
+        graph.edges.push(Edge {
+            kind: Kind::PointsTo(Vec::new()),
+            from: node,
+        });
+        graph
+    }
+
+    /// The smallest node equal to every node modulo the equalities of the graph and the given ones,
+    /// or None if they contradict its disequalities
+    fn classes(&self, equal: &[(usize, usize)]) -> Option<Vec<usize>> {
+        let equal: Vec<(usize, usize)> = self.equal.iter().chain(equal).copied().collect();
+        classes(&self.nodes, &equal, &self.distinct)
+    }
+
+    /// Fixes the root of every connected component of the segments, see the module documentation
+    fn satisfiable(&self) -> bool {
+        let Some(class) = self.classes(&[]) else {
+            return false;
+        };
+        let mut anchors = vec![class[NIL]];
+        anchors.extend(
+            self.edges
+                .iter()
+                .filter(|edge| edge.is_points_to())
+                .map(|edge| class[edge.from]),
+        );
+        let mut distinct = anchors.clone();
+        distinct.sort_unstable();
+        distinct.dedup();
+        if distinct.len() < anchors.len() {
+            return false;
+        }
+
+        let segments: Vec<(usize, usize)> = self
+            .edges
+            .iter()
+            .filter_map(|edge| edge.to().map(|to| (class[edge.from], class[to])))
+            .filter(|(from, to)| from != to)
+            .collect();
+        let component = classes(&self.nodes, &segments, &[]).expect("There are no disequalities");
+        let mut components: Vec<usize> =
+            segments.iter().map(|&(from, _)| component[from]).collect();
+        components.sort_unstable();
+        components.dedup();
+        components.into_iter().all(|c| {
+            let segments: Vec<(usize, usize)> = segments
+                .iter()
+                .copied()
+                .filter(|&(from, _)| component[from] == c)
+                .collect();
+            let anchors: Vec<usize> = anchors
+                .iter()
+                .copied()
+                .filter(|&a| component[a] == c)
+                .collect();
+            match anchors.as_slice() {
+                [] => {
+                    let trees = (0..self.nodes.len())
+                        .filter(|&node| class[node] == node && component[node] == c)
+                        .map(|root| (root, segments.clone()));
+                    let cycles = (0..segments.len()).filter_map(|i| {
+                        let mut rest = segments.clone();
+                        let (from, to) = rest.remove(i);
+                        let component =
+                            classes(&self.nodes, &rest, &[]).expect("There are no disequalities");
+                        (component[from] == component[to]).then_some((from, rest))
+                    });
+                    trees
+                        .chain(cycles)
+                        .any(|(root, segments)| self.partition(root, &segments))
+                }
+                &[anchor] => self.partition(anchor, &segments),
+                _ => false,
+            }
+        })
+    }
+
+    /// Whether the finest partition of the connected segments in which the root's part has no
+    /// segment leaving it respects the disequalities. Every other partition merges more.
+    fn partition(&self, root: usize, segments: &[(usize, usize)]) -> bool {
+        let mut merged = Vec::new();
+        loop {
+            let Some(class) = self.classes(&merged) else {
+                return false;
+            };
+            let edges: Vec<(usize, usize)> = segments
+                .iter()
+                .map(|&(from, to)| (class[from], class[to]))
+                .filter(|(from, to)| from != to)
+                .collect();
+            match forced(class[root], &edges) {
+                Some(merge) => merged.push(merge),
+                None => return true,
+            }
+        }
+    }
+}
+
+/// Assigns the edges of the antecedent to the atoms of the consequent
+struct Matching<'a> {
+    antecedent: &'a Graph,
+    /// The representative of every node modulo the equalities implied by the antecedent
+    class: Vec<usize>,
+    /// The edges of the antecedent that are not necessarily empty
+    edges: Vec<Edge>,
+    used: Vec<bool>,
+}
+
+impl Matching<'_> {
+    /// Covers every edge with exactly one of the segments, each by a path from its start to its end
+    fn cover(&mut self, segments: &[Edge]) -> bool {
+        segments.iter().all(|segment| self.path(segment)) && self.used.iter().all(|&used| used)
+    }
+
+    /// Follows unused edges from the start of the segment to its end without ever backtracking,
+    /// taking the edge to the end if there is one and otherwise one from which the end can still
+    /// be reached
+    fn path(&mut self, segment: &Edge) -> bool {
+        let (field, end) = match &segment.kind {
+            Kind::Segment(field, end) => (field, self.class[*end]),
+            Kind::PointsTo(_) => unreachable!("Only segments are covered by paths"),
+        };
+        let mut node = self.class[segment.from];
+        let mut path = Vec::new();
+        while node != end {
+            let next: Vec<(usize, usize)> = (0..self.edges.len())
+                .filter(|&index| !self.used[index] && self.class[self.edges[index].from] == node)
+                .filter_map(|index| {
+                    let next = self.edges[index].follow(field)?;
+                    Some((index, self.class[next]))
+                })
+                .collect();
+            let step = next
+                .iter()
+                .find(|&&(_, next)| next == end)
+                .or_else(|| {
+                    next.iter()
+                        .find(|&&(_, next)| self.reaches(next, field, end))
+                })
+                .or_else(|| next.first());
+            match step {
+                Some(&(index, next)) => {
+                    self.used[index] = true;
+                    path.push(index);
+                    node = next;
+                }
+                None => return false,
+            }
+        }
+        self.acyclic(end, &path)
+    }
+
+    /// Whether the unused edges lead from the node to the end along the field
+    fn reaches(&self, node: usize, field: &Field, end: usize) -> bool {
+        let mut seen = vec![node];
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if node == end {
+                return true;
+            }
+            for (index, edge) in self.edges.iter().enumerate() {
+                if self.used[index] || self.class[edge.from] != node {
+                    continue;
+                }
+                if let Some(next) = edge.follow(field).map(|next| self.class[next]) {
+                    if !seen.contains(&next) {
+                        seen.push(next);
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Whether the end of the path is never one of the cells of its edges, i.e. the first or an
+    /// inner cell of a nonempty segment. Otherwise the path would reach the end too early.
+    fn acyclic(&self, end: usize, path: &[usize]) -> bool {
+        let graph = self.antecedent;
+        path.iter().all(|&index| {
+            let edge = &self.edges[index];
+            match edge.kind {
+                Kind::PointsTo(_) => !graph.with_equal(end, edge.from).satisfiable(),
+                Kind::Segment(_, to) => {
+                    let first = graph
+                        .with_equal(end, edge.from)
+                        .with_distinct(edge.from, to);
+                    // An unallocated location can always be spliced into a nonempty segment
+                    let inner = graph
+                        .with_cell(end)
+                        .with_distinct(edge.from, to)
+                        .with_distinct(end, to);
+                    !first.satisfiable() && !inner.satisfiable()
+                }
+            }
+        })
+    }
+}
+
+/// Decides the entailment by comparing the graphs of both sides. The (dis)equalities and
+/// allocations the antecedent implies are computed with satisfiability checks, then every points-to
+/// atom of the consequent has to be an edge of the antecedent and every list segment a path of
+/// edges that cannot pass its end. Together the atoms have to use every edge exactly once.
+/// This takes polynomial time, see the module documentation.
+/// Doubly linked segments, trees, user-defined predicates and existentials are not supported.
+pub fn decide(goal: &Entailment) -> Result<bool, Error> {
+    let atoms = [&goal.antecedent, &goal.consequent].map(|formula| match formula.get_spatial() {
+        SepConj(atoms) => atoms.as_slice(),
+        _ => &[],
+    });
+    let unsupported = atoms
+        .iter()
+        .flat_map(|atoms| atoms.iter())
+        .find_map(|atom| match atom {
+            DLS(..) => Some("dls"),
+            Tree(_) => Some("tree"),
+            Pred(definition, _) => Some(definition.name.as_str()),
+            _ => None,
+        })
+        .or_else(|| match goal.consequent.get_existentials() {
+            [] => None,
+            _ => Some("existentials"),
+        });
+    if let Some(predicate) = unsupported {
+        return Err(Error::Unsupported(format!(
+            "The graph based procedure does not support {}",
+            predicate
+        )));
+    }
+    let vars = goal.get_vars();
+    let antecedent = Graph::new(&goal.antecedent, &vars);
+    if !antecedent.satisfiable() {
+        return Ok(true);
+    }
+    let consequent = Graph::new(&goal.consequent, &vars);
+
+    let nodes = antecedent.nodes.len();
+    let implied: Vec<(usize, usize)> = (0..nodes)
+        .flat_map(|a| (a + 1..nodes).map(move |b| (a, b)))
+        .filter(|&(a, b)| !antecedent.with_distinct(a, b).satisfiable())
+        .collect();
+    let class = antecedent
+        .classes(&implied)
+        .expect("The implied equalities are consistent");
+    if consequent.equal.iter().any(|&(a, b)| class[a] != class[b])
+        || consequent
+            .distinct
+            .iter()
+            .any(|&(a, b)| antecedent.with_equal(a, b).satisfiable())
+    {
+        return Ok(false);
+    }
+
+    let nonempty = |edge: &&Edge| edge.to().is_none_or(|to| class[edge.from] != class[to]);
+    let edges: Vec<Edge> = antecedent.edges.iter().filter(nonempty).cloned().collect();
+    let (points_to, segments): (Vec<Edge>, Vec<Edge>) = consequent
+        .edges
+        .iter()
+        .filter(nonempty)
+        .cloned()
+        .partition(Edge::is_points_to);
+    let mut matching = Matching {
+        antecedent: &antecedent,
+        used: vec![false; edges.len()],
+        edges,
+        class,
+    };
+    for atom in points_to {
+        let class = &matching.class;
+        let fields = match &atom.kind {
+            Kind::PointsTo(fields) => fields,
+            Kind::Segment(_, _) => unreachable!("The segments were partitioned off"),
+        };
+        // The cell of the antecedent may have more fields than the atom
+        let edge = (0..matching.edges.len()).find(|&index| {
+            let edge = &matching.edges[index];
+            !matching.used[index]
+                && edge.is_points_to()
+                && class[edge.from] == class[atom.from]
+                && fields.iter().all(|(field, to)| {
+                    edge.follow(field)
+                        .is_some_and(|target| class[target] == class[*to])
+                })
+        });
+        match edge {
+            Some(index) => matching.used[index] = true,
+            None => return Ok(false),
+        }
+    }
+    Ok(matching.cover(&segments))
+}
+
+/// Decides the entailment with [`decide`]. Valid entailments get a proof consisting of a single
+/// [`GRAPH`] node, invalid ones have no countermodel, since the procedure never builds one.
+pub fn prove(goal: &Entailment) -> Result<Verdict, Error> {
+    if decide(goal)? {
+        Ok(Verdict::Valid(ProofTree {
+            goal: goal.clone(),
+            rule: GRAPH,
+            children: Vec::new(),
+        }))
+    } else {
+        Ok(Verdict::Invalid(goal.clone(), None))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::decide;
+    use crate::error::Error;
+    use crate::generator::{Generator, GeneratorConfig};
+    use crate::parser::parse;
+    use crate::prover::{prove, Verdict};
+    use crate::reference;
+
+    #[test]
+    fn test_decide() -> Result<(), Error> {
+        let valid = [
+            "And[Neq(x,y)]|SepConj[x->y,y->Nil] |- True|SepConj[ls(x,Nil)]",
+            "True|SepConj[ls(x,y),ls(y,z),z->w] |- True|SepConj[ls(x,z),z->w]",
+            "True|SepConj[x->y,y->x] |- And[Neq(x,y)]|SepConj[ls(x,y),ls(y,x)]",
+            // Whichever segment from x is empty, x is allocated twice
+            "True|SepConj[ls(x,y),ls(x,z),y->a,z->b] |- True|Emp",
+            // One of the segments is empty, and then so is the other one
+            "True|SepConj[ls(x,y),ls(x,y)] |- And[Eq(x,y)]|Emp",
+            // Either segment from x may be the nonempty one
+            "True|SepConj[ls(x,y),ls(x,z)] |- True|SepConj[ls(x,z),ls(x,y)]",
+            // Since c is not nil, a and d both start a nonempty segment to nil and hence equal c
+            "And[Neq(c,Nil)]|SepConj[ls(a,Nil),ls(a,c),ls(d,Nil),ls(d,c)] |- True|Emp",
+        ];
+        for input in valid.iter() {
+            assert!(decide(&parse(input)?)?, "{} is valid", input);
+        }
+        let invalid = [
+            "True|SepConj[ls(x,y),ls(y,z)] |- True|SepConj[ls(x,z)]",
+            "True|SepConj[ls(x,y)] |- True|SepConj[x->y]",
+            "True|SepConj[x->y] |- True|SepConj[ls(x,y)]",
+            "True|SepConj[ls(x,y),ls(x,z)] |- True|SepConj[ls(x,y),ls(y,z)]",
+        ];
+        for input in invalid.iter() {
+            assert!(!decide(&parse(input)?)?, "{} is invalid", input);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsupported() -> Result<(), Error> {
+        let goal = parse("True|SepConj[dls(x,y,p,Nil)] |- True|SepConj[ls(x,Nil)]")?;
+        assert!(matches!(decide(&goal), Err(Error::Unsupported(_))));
+        let goal = parse("True|SepConj[x->y] |- True|SepConj[tree(x)]")?;
+        match decide(&goal) {
+            Err(Error::Unsupported(message)) => assert!(message.ends_with("tree")),
+            other => panic!("Expected trees to be unsupported but got {:?}", other),
+        }
+        let goal = parse("True|SepConj[x->y] |- exists z. True|SepConj[x->z]")?;
+        match decide(&goal) {
+            Err(Error::Unsupported(message)) => assert!(message.ends_with("existentials")),
+            other => panic!(
+                "Expected existentials to be unsupported but got {:?}",
+                other
+            ),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cross_check() -> Result<(), Error> {
+        let config = GeneratorConfig {
+            variables: 4,
+            points_to: 2,
+            segments: 3,
+            pure: 2,
+            fields: 1,
+            dls: 0,
+            trees: 0,
+            existentials: 0,
+        };
+        for goal in Generator::new(config, 0x9a4).take(1000) {
+            let rules = matches!(prove(&goal)?, Verdict::Valid(_));
+            assert_eq!(rules, decide(&goal)?, "{}", goal);
+            assert_eq!(reference::decide(&goal).is_ok(), rules, "{}", goal);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,333 @@
+use crate::{
+    countermodel::{Countermodel, Value},
+    datastructures::Entailment,
+    error::Error,
+    proof::ProofTree,
+    prover::{Statistics, Verdict},
+};
+use std::{fmt, time::Duration};
+
+/// A JSON value. Objects keep the order in which their members were added.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+/// What is left to write of a JSON value, the next part last
+enum Pending<'a> {
+    Value(&'a Json),
+    Key(&'a str),
+    Text(&'static str),
+}
+
+/// Written without recursion, since the proofs in a [`Json`] can be arbitrarily deep
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut pending = vec![Pending::Value(self)];
+        while let Some(next) = pending.pop() {
+            match next {
+                Pending::Text(text) => write!(f, "{}", text)?,
+                Pending::Key(key) => {
+                    write_string(f, key)?;
+                    write!(f, ":")?;
+                }
+                Pending::Value(Json::Null) => write!(f, "null")?,
+                Pending::Value(Json::Bool(b)) => write!(f, "{}", b)?,
+                Pending::Value(Json::Number(n)) => write!(f, "{}", n)?,
+                Pending::Value(Json::String(s)) => write_string(f, s)?,
+                Pending::Value(Json::Array(elements)) => {
+                    write!(f, "[")?;
+                    pending.push(Pending::Text("]"));
+                    for (i, element) in elements.iter().enumerate().rev() {
+                        pending.push(Pending::Value(element));
+                        if i > 0 {
+                            pending.push(Pending::Text(","));
+                        }
+                    }
+                }
+                Pending::Value(Json::Object(members)) => {
+                    write!(f, "{{")?;
+                    pending.push(Pending::Text("}"));
+                    for (i, (key, value)) in members.iter().enumerate().rev() {
+                        pending.push(Pending::Value(value));
+                        pending.push(Pending::Key(key));
+                        if i > 0 {
+                            pending.push(Pending::Text(","));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Dropped without recursion for the same reason
+impl Drop for Json {
+    fn drop(&mut self) {
+        let take = |json: &mut Json, pending: &mut Vec<Json>| match json {
+            Json::Array(elements) => pending.append(elements),
+            Json::Object(members) => pending.extend(members.drain(..).map(|(_, value)| value)),
+            _ => {}
+        };
+        let mut pending = Vec::new();
+        take(self, &mut pending);
+        while let Some(mut json) = pending.pop() {
+            take(&mut json, &mut pending);
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+fn string(s: impl ToString) -> Json {
+    Json::String(s.to_string())
+}
+
+fn object(members: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+        members
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+impl From<&ProofTree> for Json {
+    /// Converts the nodes bottom up with an explicit stack, as deep proofs would overflow the call stack
+    fn from(proof: &ProofTree) -> Self {
+        // The nodes on the path to the current one with their children converted so far
+        let mut path: Vec<(&ProofTree, Vec<Json>)> = vec![(proof, Vec::new())];
+        loop {
+            let next = path
+                .last()
+                .and_then(|(node, children)| node.children.get(children.len()));
+            if let Some(child) = next {
+                path.push((child, Vec::new()));
+                continue;
+            }
+            let (node, children) = path.pop().expect("The path ends at the root");
+            let json = object(vec![
+                ("goal", string(&node.goal)),
+                ("rule", string(node.rule)),
+                ("children", Json::Array(children)),
+            ]);
+            match path.last_mut() {
+                Some((_, siblings)) => siblings.push(json),
+                None => return json,
+            }
+        }
+    }
+}
+
+impl From<&Countermodel> for Json {
+    fn from(model: &Countermodel) -> Self {
+        let stack = model
+            .stack
+            .iter()
+            .map(|(var, value)| (var.to_string(), string(value)))
+            .collect();
+        let heap = model
+            .heap
+            .iter()
+            .map(|(&loc, value)| (Value::Loc(loc).to_string(), string(value)))
+            .collect();
+        object(vec![
+            ("stack", Json::Object(stack)),
+            ("heap", Json::Object(heap)),
+        ])
+    }
+}
+
+impl From<&Statistics> for Json {
+    fn from(statistics: &Statistics) -> Self {
+        let applications = statistics
+            .applications
+            .iter()
+            .map(|&(rule, count)| (rule.to_string(), Json::Number(count as f64)))
+            .collect();
+        object(vec![
+            ("goals", Json::Number(statistics.goals as f64)),
+            ("applications", Json::Object(applications)),
+            ("memo_hits", Json::Number(statistics.memo_hits as f64)),
+            ("witnesses", Json::Number(statistics.witnesses as f64)),
+        ])
+    }
+}
+
+/// Everything known about a single run of the decision procedure
+pub struct Run<'a> {
+    pub entailment: Option<&'a Entailment>,
+    pub result: &'a Result<Verdict, Error>,
+    pub statistics: &'a Statistics,
+    pub time: Duration,
+}
+
+impl Run<'_> {
+    /// The run as a JSON object. The proof tree of valid entailments is only included on request,
+    /// since it can be much larger than the rest.
+    pub fn to_json(&self, with_proof: bool) -> Json {
+        let mut proof = Json::Null;
+        let mut stuck = Json::Null;
+        let mut countermodel = Json::Null;
+        let mut error = Json::Null;
+        let verdict = match self.result {
+            Ok(Verdict::Valid(tree)) => {
+                if with_proof {
+                    proof = Json::from(tree);
+                }
+                "valid"
+            }
+            Ok(Verdict::Invalid(goal, model)) => {
+                stuck = string(goal);
+                countermodel = model.as_ref().map_or(Json::Null, Json::from);
+                "invalid"
+            }
+            Ok(Verdict::Unknown(reason)) => {
+                error = error_json(&Error::from(reason.clone()));
+                "unknown"
+            }
+            Err(e) => {
+                error = error_json(e);
+                "error"
+            }
+        };
+        object(vec![
+            ("entailment", self.entailment.map_or(Json::Null, string)),
+            ("verdict", string(verdict)),
+            // In whole microseconds, the nanoseconds are noise and would print as long fractions
+            (
+                "time_ms",
+                Json::Number(self.time.as_micros() as f64 / 1000.0),
+            ),
+            ("statistics", Json::from(self.statistics)),
+            ("proof", proof),
+            ("stuck", stuck),
+            ("countermodel", countermodel),
+            ("error", error),
+        ])
+    }
+}
+
+fn error_json(error: &Error) -> Json {
+    match error {
+        Error::Parse { line, column, .. } => object(vec![
+            ("message", string(error)),
+            ("line", Json::Number(f64::from(*line))),
+            ("column", Json::Number(f64::from(*column))),
+        ]),
+        _ => object(vec![("message", string(error))]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Json, Run};
+    use crate::error::Error;
+    use crate::parser::parse;
+    use crate::proof::test::linear;
+    use crate::prover::{prove_with_statistics, Statistics};
+    use std::time::Duration;
+
+    #[test]
+    fn test_display() {
+        let json = Json::Object(vec![
+            (
+                "a\"b".to_string(),
+                Json::Array(vec![Json::Null, Json::Bool(true), Json::Number(1.5)]),
+            ),
+            (
+                "c".to_string(),
+                Json::String("line\nbreak\u{1}".to_string()),
+            ),
+        ]);
+        assert_eq!(
+            r#"{"a\"b":[null,true,1.5],"c":"line\nbreak\u0001"}"#,
+            json.to_string()
+        );
+    }
+
+    #[test]
+    fn test_run() -> Result<(), Error> {
+        let entailment = parse("True|SepConj[x->y] |- True|SepConj[x->y]")?;
+        let mut statistics = Statistics::default();
+        let result = prove_with_statistics(&entailment, &mut statistics);
+        let run = Run {
+            entailment: Some(&entailment),
+            result: &result,
+            statistics: &statistics,
+            time: Duration::from_millis(2),
+        };
+        let json = run.to_json(false).to_string();
+        assert!(json.starts_with(
+            r#"{"entailment":"True|SepConj[x->y] |- True|SepConj[x->y]","verdict":"valid","time_ms":2,"#
+        ));
+        assert!(json.contains(r#""goals":13,"#));
+        assert!(json.contains(r#""memo_hits":1,"witnesses":0}"#));
+        assert!(json.contains(r#""Frame":3,"#));
+        assert!(json.ends_with(r#""proof":null,"stuck":null,"countermodel":null,"error":null}"#));
+        assert!(run
+            .to_json(true)
+            .to_string()
+            .contains(r#""rule":"Tautology","children":[]"#));
+
+        let invalid = parse("True|SepConj[x->y] |- True|Emp")?;
+        let mut statistics = Statistics::default();
+        let result = prove_with_statistics(&invalid, &mut statistics);
+        let run = Run {
+            entailment: Some(&invalid),
+            result: &result,
+            statistics: &statistics,
+            ..run
+        };
+        let json = run.to_json(false).to_string();
+        assert!(json.contains(r#""verdict":"invalid","#));
+        assert!(
+            json.contains(r#""countermodel":{"stack":{"x":"l1","y":"nil"},"heap":{"l1":"nil"}}"#)
+        );
+
+        for (time, expected) in [
+            (Duration::from_nanos(1_234_567), r#""time_ms":1.234,"#),
+            (Duration::from_nanos(999), r#""time_ms":0,"#),
+            (Duration::from_micros(300), r#""time_ms":0.3,"#),
+            (Duration::from_secs(90), r#""time_ms":90000,"#),
+        ] {
+            let run = Run { time, ..run };
+            assert!(
+                run.to_json(false).to_string().contains(expected),
+                "{:?}",
+                time
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_deep_proof() {
+        let deep = std::thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(|| Json::from(&linear(20_000)).to_string());
+        let json = deep.unwrap().join().unwrap();
+        assert_eq!(20_000, json.matches(r#""rule":"#).count());
+        assert!(json.contains(r#""rule":"Tautology","children":[]}]}"#));
+    }
+}
@@ -0,0 +1,32 @@
+//! Alice_rs is a decision procedure for the decidable fragment of separation logic
+//! described in [A Decidable Fragment of Separation Logic](http://www0.cs.ucl.ac.uk/staff/p.ohearn/papers/unroll_collapse_withproofs.pdf).
+//!
+//! The usual entry points are [`parser::parse`] to read an entailment from a string
+//! and [`prove`] to decide it. Both report failures through [`Error`].
+pub mod batch;
+pub mod congruence;
+pub mod countermodel;
+pub mod datastructures;
+pub mod error;
+pub mod generator;
+pub mod graph;
+pub mod json;
+pub mod limits;
+mod misc;
+pub mod parser;
+pub mod printer;
+pub mod proof;
+pub mod prover;
+pub mod reference;
+pub mod repl;
+pub mod rules;
+pub mod semantics;
+pub mod smtlib;
+pub mod trace;
+
+pub use countermodel::Countermodel;
+pub use datastructures::{Entailment, Formula, Rule};
+pub use error::Error;
+pub use parser::parse_entailment;
+pub use proof::ProofTree;
+pub use prover::{prove, Reason, Verdict};
@@ -0,0 +1,75 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Bounds on the proof search. `None` means unbounded, which is the default.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Limits {
+    /// Maximum number of rule applications
+    pub max_steps: Option<usize>,
+    /// Maximum distance of a goal from the root of the proof
+    pub max_depth: Option<usize>,
+    /// Maximum wall-clock time of the proof search
+    pub timeout: Option<Duration>,
+}
+
+/// The limit that stopped a proof search
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Limit {
+    Steps(usize),
+    Depth(usize),
+    Timeout(Duration),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Steps(max) => write!(f, "more than {} rule applications", max),
+            Limit::Depth(max) => write!(f, "a goal deeper than {}", max),
+            Limit::Timeout(max) => write!(f, "longer than {:?}", max),
+        }
+    }
+}
+
+/// Enforces the limits during a single proof search, which checks them before every rule application.
+/// It can be shared by the threads of a parallel search, which may then overshoot the steps slightly.
+pub struct Governor {
+    limits: Limits,
+    deadline: Option<Instant>,
+    steps: AtomicUsize,
+}
+
+impl Governor {
+    /// Starts the clock for the timeout
+    pub fn new(limits: Limits) -> Self {
+        Governor {
+            limits,
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            steps: AtomicUsize::new(0),
+        }
+    }
+
+    /// Checks the limits before a rule is applied to a goal at the given depth
+    pub fn check(&self, depth: usize) -> Result<(), Limit> {
+        match (self.limits.max_steps, self.limits.max_depth) {
+            (Some(max), _) if self.steps.load(Ordering::Relaxed) >= max => {
+                return Err(Limit::Steps(max))
+            }
+            (_, Some(max)) if depth > max => return Err(Limit::Depth(max)),
+            _ => {}
+        }
+        match (self.limits.timeout, self.deadline) {
+            (Some(timeout), Some(deadline)) if Instant::now() >= deadline => {
+                Err(Limit::Timeout(timeout))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a rule application
+    pub fn applied(&self) {
+        self.steps.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -1,107 +1,255 @@
-mod datastructures;
-mod misc;
-mod parser;
-mod rules;
-use combine::{stream::position::Stream, Parser};
-use datastructures::{Entailment, Rule};
-use parser::parse_entailment;
-use rules::*;
-use std::env;
+use alice_rs::{
+    batch::{check_with, read_cases, Summary},
+    graph,
+    json::Run,
+    limits::Limits,
+    parser::parse,
+    prover::{prove_parallel, prove_with, Statistics},
+    repl::{self, Session},
+    smtlib,
+    trace::{Trace, Verbosity},
+    Entailment, Error, Verdict,
+};
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    process,
+    time::{Duration, Instant},
+};
 
-fn main() -> Result<(), String> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!("Usage: alice \"[Entailment with possible whitespaces]\"");
-        return Err("Wrong number of Arguments!".to_string());
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("{}", error);
+        process::exit(exit_code(&error));
     }
+}
+
+/// The flags that change how a single entailment is decided and reported
+struct Options {
+    json: bool,
+    with_proof: bool,
+    verbosity: Verbosity,
+    pretty: bool,
+    limits: Limits,
+    threads: usize,
+    graph: bool,
+}
 
-    let entailment_raw = &args[1];
-    let entailemnt_parsed_result = parse_entailment().parse(Stream::new(&**entailment_raw));
+impl Options {
+    /// Runs the decision procedure, tracing to stderr as requested
+    fn prove(
+        &self,
+        entailment: &Entailment,
+        statistics: &mut Statistics,
+    ) -> Result<Verdict, Error> {
+        if self.graph {
+            return graph::prove(entailment);
+        }
+        if self.threads > 1 {
+            return prove_parallel(entailment, &self.limits, self.threads, statistics);
+        }
+        let mut stderr = io::stderr();
+        let mut trace = Trace::new(self.verbosity, self.pretty, &mut stderr);
+        prove_with(entailment, &self.limits, statistics, &mut trace)
+    }
+}
 
-    if let Ok((entailment, _)) = entailemnt_parsed_result {
-        ps(entailment)
+fn run() -> Result<(), Error> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let verbosity = if take_flag(&mut args, "-vvv") {
+        Verbosity::Premisses
+    } else if take_flag(&mut args, "-vv") {
+        Verbosity::Rules
+    } else if take_flag(&mut args, "-v") {
+        Verbosity::Goals
     } else {
-        println!("{:?}", entailemnt_parsed_result);
-        Err("Could not parse input correctly!".to_string())
+        Verbosity::Quiet
+    };
+    let limits = Limits {
+        max_steps: take_value(&mut args, "--max-steps").map(|max| max as usize),
+        max_depth: take_value(&mut args, "--max-depth").map(|max| max as usize),
+        timeout: take_value(&mut args, "--timeout").map(Duration::from_millis),
+    };
+    let options = Options {
+        json: take_flag(&mut args, "--json"),
+        with_proof: take_flag(&mut args, "--proof"),
+        verbosity,
+        pretty: take_flag(&mut args, "--pretty"),
+        limits,
+        threads: take_value(&mut args, "--threads").map_or(1, |threads| threads as usize),
+        graph: take_flag(&mut args, "--graph"),
+    };
+    if (options.threads > 1 || options.graph) && options.verbosity > Verbosity::Quiet {
+        eprintln!("Only the sequential proof search can be traced");
+        process::exit(2);
+    }
+    match (args.as_slice(), options.json) {
+        ([command], false) if command == "repl" => run_repl(),
+        ([entailment], false) if !entailment.starts_with("--") => {
+            decide(&options, &parse(entailment)?)
+        }
+        ([entailment], true) if !entailment.starts_with("--") => {
+            run_json(&options, parse(entailment))
+        }
+        ([flag, path], false) if flag == "--batch" => run_batch(&options, &read_input(path)),
+        ([flag, path], false) if flag == "--smt2" => run_smt2(&options, &read_input(path)),
+        ([flag, path], true) if flag == "--smt2" => {
+            run_json(&options, smtlib::parse(&read_input(path)))
+        }
+        _ => {
+            println!("Usage: alice [Options] \"[Entailment with possible whitespaces]\"");
+            println!("       alice [Options] --smt2 [SL-COMP benchmark, - for stdin]");
+            println!(
+                "       alice [Options] --batch [File with one entailment per line, - for stdin]"
+            );
+            println!("       alice repl");
+            println!("Options: --json [--proof]    print the result as JSON (not in batch mode)");
+            println!("         -v | -vv | -vvv     trace goals, rules or premisses to stderr");
+            println!("         --pretty            indent the trace and use Unicode notation");
+            println!("         --threads [n]       prove the cases of case splits on n threads");
+            println!("         --graph             use the graph based procedure");
+            println!("Limits:  --max-steps [n]     give up after n rule applications");
+            println!("         --max-depth [n]     give up on goals deeper than n");
+            println!("         --timeout [ms]      give up after the given number of milliseconds");
+            process::exit(2);
+        }
+    }
+}
+
+/// Removes the option and its numeric value, exiting with a usage error for malformed values
+fn take_value(args: &mut Vec<String>, option: &str) -> Option<u64> {
+    let index = args.iter().position(|arg| arg == option)?;
+    let value = args.get(index + 1).and_then(|value| value.parse().ok());
+    match value {
+        Some(value) => {
+            args.drain(index..index + 2);
+            Some(value)
+        }
+        None => {
+            eprintln!("{} expects a number", option);
+            process::exit(2);
+        }
     }
 }
 
-const RULES: [&dyn Rule; 13] = [
-    // Axioms
-    &Tautology,
-    &Contradiction,
-    // Bring the entailment to normal form
-    &Substitution,
-    &EqReflexiveL,
-    &NilNotLVal,
-    &StarPartial,
-    &UnrollCollapse,
-    // Simplification without normalform needed
-    &EqReflexiveR,
-    &EmptyLs,
-    &Hypothesis,
-    // Simplifications that need normalform
-    &Frame,
-    &NonEmptyLS,
-    // Rule to cleanup empty vectors to enum counterparts
-    &Cleanup,
-];
+/// Removes all occurrences of the flag and returns whether there were any
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != flag);
+    args.len() != len
+}
+
+fn decide(options: &Options, entailment: &Entailment) -> Result<(), Error> {
+    report(options.prove(entailment, &mut Statistics::default())?)
+}
+
+/// Prints the countermodel of invalid entailments and turns them into errors
+fn report(verdict: Verdict) -> Result<(), Error> {
+    match verdict {
+        Verdict::Valid(_) => Ok(()),
+        Verdict::Invalid(stuck, model) => {
+            if let Some(model) = model {
+                println!("Countermodel: {}", model);
+            }
+            Err(Error::Invalid(Box::new(stuck)))
+        }
+        Verdict::Unknown(reason) => Err(reason.into()),
+    }
+}
+
+/// Prints the verdict, statistics and countermodel (or proof) as a single JSON object
+fn run_json(options: &Options, entailment: Result<Entailment, Error>) -> Result<(), Error> {
+    let mut statistics = Statistics::default();
+    let start = Instant::now();
+    let result = entailment
+        .as_ref()
+        .map_err(Error::clone)
+        .and_then(|entailment| options.prove(entailment, &mut statistics));
+    let run = Run {
+        entailment: entailment.as_ref().ok(),
+        result: &result,
+        statistics: &statistics,
+        time: start.elapsed(),
+    };
+    println!("{}", run.to_json(options.with_proof));
+    match result? {
+        Verdict::Valid(_) => Ok(()),
+        Verdict::Invalid(stuck, _) => Err(Error::Invalid(Box::new(stuck))),
+        Verdict::Unknown(reason) => Err(reason.into()),
+    }
+}
 
-/// The actual decision procedure
-fn ps(goal: Entailment) -> Result<(), String> {
-    for &rule in RULES.iter() {
-        if rule.predicate(&goal) {
-            if let Some(new_goals) = rule.premisses(goal.clone()) {
-                for new_goal in new_goals {
-                    ps(new_goal)?;
-                }
+/// Reads commands from stdin until `quit` or the end of the input
+fn run_repl() -> Result<(), Error> {
+    let mut session = Session::new();
+    println!("{}", repl::HELP);
+    loop {
+        print!("alice> ");
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => {
+                println!();
                 return Ok(());
             }
+            Ok(_) => match session.execute(&line) {
+                Some(answer) if answer.is_empty() => {}
+                Some(answer) => println!("{}", answer),
+                None => return Ok(()),
+            },
         }
     }
-    Err("Entailment is invalid!".to_string())
 }
 
-#[cfg(test)]
-pub mod test {
-    use super::ps;
-    use crate::datastructures::{
-        AtomSpatial::{PointsTo, LS},
-        Entailment, Expr,
-        Expr::Nil,
-        Formula,
-        Op::{AtomEq, AtomNeq},
-        Pure::{And, True},
-        Spatial::SepConj,
+/// Reads the whole file, where `-` stands for stdin
+fn read_input(path: &str) -> String {
+    let mut input = String::new();
+    let read = if path == "-" {
+        io::stdin().read_to_string(&mut input).map(|_| ())
+    } else {
+        fs::read_to_string(path).map(|content| input = content)
     };
+    if let Err(error) = read {
+        eprintln!("Could not read {}: {}", path, error);
+        process::exit(2);
+    }
+    input
+}
 
-    #[test]
-    pub fn test_ps() {
-        let valid = Entailment {
-            antecedent: Formula(
-                And(vec![AtomNeq(Expr::new_var("x"), Expr::new_var("y"))]),
-                SepConj(vec![
-                    PointsTo(Expr::new_var("x"), Expr::new_var("y")),
-                    PointsTo(Expr::new_var("y"), Nil),
-                ]),
-            ),
-            consequent: Formula(True, SepConj(vec![LS(Expr::new_var("x"), Nil)])),
-        };
-        assert_eq!(Ok(()), ps(valid));
+/// Checks every entailment of the file and exits with 1 if any of them has an unexpected verdict
+fn run_batch(options: &Options, input: &str) -> Result<(), Error> {
+    let reports: Vec<_> = read_cases(input)
+        .into_iter()
+        .map(|case| check_with(case, |goal| options.prove(goal, &mut Statistics::default())))
+        .collect();
+    println!("{}", Summary(&reports));
+    if !reports.iter().all(|report| report.passed()) {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Answers the `(check-sat)` of the benchmark, i.e. `unsat` iff the entailment is valid
+fn run_smt2(options: &Options, input: &str) -> Result<(), Error> {
+    let entailment = smtlib::parse(input)?;
+    let verdict = options
+        .prove(&entailment, &mut Statistics::default())
+        .inspect_err(|_| println!("unknown"))?;
+    match verdict {
+        Verdict::Valid(_) => println!("unsat"),
+        Verdict::Invalid(_, _) => println!("sat"),
+        Verdict::Unknown(_) => println!("unknown"),
+    }
+    report(verdict)
+}
 
-        let invalid = Entailment {
-            antecedent: Formula(
-                True,
-                SepConj(vec![
-                    PointsTo(Expr::new_var("x"), Nil),
-                    PointsTo(Expr::new_var("y"), Nil),
-                ]),
-            ),
-            consequent: Formula(
-                And(vec![AtomEq(Expr::new_var("x"), Expr::new_var("y"))]),
-                SepConj(vec![PointsTo(Expr::new_var("y"), Nil)]),
-            ),
-        };
-        assert_eq!(Err("Entailment is invalid!".to_string()), ps(invalid));
+/// Distinct exit codes let wrappers tell invalid entailments apart from malformed input
+fn exit_code(error: &Error) -> i32 {
+    match error {
+        Error::Invalid(_) => 1,
+        Error::Parse { .. } | Error::Unsupported(_) => 2,
+        Error::Internal(_) => 3,
+        Error::ResourceLimit(_) => 4,
+        Error::Undecided(_) => 5,
     }
 }
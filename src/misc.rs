@@ -20,7 +20,7 @@ pub fn find_and_remove<T, P>(vec: &mut Vec<T>, pred: P) -> Option<T>
 where
     P: Fn(&T) -> bool,
 {
-    if let Some(index) = find_first(&vec, pred) {
+    if let Some(index) = find_first(vec, pred) {
         let elem = vec.swap_remove(index);
         Some(elem)
     } else {
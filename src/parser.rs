@@ -1,16 +1,23 @@
-use crate::datastructures::*;
+use crate::{datastructures::*, error::Error};
 use combine::{
-    between,
-    error::ParseError,
-    many1,
+    attempt, between, eof,
+    error::{ParseError, StreamError},
+    look_ahead, many, many1, not_followed_by, optional,
     parser::{
-        char::{char, letter, spaces, string},
+        char::{char, digit, space, spaces, string},
         error::Silent,
+        repeat::skip_many1,
         sequence::Skip,
         token::Token,
     },
-    sep_by, tokens, Parser, Stream,
+    satisfy, sep_by, sep_by1,
+    stream::{position, StreamErrorFor},
+    tokens, EasyParser, Parser, Stream,
 };
+use std::{collections::BTreeMap, sync::Arc};
+
+/// The declared predicates by name
+type Predicates = BTreeMap<String, Arc<Predicate>>;
 
 fn lex_char<Input>(c: char) -> Skip<Token<Input>, Silent<impl Parser<Input, Output = ()>>>
 where
@@ -48,6 +55,39 @@ where
     (pair).map(|pair_raw| pair_raw)
 }
 
+/// Whether the character may start a name, i.e. is an ASCII letter
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic()
+}
+
+/// Whether the character may continue a name, i.e. is an ASCII letter, a digit or `_`
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Whether the name can be written and parsed as a variable, field or predicate, i.e. starts with
+/// a letter, continues with letters, digits and `_` and is not `Nil` in any case, as that is
+/// parsed case-insensitively
+pub fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(is_name_start)
+        && chars.all(is_name_char)
+        && !name.eq_ignore_ascii_case("nil")
+}
+
+/// A letter followed by letters, digits and `_`
+fn parse_name<Input>() -> impl Parser<Input, Output = String>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        satisfy(is_name_start),
+        many::<String, _, _>(satisfy(is_name_char)),
+    )
+        .map(|(first, rest)| format!("{}{}", first, rest))
+}
+
 fn parse_vec<Input, T>(
     parse_t: impl Parser<Input, Output = T>,
 ) -> impl Parser<Input, Output = Vec<T>>
@@ -65,9 +105,19 @@ where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    let parse_nil = parse_atomic_val("Nil", Expr::Nil);
-    let parse_var = (many1::<Vec<_>, _, _>(letter()))
-        .map(|name| Expr::new_var(&name.into_iter().collect::<String>()));
+    // Variables may start like nil, e.g. next
+    let parse_nil =
+        attempt(parse_atomic_val("Nil", Expr::Nil).skip(not_followed_by(satisfy(is_name_char))));
+    // The names of fresh variables like x'1 are accepted as well, so that goals printed by the
+    // decision procedure can be parsed again
+    let parse_fresh = optional((char(FRESH_SEPARATOR), many1::<String, _, _>(digit())));
+    let parse_var = (parse_name(), parse_fresh).map(|(mut name, fresh)| {
+        if let Some((separator, number)) = fresh {
+            name.push(separator);
+            name.push_str(&number);
+        }
+        Expr::new_var(&name)
+    });
     parse_nil.or(parse_var)
 }
 
@@ -100,57 +150,391 @@ where
     parse_true.or(parse_and)
 }
 
-fn parse_atom_spatial<Input>() -> impl Parser<Input, Output = AtomSpatial>
+fn parse_field<Input>() -> impl Parser<Input, Output = Field>
 where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    let parse_points_to = (parse_expr(), spaces(), string("->"), spaces(), parse_expr())
-        .map(|(l, _, _, _, r)| AtomSpatial::PointsTo(l, r));
-    let parse_ls = (
-        string("ls"),
+    parse_name().map(Field)
+}
+
+fn parse_record<Input>() -> impl Parser<Input, Output = Record>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let parse_entry = (
+        parse_field(),
         spaces(),
-        parse_pair(parse_expr(), parse_expr()),
+        lex_char(':'),
+        parse_expr(),
+        spaces(),
+    )
+        .map(|(field, _, _, value, _)| (field, value));
+    let parse_fields = between(
+        lex_char('['),
+        char(']'),
+        sep_by::<Vec<_>, _, _, _>(parse_entry, lex_char(',')),
+    )
+    .and_then(|entries| {
+        let mut record = Record::default();
+        for (field, value) in entries {
+            if record.0.insert(field, value).is_some() {
+                return Err(StreamErrorFor::<Input>::message_static_message(
+                    "a field occurs twice in a record",
+                ));
+            }
+        }
+        Ok(record)
+    });
+    parse_fields.or(parse_expr().map(Record::next))
+}
+
+/// Resolves `name[field](args)` to a built-in or a declared predicate
+fn resolve_call(
+    predicates: &Predicates,
+    name: String,
+    field: Option<Field>,
+    mut args: Vec<Expr>,
+) -> Result<AtomSpatial, String> {
+    let arity = match (name.as_str(), predicates.get(&name)) {
+        ("ls", _) => 2,
+        (_, _) if field.is_some() => return Err(format!("{} does not follow a field", name)),
+        ("dls", _) => 4,
+        ("tree", _) => 1,
+        (_, Some(definition)) => definition.params.len(),
+        (_, None) => return Err(format!("{} is not a declared predicate", name)),
+    };
+    if args.len() != arity {
+        return Err(format!("{} expects {} arguments", name, arity));
+    }
+    Ok(match name.as_str() {
+        "ls" => {
+            let r = args.pop().unwrap();
+            let l = args.pop().unwrap();
+            AtomSpatial::LS(field.unwrap_or_else(Field::next), l, r)
+        }
+        "dls" => {
+            let (d, c, b, a) = (args.pop(), args.pop(), args.pop(), args.pop());
+            AtomSpatial::DLS(a.unwrap(), b.unwrap(), c.unwrap(), d.unwrap())
+        }
+        "tree" => AtomSpatial::Tree(args.pop().unwrap()),
+        _ => AtomSpatial::Pred(predicates[&name].clone(), args),
+    })
+}
+
+fn parse_atom_spatial<Input>(
+    predicates: Arc<Predicates>,
+) -> impl Parser<Input, Output = AtomSpatial>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let parse_points_to = (
+        parse_expr(),
+        spaces(),
+        string("->"),
+        spaces(),
+        parse_record(),
     )
-        .map(|(_, _, (l, r))| AtomSpatial::LS(l, r));
-    parse_ls.or(parse_points_to)
+        .map(|(l, _, _, _, r)| AtomSpatial::PointsTo(l, r));
+    // Variables may start like a predicate, so only a following bracket commits to a call
+    let parse_callee = (parse_name(), spaces(), look_ahead(char('(').or(char('['))));
+    let parse_link = optional(between(lex_char('['), lex_char(']'), parse_field()));
+    let parse_args = sep_by::<Vec<_>, _, _, _>(parse_expr().skip(spaces()), lex_char(','));
+    let parse_call = (
+        attempt(parse_callee),
+        parse_link,
+        between(lex_char('('), char(')'), parse_args),
+    )
+        .and_then(move |((name, _, _), field, args)| {
+            resolve_call(&predicates, name, field, args)
+                .map_err(StreamErrorFor::<Input>::message_format)
+        });
+    parse_call.or(parse_points_to)
 }
 
-fn parse_spatial<Input>() -> impl Parser<Input, Output = Spatial>
+fn parse_spatial<Input>(predicates: Arc<Predicates>) -> impl Parser<Input, Output = Spatial>
 where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    let parse_sep_conj = (string("SepConj"), parse_vec(parse_atom_spatial()))
+    let parse_sep_conj = (string("SepConj"), parse_vec(parse_atom_spatial(predicates)))
         .map(|(_, atom_sp_vec)| Spatial::SepConj(atom_sp_vec));
     let parse_emp = parse_atomic_val("Emp", Spatial::Emp);
     parse_sep_conj.or(parse_emp)
 }
 
-fn parse_formula<Input>() -> impl Parser<Input, Output = Formula>
+fn parse_formula<Input>(predicates: Arc<Predicates>) -> impl Parser<Input, Output = Formula>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (parse_pure(), lex_char('|'), parse_spatial(predicates))
+        .map(|(pure, _, spatial)| Formula(pure, spatial, Vec::new()))
+}
+
+fn parse_variable<Input>() -> impl Parser<Input, Output = Variable>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    parse_expr().skip(spaces()).and_then(|expr| match expr {
+        Expr::Var(var) => Ok(var),
+        Expr::Nil => Err(StreamErrorFor::<Input>::message_static_message(
+            "nil is not a variable",
+        )),
+    })
+}
+
+/// The quantifier in front of a formula, e.g. `exists x, y. `
+fn parse_exists<Input>() -> impl Parser<Input, Output = Vec<Variable>>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    (
+        attempt((string("exists"), skip_many1(space()))),
+        sep_by1(parse_variable(), lex_char(',')),
+        lex_char('.'),
+    )
+        .map(|(_, existentials, _)| existentials)
+}
+
+/// The pure part of a case of a declaration, e.g. ` & x=y & y!=nil`
+fn parse_conjuncts<Input>() -> impl Parser<Input, Output = Vec<Op>>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let parse_op = (
+        parse_expr().skip(spaces()),
+        string("!=").or(string("=")).skip(spaces()),
+        parse_expr().skip(spaces()),
+    )
+        .map(|(l, op, r)| match op {
+            "=" => Op::AtomEq(l, r),
+            _ => Op::AtomNeq(l, r),
+        });
+    many(lex_char('&').with(parse_op))
+}
+
+/// A case of a declaration before it is known to be the empty or the nonempty one
+enum Case {
+    Empty(Vec<Op>),
+    Cell {
+        existentials: Vec<Variable>,
+        source: Expr,
+        record: Record,
+        name: String,
+        call: Vec<Expr>,
+        step: Vec<Op>,
+    },
+}
+
+fn parse_case<Input>() -> impl Parser<Input, Output = Case>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let parse_empty = (
+        attempt(string("emp").skip(not_followed_by(satisfy(is_name_char)))),
+        spaces(),
+        parse_conjuncts(),
+    )
+        .map(|(_, _, base)| Case::Empty(base));
+    let parse_args = sep_by::<Vec<_>, _, _, _>(parse_expr().skip(spaces()), lex_char(','));
+    let parse_cell = (
+        optional(parse_exists()),
+        (parse_expr(), spaces(), string("->"), spaces()),
+        parse_record().skip(spaces()),
+        lex_char('*'),
+        parse_name().skip(spaces()),
+        between(lex_char('('), lex_char(')'), parse_args),
+        parse_conjuncts(),
+    )
+        .map(
+            |(existentials, (source, _, _, _), record, _, name, call, step)| Case::Cell {
+                existentials: existentials.unwrap_or_default(),
+                source,
+                record,
+                name,
+                call,
+                step,
+            },
+        );
+    parse_empty.or(parse_cell)
+}
+
+/// A declaration like `pred ls(x,y) := emp & x=y | exists z. x->z * ls(z,y) & x!=y;`
+fn parse_declaration<Input>() -> impl Parser<Input, Output = Predicate>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let parse_params = between(
+        lex_char('('),
+        lex_char(')'),
+        sep_by::<Vec<_>, _, _, _>(parse_variable(), lex_char(',')),
+    );
+    (
+        (string("pred"), skip_many1(space())),
+        parse_name().skip(spaces()),
+        parse_params,
+        string(":=").skip(spaces()),
+        parse_case(),
+        lex_char('|'),
+        parse_case(),
+        lex_char(';'),
+    )
+        .and_then(|(_, name, params, _, first, _, second, _)| {
+            let (base, cell) = match (first, second) {
+                (Case::Empty(base), cell) | (cell, Case::Empty(base)) => (base, cell),
+                _ => {
+                    return Err(StreamErrorFor::<Input>::message_static_message(
+                        "a predicate needs an empty case",
+                    ))
+                }
+            };
+            let definition = match cell {
+                Case::Cell {
+                    existentials,
+                    source,
+                    record,
+                    name: callee,
+                    call,
+                    step,
+                } => {
+                    // Without parameters check rejects the declaration below
+                    if matches!(params.first(), Some(first) if source != Expr::Var(first.clone())) {
+                        return Err(StreamErrorFor::<Input>::message_format(format!(
+                            "the cell of {} has to belong to its first parameter",
+                            name
+                        )));
+                    }
+                    if callee != name {
+                        return Err(StreamErrorFor::<Input>::message_format(format!(
+                            "the nonempty case of {} has to use {}",
+                            name, name
+                        )));
+                    }
+                    Predicate {
+                        name,
+                        params,
+                        base,
+                        existentials,
+                        record,
+                        call,
+                        step,
+                    }
+                }
+                Case::Empty(_) => {
+                    return Err(StreamErrorFor::<Input>::message_static_message(
+                        "a predicate needs a nonempty case",
+                    ))
+                }
+            };
+            definition
+                .check()
+                .map_err(StreamErrorFor::<Input>::message_format)?;
+            Ok(definition)
+        })
+}
+
+/// The declarations in front of an entailment, which must have distinct names
+fn parse_declarations<Input>() -> impl Parser<Input, Output = Predicates>
 where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
-    (parse_pure(), lex_char('|'), parse_spatial()).map(|(pure, _, spatial)| Formula(pure, spatial))
+    many::<Vec<_>, _, _>(parse_declaration().skip(spaces())).and_then(|declarations| {
+        let mut predicates = Predicates::new();
+        for definition in declarations {
+            let name = definition.name.clone();
+            if predicates
+                .insert(name.clone(), Arc::new(definition))
+                .is_some()
+            {
+                return Err(StreamErrorFor::<Input>::message_format(format!(
+                    "{} is declared twice",
+                    name
+                )));
+            }
+        }
+        Ok(predicates)
+    })
 }
 
 /// Just a simple parser for entailments based on parser combinators
 pub fn parse_entailment<Input>() -> impl Parser<Input, Output = Entailment>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    parse_entailment_with(Arc::default())
+}
+
+/// Parses an entailment that may use the given user-defined predicates
+fn parse_entailment_with<Input>(
+    predicates: Arc<Predicates>,
+) -> impl Parser<Input, Output = Entailment>
 where
     Input: Stream<Token = char>,
     Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
 {
     (
-        parse_formula(),
+        parse_formula(predicates.clone()),
         spaces(),
         string("|-"),
         spaces(),
-        parse_formula(),
+        optional(parse_exists()),
+        parse_formula(predicates),
     )
-        .map(|(antecedent, _, _, _, consequent)| Entailment {
-            antecedent,
-            consequent,
+        .and_then(|(antecedent, _, _, _, existentials, mut consequent)| {
+            let existentials = existentials.unwrap_or_default();
+            let free: Vec<Variable> = (antecedent.get_pure_vars().into_iter().flatten())
+                .chain(antecedent.get_spatial_vars().into_iter().flatten())
+                .collect();
+            for (i, var) in existentials.iter().enumerate() {
+                if existentials[..i].contains(var) {
+                    return Err(StreamErrorFor::<Input>::message_format(format!(
+                        "{} is bound twice in the consequent",
+                        var
+                    )));
+                }
+                if free.contains(var) {
+                    return Err(StreamErrorFor::<Input>::message_format(format!(
+                        "{} is bound in the consequent but free in the antecedent",
+                        var
+                    )));
+                }
+            }
+            consequent.2 = existentials;
+            Ok(Entailment {
+                antecedent,
+                consequent,
+            })
+        })
+}
+
+/// Parses a whole string as an entailment, preceded by the declarations of the predicates it uses,
+/// and reports the position of malformed input
+pub fn parse(input: &str) -> Result<Entailment, Error> {
+    let parse_input =
+        parse_declarations().then(|predicates| parse_entailment_with(Arc::new(predicates)));
+    (spaces(), parse_input, spaces(), eof())
+        .map(|(_, entailment, _, _)| entailment)
+        .easy_parse(position::Stream::new(input))
+        .map(|(entailment, _)| entailment)
+        .map_err(|errors| Error::Parse {
+            line: errors.position.line,
+            column: errors.position.column,
+            message: errors
+                .errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
         })
 }
 
@@ -170,40 +554,41 @@ fn tst() {
         Pure::And(vec![Op::AtomNeq(Expr::new_var("x"), Expr::new_var("y"))]),
     );
 
-    let points_to1 = parse_atom_spatial().parse("x->y");
+    let points_to1 = parse_atom_spatial(Arc::default()).parse("x->y");
     assert!(points_to1.is_ok());
     assert_eq!(
         points_to1.unwrap().0,
-        AtomSpatial::PointsTo(Expr::new_var("x"), Expr::new_var("y"))
+        AtomSpatial::PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("y")))
     );
 
-    let pointsto2 = parse_atom_spatial().parse("y->Nil");
+    let pointsto2 = parse_atom_spatial(Arc::default()).parse("y->Nil");
     assert!(pointsto2.is_ok());
     assert_eq!(
         pointsto2.unwrap().0,
-        AtomSpatial::PointsTo(Expr::new_var("y"), Expr::Nil)
+        AtomSpatial::PointsTo(Expr::new_var("y"), Record::next(Expr::Nil))
     );
 
-    let sepconj = parse_spatial().parse("SepConj[x->y,y->Nil]");
+    let sepconj = parse_spatial(Arc::default()).parse("SepConj[x->y,y->Nil]");
     assert!(sepconj.is_ok());
     assert_eq!(
         sepconj.unwrap().0,
         Spatial::SepConj(vec![
-            AtomSpatial::PointsTo(Expr::new_var("x"), Expr::new_var("y")),
-            AtomSpatial::PointsTo(Expr::new_var("y"), Expr::Nil),
+            AtomSpatial::PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("y"))),
+            AtomSpatial::PointsTo(Expr::new_var("y"), Record::next(Expr::Nil)),
         ])
     );
 
-    let form = parse_formula().parse("And[Neq(x,y)]|SepConj[x->y,y->Nil]");
+    let form = parse_formula(Arc::default()).parse("And[Neq(x,y)]|SepConj[x->y,y->Nil]");
     assert!(form.is_ok());
     assert_eq!(
         form.unwrap().0,
         Formula(
             Pure::And(vec![Op::AtomNeq(Expr::new_var("x"), Expr::new_var("y"))]),
             Spatial::SepConj(vec![
-                AtomSpatial::PointsTo(Expr::new_var("x"), Expr::new_var("y")),
-                AtomSpatial::PointsTo(Expr::new_var("y"), Expr::Nil),
+                AtomSpatial::PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("y"))),
+                AtomSpatial::PointsTo(Expr::new_var("y"), Record::next(Expr::Nil)),
             ]),
+            Vec::new()
         )
     );
 
@@ -211,24 +596,30 @@ fn tst() {
     assert!(p_true.is_ok());
     assert_eq!(p_true.unwrap().0, Pure::True);
 
-    let ls = parse_atom_spatial().parse("ls(x, Nil)");
+    let ls = parse_atom_spatial(Arc::default()).parse("ls(x, Nil)");
     assert!(ls.is_ok());
     assert_eq!(
         ls.unwrap().0,
-        AtomSpatial::LS(Expr::new_var("x"), Expr::Nil)
+        AtomSpatial::LS(Field::next(), Expr::new_var("x"), Expr::Nil)
     );
 
     let expected = Entailment {
         antecedent: Formula(
             Pure::And(vec![Op::AtomNeq(Expr::new_var("x"), Expr::new_var("y"))]),
             Spatial::SepConj(vec![
-                AtomSpatial::PointsTo(Expr::new_var("x"), Expr::new_var("y")),
-                AtomSpatial::PointsTo(Expr::new_var("y"), Expr::Nil),
+                AtomSpatial::PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("y"))),
+                AtomSpatial::PointsTo(Expr::new_var("y"), Record::next(Expr::Nil)),
             ]),
+            Vec::new(),
         ),
         consequent: Formula(
             Pure::True,
-            Spatial::SepConj(vec![AtomSpatial::LS(Expr::new_var("x"), Expr::Nil)]),
+            Spatial::SepConj(vec![AtomSpatial::LS(
+                Field::next(),
+                Expr::new_var("x"),
+                Expr::Nil,
+            )]),
+            Vec::new(),
         ),
     };
 
@@ -237,3 +628,276 @@ fn tst() {
     assert!(parsed.is_ok());
     assert_eq!(expected, parsed.unwrap().0);
 }
+
+#[test]
+fn test_parse() {
+    let parsed = parse(" True|SepConj[x->y] |- True|SepConj[x->y] ");
+    assert_eq!(
+        Ok(Entailment {
+            antecedent: Formula(
+                Pure::True,
+                Spatial::SepConj(vec![AtomSpatial::PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y"))
+                )]),
+                Vec::new()
+            ),
+            consequent: Formula(
+                Pure::True,
+                Spatial::SepConj(vec![AtomSpatial::PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y"))
+                )]),
+                Vec::new()
+            ),
+        }),
+        parsed
+    );
+
+    match parse("True|SepConj[x->y] |- True|Sepconj[x->y]") {
+        Err(Error::Parse { line, column, .. }) => {
+            assert_eq!(1, line);
+            assert_eq!(28, column);
+        }
+        other => panic!("Expected a parse error but got {:?}", other),
+    }
+
+    match parse("True|Emp |- True|Emp garbage") {
+        Err(Error::Parse { line, column, .. }) => {
+            assert_eq!(1, line);
+            assert_eq!(22, column);
+        }
+        other => panic!("Expected a parse error but got {:?}", other),
+    }
+    // Names are ASCII only, as documented in the grammar
+    match parse("True|SepConj[\u{e9}->y] |- True|Emp") {
+        Err(Error::Parse { line, column, .. }) => {
+            assert_eq!(1, line);
+            assert_eq!(14, column);
+        }
+        other => panic!("Expected a parse error but got {:?}", other),
+    }
+}
+
+#[test]
+fn test_records() -> Result<(), Error> {
+    let var = Expr::new_var;
+    let field = |name: &str| Field(name.to_string());
+    let parsed = parse("True|SepConj[x->[next: y, data:z],ls[prev](y, Nil)] |- True|Emp")?;
+    let record = Record(
+        vec![(field("next"), var("y")), (field("data"), var("z"))]
+            .into_iter()
+            .collect(),
+    );
+    assert_eq!(
+        Formula(
+            Pure::True,
+            Spatial::SepConj(vec![
+                AtomSpatial::PointsTo(var("x"), record),
+                AtomSpatial::LS(field("prev"), var("y"), Expr::Nil),
+            ]),
+            Vec::new()
+        ),
+        parsed.antecedent
+    );
+    assert_eq!(
+        parse("True|SepConj[x->y] |- True|SepConj[ls[next](x,y)]")?,
+        parse("True|SepConj[x->[next:y]] |- True|SepConj[ls(x,y)]")?
+    );
+    assert!(parse("True|SepConj[x->[]] |- True|Emp").is_ok());
+    match parse("True|SepConj[x->[next:y,next:z]] |- True|Emp") {
+        Err(Error::Parse { message, .. }) => assert!(message.contains("twice")),
+        other => panic!("Expected a parse error but got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_dls() -> Result<(), Error> {
+    let var = Expr::new_var;
+    let input = "True|SepConj[dls(x,y,Nil,z),d->Nil] |- True|SepConj[dls(x,d,Nil,Nil)]";
+    let parsed = parse(input)?;
+    assert_eq!(
+        Formula(
+            Pure::True,
+            Spatial::SepConj(vec![
+                AtomSpatial::DLS(var("x"), var("y"), Expr::Nil, var("z")),
+                AtomSpatial::PointsTo(var("d"), Record::next(Expr::Nil)),
+            ]),
+            Vec::new()
+        ),
+        parsed.antecedent
+    );
+    assert_eq!(input, parsed.to_string());
+    assert!(parse("True|SepConj[dls(x,y,z)] |- True|Emp").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_tree() -> Result<(), Error> {
+    let var = Expr::new_var;
+    let input = "True|SepConj[tree(x),t->[left:Nil,right:x]] |- True|SepConj[tree(t)]";
+    let parsed = parse(input)?;
+    let record = Record(
+        vec![
+            (Field("left".to_string()), Expr::Nil),
+            (Field("right".to_string()), var("x")),
+        ]
+        .into_iter()
+        .collect(),
+    );
+    assert_eq!(
+        Formula(
+            Pure::True,
+            Spatial::SepConj(vec![
+                AtomSpatial::Tree(var("x")),
+                AtomSpatial::PointsTo(var("t"), record),
+            ]),
+            Vec::new()
+        ),
+        parsed.antecedent
+    );
+    assert_eq!(input, parsed.to_string());
+    Ok(())
+}
+
+#[test]
+fn test_predicates() -> Result<(), Error> {
+    let var = Expr::new_var;
+    let lseg = "pred lseg(a,b) := emp & a=b | exists c. a->c * lseg(c,b) & a!=b;";
+    let input = format!("{} True|SepConj[lseg(x,y)] |- True|SepConj[ls(x,y)]", lseg);
+    let parsed = parse(&input)?;
+    let definition = match parsed.antecedent.get_spatial() {
+        Spatial::SepConj(atoms) => match &atoms[0] {
+            AtomSpatial::Pred(definition, args) => {
+                assert_eq!(&vec![var("x"), var("y")], args);
+                definition.clone()
+            }
+            other => panic!("Expected lseg but got {:?}", other),
+        },
+        other => panic!("Expected lseg but got {:?}", other),
+    };
+    assert_eq!(
+        Predicate {
+            name: "lseg".to_string(),
+            params: vec![Variable("a".to_string()), Variable("b".to_string())],
+            base: vec![Op::AtomEq(var("a"), var("b"))],
+            existentials: vec![Variable("c".to_string())],
+            record: Record::next(var("c")),
+            call: vec![var("c"), var("b")],
+            step: vec![Op::AtomNeq(var("a"), var("b"))],
+        },
+        *definition
+    );
+    assert_eq!(input, parsed.to_string());
+    // The cases may come in either order and without existentials
+    let swapped = "pred  odd (a, b):=a->[next:b,data:Nil]*odd(b,a)&a!=b|emp&a=b ;\n";
+    let swapped = parse(&format!("{} True|Emp |- True|SepConj[odd(x,y)]", swapped))?;
+    assert_eq!(
+        "pred odd(a,b) := emp & a=b | a->[data:Nil,next:b] * odd(b,a) & a!=b; True|Emp |- True|SepConj[odd(x,y)]",
+        swapped.to_string()
+    );
+    // Variables may start like a predicate
+    assert!(parse("True|SepConj[lx->y,treex->y] |- True|Emp").is_ok());
+
+    let errors = [
+        (
+            "True|Emp |- True|SepConj[lseg(x,y)]",
+            "lseg is not a declared",
+        ),
+        (
+            &format!("{} True|Emp |- True|SepConj[lseg(x)]", lseg),
+            "lseg expects 2",
+        ),
+        (
+            &format!("{} True|Emp |- True|SepConj[lseg[prev](x,y)]", lseg),
+            "does not follow",
+        ),
+        (
+            &format!("{} {} True|Emp |- True|Emp", lseg, lseg),
+            "lseg is declared twice",
+        ),
+        (
+            "pred ls(a,b) := emp & a=b | exists c. a->c * ls(c,b) & a!=b; True|Emp |- True|Emp",
+            "built-in",
+        ),
+        (
+            "pred p(a,b) := emp & a=b | exists c. a->c * p(c,b); True|Emp |- True|Emp",
+            "exclude",
+        ),
+        (
+            "pred p(a,b) := emp & a=d | exists c. a->c * p(c,b) & a!=d; True|Emp |- True|Emp",
+            "d is not bound",
+        ),
+        (
+            "pred p(a,b) := emp & a=b | exists c,d. a->c * p(c,b) & a!=b; True|Emp |- True|Emp",
+            "d is not the value",
+        ),
+        (
+            "pred p(a,b) := emp & a=b | exists c. b->c * p(c,b) & a!=b; True|Emp |- True|Emp",
+            "first parameter",
+        ),
+        (
+            "pred p(a,b) := emp & a=b | exists c. a->c * q(c,b) & a!=b; True|Emp |- True|Emp",
+            "has to use p",
+        ),
+        (
+            "pred p(a,b) := emp & a=b | exists c. a->c * p(c) & a!=b; True|Emp |- True|Emp",
+            "p expects 2",
+        ),
+        (
+            "pred p(a,a) := emp & a=a | exists c. a->c * p(c,a) & a!=a; True|Emp |- True|Emp",
+            "bound twice",
+        ),
+        (
+            "pred p(a,b) := emp & a=b | emp & a!=b; True|Emp |- True|Emp",
+            "nonempty case",
+        ),
+        (
+            "pred p() := emp & x=y | x->y * p() & x!=y; True|Emp |- True|Emp",
+            "needs a parameter",
+        ),
+    ];
+    for (input, expected) in errors.iter() {
+        match parse(input) {
+            Err(Error::Parse { message, .. }) => {
+                assert!(message.contains(expected), "{}: {}", input, message)
+            }
+            other => panic!("Expected a parse error for {} but got {:?}", input, other),
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_existentials() -> Result<(), Error> {
+    let input = "True|SepConj[x->y,ls(y,Nil)] |- exists z. True|SepConj[x->z,ls(z,Nil)]";
+    let parsed = parse(input)?;
+    assert_eq!(
+        &[Variable("z".to_string())],
+        parsed.consequent.get_existentials()
+    );
+    assert_eq!(input, parsed.to_string());
+    assert_eq!(
+        parsed,
+        parse("True|SepConj[x->y,ls(y,Nil)] |- exists  z .True|SepConj[x->z,ls(z,Nil)]")?
+    );
+
+    let errors = [
+        ("True|Emp |- exists z, z. True|Emp", "bound twice"),
+        (
+            "True|SepConj[z->Nil] |- exists z. True|SepConj[z->Nil]",
+            "free in the antecedent",
+        ),
+        ("exists z. True|Emp |- True|Emp", ""),
+    ];
+    for (input, expected) in errors.iter() {
+        match parse(input) {
+            Err(Error::Parse { message, .. }) => {
+                assert!(message.contains(expected), "{}: {}", input, message)
+            }
+            other => panic!("Expected a parse error for {} but got {:?}", input, other),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,368 @@
+use crate::datastructures::{
+    AtomSpatial,
+    AtomSpatial::{PointsTo, Pred, Tree, DLS, LS},
+    Entailment, Expr, Field, Formula, Op,
+    Op::{AtomEq, AtomNeq},
+    Predicate, Pure,
+    Pure::{And, True},
+    Record, Spatial,
+    Spatial::{Emp, SepConj},
+    Variable, NEXT,
+};
+use std::fmt;
+
+// The Display implementations emit exactly the syntax accepted by the parser,
+// such that parsing a printed entailment yields the entailment again. This includes the goals
+// printed by the decision procedure, whose variables may be fresh ones like x'1.
+
+impl fmt::Display for Variable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Nil => write!(f, "Nil"),
+            Expr::Var(var) => write!(f, "{}", var),
+        }
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.next_only() {
+            Some(next) => write!(f, "{}", next),
+            None => {
+                let fields: Vec<_> = self.0.iter().map(|(k, v)| format!("{}:{}", k, v)).collect();
+                write!(f, "[{}]", fields.join(","))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AtomEq(l, r) => write!(f, "Eq({},{})", l, r),
+            AtomNeq(l, r) => write!(f, "Neq({},{})", l, r),
+        }
+    }
+}
+
+impl fmt::Display for Pure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            True => write!(f, "True"),
+            And(ops) => write!(f, "And[{}]", join(ops, ",")),
+        }
+    }
+}
+
+impl fmt::Display for AtomSpatial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointsTo(l, r) => write!(f, "{}->{}", l, r),
+            LS(field, l, r) if field.0 == NEXT => write!(f, "ls({},{})", l, r),
+            LS(field, l, r) => write!(f, "ls[{}]({},{})", field, l, r),
+            DLS(a, b, c, d) => write!(f, "dls({},{},{},{})", a, b, c, d),
+            Tree(root) => write!(f, "tree({})", root),
+            Pred(definition, args) => write!(f, "{}({})", definition.name, join(args, ",")),
+        }
+    }
+}
+
+impl fmt::Display for Spatial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Emp => write!(f, "Emp"),
+            SepConj(atoms) => write!(f, "SepConj[{}]", join(atoms, ",")),
+        }
+    }
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.get_existentials().is_empty() {
+            write!(f, "exists {}. ", join(self.get_existentials(), ","))?;
+        }
+        write!(f, "{}|{}", self.get_pure(), self.get_spatial())
+    }
+}
+
+/// Prints the declaration, e.g. `pred ls(x,y) := emp & x=y | exists z. x->z * ls(z,y) & x!=y;`
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn conjuncts(ops: &[Op]) -> String {
+            ops.iter()
+                .map(|op| match op {
+                    AtomEq(l, r) => format!(" & {}={}", l, r),
+                    AtomNeq(l, r) => format!(" & {}!={}", l, r),
+                })
+                .collect()
+        }
+        write!(
+            f,
+            "pred {}({}) := emp{} | ",
+            self.name,
+            join(&self.params, ","),
+            conjuncts(&self.base)
+        )?;
+        if !self.existentials.is_empty() {
+            write!(f, "exists {}. ", join(&self.existentials, ","))?;
+        }
+        write!(
+            f,
+            "{}->{} * {}({}){};",
+            // Only a checked predicate is sure to have a first parameter
+            self.params
+                .first()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            self.record,
+            self.name,
+            join(&self.call, ","),
+            conjuncts(&self.step)
+        )
+    }
+}
+
+/// Prints the declarations of the user-defined predicates in front of the entailment
+impl fmt::Display for Entailment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for definition in self.predicates() {
+            write!(f, "{} ", definition)?;
+        }
+        write!(f, "{} |- {}", self.antecedent, self.consequent)
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T], separator: &str) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// Displays the wrapped value in the mathematical notation of the rule documentation,
+/// e.g. `x!=nil | x->y ∗ ls(y,nil)  |-  true | ls(x,nil)`.
+pub struct Unicode<'a, T>(pub &'a T);
+
+impl fmt::Display for Unicode<'_, Expr> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Expr::Nil => write!(f, "nil"),
+            Expr::Var(var) => write!(f, "{}", var),
+        }
+    }
+}
+
+impl fmt::Display for Unicode<'_, Record> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.next_only() {
+            Some(next) => write!(f, "{}", Unicode(next)),
+            None => {
+                let fields: Vec<_> = (self.0 .0.iter())
+                    .map(|(k, v)| format!("{}: {}", k, Unicode(v)))
+                    .collect();
+                write!(f, "[{}]", fields.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Unicode<'_, Op> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            AtomEq(l, r) => write!(f, "{}={}", Unicode(l), Unicode(r)),
+            AtomNeq(l, r) => write!(f, "{}!={}", Unicode(l), Unicode(r)),
+        }
+    }
+}
+
+impl fmt::Display for Unicode<'_, Pure> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            And(ops) if !ops.is_empty() => {
+                let ops: Vec<_> = ops.iter().map(Unicode).collect();
+                write!(f, "{}", join(&ops, " ∧ "))
+            }
+            _ => write!(f, "true"),
+        }
+    }
+}
+
+impl fmt::Display for Unicode<'_, AtomSpatial> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            PointsTo(l, r) => write!(f, "{}->{}", Unicode(l), Unicode(r)),
+            LS(field, l, r) if field.0 == NEXT => write!(f, "ls({},{})", Unicode(l), Unicode(r)),
+            LS(field, l, r) => write!(f, "ls[{}]({},{})", field, Unicode(l), Unicode(r)),
+            DLS(a, b, c, d) => write!(
+                f,
+                "dls({},{},{},{})",
+                Unicode(a),
+                Unicode(b),
+                Unicode(c),
+                Unicode(d)
+            ),
+            Tree(root) => write!(f, "tree({})", Unicode(root)),
+            Pred(definition, args) => {
+                let args: Vec<_> = args.iter().map(Unicode).collect();
+                write!(f, "{}({})", definition.name, join(&args, ","))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Unicode<'_, Spatial> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            SepConj(atoms) if !atoms.is_empty() => {
+                let atoms: Vec<_> = atoms.iter().map(Unicode).collect();
+                write!(f, "{}", join(&atoms, " ∗ "))
+            }
+            _ => write!(f, "emp"),
+        }
+    }
+}
+
+impl fmt::Display for Unicode<'_, Formula> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.0.get_existentials().is_empty() {
+            write!(f, "∃{}. ", join(self.0.get_existentials(), ","))?;
+        }
+        write!(
+            f,
+            "{} | {}",
+            Unicode(self.0.get_pure()),
+            Unicode(self.0.get_spatial())
+        )
+    }
+}
+
+impl fmt::Display for Unicode<'_, Entailment> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  |-  {}",
+            Unicode(&self.0.antecedent),
+            Unicode(&self.0.consequent)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Unicode;
+    use crate::datastructures::{
+        AtomSpatial::{PointsTo, LS},
+        Entailment, Expr,
+        Expr::Nil,
+        Field, Formula,
+        Op::{AtomEq, AtomNeq},
+        Pure::{And, True},
+        Record,
+        Spatial::{Emp, SepConj},
+    };
+    use crate::parser::parse;
+    use crate::prover::{prove, Verdict};
+
+    fn example() -> Entailment {
+        Entailment {
+            antecedent: Formula(
+                And(vec![
+                    AtomNeq(Expr::new_var("x"), Nil),
+                    AtomEq(Expr::new_var("x"), Expr::new_var("y")),
+                ]),
+                SepConj(vec![
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("y"))),
+                    LS(Field::next(), Expr::new_var("y"), Nil),
+                ]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, Emp, Vec::new()),
+        }
+    }
+
+    #[test]
+    fn test_display() -> Result<(), String> {
+        assert_eq!(
+            "And[Neq(x,Nil),Eq(x,y)]|SepConj[x->y,ls(y,Nil)] |- True|Emp",
+            example().to_string()
+        );
+        assert_eq!(
+            "x!=nil ∧ x=y | x->y ∗ ls(y,nil)  |-  true | emp",
+            Unicode(&example()).to_string()
+        );
+        assert_eq!(
+            example(),
+            parse(&example().to_string()).map_err(|e| e.to_string())?
+        );
+
+        let records = parse("True|SepConj[x->[next:y,data:Nil],ls[prev](y,x)] |- True|Emp")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(
+            "True|SepConj[x->[data:Nil,next:y],ls[prev](y,x)] |- True|Emp",
+            records.to_string()
+        );
+        assert_eq!(
+            "true | x->[data: nil, next: y] ∗ ls[prev](y,x)  |-  true | emp",
+            Unicode(&records).to_string()
+        );
+        assert_eq!(
+            records,
+            parse(&records.to_string()).map_err(|e| e.to_string())?
+        );
+
+        let existentials = parse("True|SepConj[x->y] |- exists z, w. And[Eq(z,y)]|SepConj[x->z]")
+            .map_err(|e| e.to_string())?;
+        assert_eq!(
+            "true | x->y  |-  ∃z,w. z=y | x->z",
+            Unicode(&existentials).to_string()
+        );
+        assert_eq!(
+            existentials,
+            parse(&existentials.to_string()).map_err(|e| e.to_string())?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_round_trip() -> Result<(), crate::error::Error> {
+        // Variables may start like nil and contain digits
+        let input = "And[Neq(n,nil)]|SepConj[n1->nx,nilx->Nil] |- True|SepConj[ls(n1,Nil)]";
+        let parsed = parse(input)?;
+        assert_eq!(input.replace("nil)", "Nil)"), parsed.to_string());
+        assert_eq!(parsed, parse(&parsed.to_string())?);
+
+        // Names may contain _ and fields digits, as SMT-LIB symbols do
+        for input in [
+            "And[Neq(x_1,Nil)]|Emp |- True|SepConj[Nil->Nil]",
+            "True|SepConj[x_1->[f1:Nil_,f_2:y]] |- True|SepConj[ls[f1](x_1,Nil_)]",
+        ] {
+            let parsed = parse(input)?;
+            assert_eq!(input, parsed.to_string());
+            assert_eq!(parsed, parse(&parsed.to_string())?);
+        }
+
+        // The goal on which the proof search got stuck contains the fresh variable x'1
+        let stuck = match prove(&parse(
+            "And[Neq(x,y)]|SepConj[ls(x,y)] |- True|SepConj[x->y]",
+        )?)? {
+            Verdict::Invalid(stuck, _) => stuck,
+            other => panic!("Expected an invalid verdict but got {:?}", other),
+        };
+        assert!(stuck.to_string().contains("x'1"));
+        assert_eq!(stuck, parse(&stuck.to_string())?);
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use crate::datastructures::Entailment;
+use std::mem;
+
+/// A derivation of a valid entailment.
+/// Every node records the goal, the rule that discharged it and the proofs of the rule's premisses.
+/// A [`MEMO`](crate::prover::MEMO) leaf stands for the proof of an equivalent goal elsewhere in the tree.
+/// A [`WITNESS`](crate::prover::WITNESS) node instantiates an existential of a goal no rule applies to.
+#[derive(Eq, Debug)]
+pub struct ProofTree {
+    pub goal: Entailment,
+    pub rule: &'static str,
+    pub children: Vec<ProofTree>,
+}
+
+impl ProofTree {
+    /// Number of rule applications in the whole derivation
+    pub fn size(&self) -> usize {
+        let mut size = 0;
+        let mut pending = vec![self];
+        while let Some(node) = pending.pop() {
+            size += 1;
+            pending.extend(&node.children);
+        }
+        size
+    }
+
+    /// Length of the longest path from the root to an axiom
+    pub fn depth(&self) -> usize {
+        let mut depth = 0;
+        let mut pending = vec![(self, 1)];
+        while let Some((node, node_depth)) = pending.pop() {
+            depth = depth.max(node_depth);
+            pending.extend(node.children.iter().map(|child| (child, node_depth + 1)));
+        }
+        depth
+    }
+}
+
+/// Cloned without recursion, since proofs can be deeper than the stack allows
+impl Clone for ProofTree {
+    fn clone(&self) -> Self {
+        // The clones of the children of a node are on top of `done` once it is visited again
+        let mut done: Vec<ProofTree> = Vec::new();
+        let mut pending = vec![(self, false)];
+        while let Some((node, visited)) = pending.pop() {
+            if visited {
+                let children = done.split_off(done.len() - node.children.len());
+                done.push(ProofTree {
+                    goal: node.goal.clone(),
+                    rule: node.rule,
+                    children,
+                });
+            } else {
+                pending.push((node, true));
+                pending.extend(node.children.iter().rev().map(|child| (child, false)));
+            }
+        }
+        done.pop().expect("the root is cloned last")
+    }
+}
+
+/// Compared without recursion, since proofs can be deeper than the stack allows
+impl PartialEq for ProofTree {
+    fn eq(&self, other: &Self) -> bool {
+        let mut pending = vec![(self, other)];
+        while let Some((left, right)) = pending.pop() {
+            if left.goal != right.goal
+                || left.rule != right.rule
+                || left.children.len() != right.children.len()
+            {
+                return false;
+            }
+            pending.extend(left.children.iter().zip(&right.children));
+        }
+        true
+    }
+}
+
+/// Dropped without recursion, since proofs can be deeper than the stack allows
+impl Drop for ProofTree {
+    fn drop(&mut self) {
+        let mut pending = mem::take(&mut self.children);
+        while let Some(mut node) = pending.pop() {
+            pending.append(&mut node.children);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::ProofTree;
+    use crate::datastructures::{
+        AtomSpatial::PointsTo,
+        Entailment, Expr,
+        Expr::Nil,
+        Formula,
+        Pure::True,
+        Record,
+        Spatial::{Emp, SepConj},
+    };
+    use crate::prover::{prove, Verdict};
+
+    #[test]
+    fn test_proof_tree() -> Result<(), String> {
+        let goal = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![PointsTo(Expr::new_var("x"), Record::next(Nil))]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![PointsTo(Expr::new_var("x"), Record::next(Nil))]),
+                Vec::new(),
+            ),
+        };
+
+        let proof = match prove(&goal) {
+            Ok(Verdict::Valid(proof)) => proof,
+            other => return Err(format!("Expected a proof but got {:?}", other)),
+        };
+
+        let mut rules = Vec::new();
+        let mut node: &ProofTree = &proof;
+        loop {
+            rules.push(node.rule);
+            match node.children.as_slice() {
+                [] => break,
+                [child] => node = child,
+                _ => return Err("Expected a linear derivation!".to_string()),
+            }
+        }
+
+        assert_eq!(goal, proof.goal);
+        assert_eq!(vec!["NilNotLVal", "Frame", "Cleanup", "Tautology"], rules);
+        assert_eq!(4, proof.size());
+        assert_eq!(4, proof.depth());
+        Ok(())
+    }
+
+    /// A linear proof of the given length, built without recursion
+    pub fn linear(length: usize) -> ProofTree {
+        let goal = Entailment {
+            antecedent: Formula(True, Emp, Vec::new()),
+            consequent: Formula(True, Emp, Vec::new()),
+        };
+        let mut proof = ProofTree {
+            goal: goal.clone(),
+            rule: "Tautology",
+            children: Vec::new(),
+        };
+        for _ in 1..length {
+            proof = ProofTree {
+                goal: goal.clone(),
+                rule: "Cleanup",
+                children: vec![proof],
+            };
+        }
+        proof
+    }
+
+    #[test]
+    fn test_deep_proof_tree() {
+        let deep = std::thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(|| {
+                let proof = linear(100_000);
+                let copy = proof.clone();
+                // Unlike assert_eq!, this does not format the trees, which would recurse
+                assert!(copy == proof);
+                assert!(copy != linear(99_999));
+                (copy.size(), copy.depth())
+            });
+        assert_eq!((100_000, 100_000), deep.unwrap().join().unwrap());
+    }
+}
@@ -0,0 +1,1102 @@
+use crate::congruence::Congruence;
+use crate::countermodel::{countermodel, Countermodel};
+use crate::datastructures::{
+    AtomSpatial,
+    AtomSpatial::PointsTo,
+    Entailment, Expr, Rule,
+    Spatial::{Emp, SepConj},
+    Variable,
+};
+use crate::error::Error;
+use crate::limits::{Governor, Limit, Limits};
+use crate::proof::ProofTree;
+use crate::rules::*;
+use crate::trace::{Trace, Verbosity};
+use std::{
+    collections::HashSet,
+    io, mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+/// The outcome of the decision procedure for a single entailment
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Verdict {
+    /// Contains a derivation of the entailment
+    Valid(ProofTree),
+    /// Contains the goal on which the proof search got stuck and, if one was found,
+    /// a concrete state falsifying the entailment. It is found for the stuck goal and then
+    /// extended back through the rule applications, so it only mentions the input's variables.
+    /// The rule based procedure always finds one, as it reports the entailment as unknown otherwise.
+    Invalid(Entailment, Option<Countermodel>),
+    /// The proof search could not decide the entailment for the given reason
+    Unknown(Reason),
+}
+
+/// Why the decision procedure could not decide an entailment
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum Reason {
+    /// The proof search was stopped by the given limit
+    Limit(Limit),
+    /// The proof search got stuck on the contained goal, but it still has existentials that no
+    /// tried witness instantiates and no countermodel was found, so the entailment may be valid
+    Existentials(Entailment),
+    /// The proof search got stuck on the contained goal, which has no existentials, but no state
+    /// falsifying the entailment was found, so the rules may just have missed a proof
+    NoCountermodel(Entailment),
+}
+
+impl From<Reason> for Error {
+    fn from(reason: Reason) -> Self {
+        match reason {
+            Reason::Limit(limit) => Error::ResourceLimit(limit),
+            Reason::Existentials(goal) | Reason::NoCountermodel(goal) => {
+                Error::Undecided(Box::new(goal))
+            }
+        }
+    }
+}
+
+pub const RULES: [&dyn Rule; 18] = [
+    // Axioms
+    &Tautology,
+    &Contradiction,
+    // Bring the entailment to normal form
+    &Substitution,
+    &EqReflexiveL,
+    &NilNotLVal,
+    &StarPartial,
+    &UnrollCollapse,
+    &TreeRoot,
+    &ExcludedMiddle,
+    // Simplification without normalform needed
+    &EqReflexiveR,
+    &EmptyPred,
+    &EmptyTree,
+    &Hypothesis,
+    &ExistsIntro,
+    // Simplifications that need normalform
+    &Frame,
+    &NonEmptyPred,
+    &UnfoldTree,
+    // Rule to cleanup empty vectors to enum counterparts
+    &Cleanup,
+];
+
+/// Counts the work done by a single run of the decision procedure
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Statistics {
+    /// Number of goals the proof search looked at
+    pub goals: usize,
+    /// Number of applications of every rule, in the order of [`RULES`]
+    pub applications: Vec<(&'static str, usize)>,
+    /// Number of goals that were not expanded, since an equivalent goal was already proven
+    pub memo_hits: usize,
+    /// Number of witnesses tried for the existentials of stuck goals
+    pub witnesses: usize,
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Statistics {
+            goals: 0,
+            applications: RULES.iter().map(|rule| (rule.name(), 0)).collect(),
+            memo_hits: 0,
+            witnesses: 0,
+        }
+    }
+}
+
+impl Statistics {
+    /// Adds the work recorded in `other`, e.g. by another thread
+    pub fn merge(&mut self, other: &Statistics) {
+        self.goals += other.goals;
+        for (mine, (_, count)) in self.applications.iter_mut().zip(&other.applications) {
+            mine.1 += count;
+        }
+        self.memo_hits += other.memo_hits;
+        self.witnesses += other.witnesses;
+    }
+}
+
+/// Decides whether the given entailment is valid
+pub fn prove(goal: &Entailment) -> Result<Verdict, Error> {
+    prove_with_statistics(goal, &mut Statistics::default())
+}
+
+/// Decides whether the given entailment is valid and records the work done in `statistics`
+pub fn prove_with_statistics(
+    goal: &Entailment,
+    statistics: &mut Statistics,
+) -> Result<Verdict, Error> {
+    let mut sink = io::sink();
+    prove_traced(
+        goal,
+        statistics,
+        &mut Trace::new(Verbosity::Quiet, false, &mut sink),
+    )
+}
+
+/// Decides whether the given entailment is valid and reports every step of the search to `trace`
+pub fn prove_traced(
+    goal: &Entailment,
+    statistics: &mut Statistics,
+    trace: &mut Trace,
+) -> Result<Verdict, Error> {
+    prove_with(goal, &Limits::default(), statistics, trace)
+}
+
+/// Decides whether the given entailment is valid within the given limits
+pub fn prove_with(
+    goal: &Entailment,
+    limits: &Limits,
+    statistics: &mut Statistics,
+    trace: &mut Trace,
+) -> Result<Verdict, Error> {
+    verdict(goal, ps(goal.clone(), limits, statistics, trace))
+}
+
+/// Decides whether the given entailment is valid within the given limits, proving the premisses
+/// of case splits concurrently on up to `threads` threads. As soon as one premiss turns out invalid
+/// the other threads stop, so the stuck goal need not be the one a sequential search reports.
+/// The search is not traced, since the threads would interleave their steps.
+pub fn prove_parallel(
+    goal: &Entailment,
+    limits: &Limits,
+    threads: usize,
+    statistics: &mut Statistics,
+) -> Result<Verdict, Error> {
+    let shared = Shared {
+        governor: Governor::new(*limits),
+        cancelled: AtomicBool::new(false),
+        failure: Mutex::new(None),
+    };
+    let result = split(goal.clone(), &[], 0, threads, &shared, statistics);
+    let failure = shared
+        .failure
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner());
+    verdict(goal, result.map_err(|error| failure.unwrap_or(error)))
+}
+
+fn verdict(goal: &Entailment, result: Result<ProofTree, Failure>) -> Result<Verdict, Error> {
+    let Failure { error, path } = match result {
+        Ok(proof) => return Ok(Verdict::Valid(proof)),
+        Err(failure) => failure,
+    };
+    match error {
+        Error::Invalid(stuck) => {
+            // The path ends with the entailment, the first goal of the search
+            let model = countermodel(&stuck)
+                .and_then(|model| path.iter().try_fold(model, |model, goal| model.lift(goal)))
+                .filter(|model| model.falsifies(goal));
+            // Only a state the semantics confirms makes the entailment invalid, the witnesses tried
+            // need not be all there are and the rules are not proven complete
+            match model {
+                Some(model) => Ok(Verdict::Invalid(*stuck, Some(model))),
+                None if stuck.consequent.get_existentials().is_empty() => {
+                    Ok(Verdict::Unknown(Reason::NoCountermodel(*stuck)))
+                }
+                None => Ok(Verdict::Unknown(Reason::Existentials(*stuck))),
+            }
+        }
+        Error::ResourceLimit(limit) => Ok(Verdict::Unknown(Reason::Limit(limit))),
+        error => Err(error),
+    }
+}
+
+/// The reason a proof search failed and, if it got stuck, the goals from the parent of the stuck
+/// goal up to the first goal of the search, to which the countermodel is extended
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct Failure {
+    error: Error,
+    path: Vec<Entailment>,
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        Failure {
+            error,
+            path: Vec::new(),
+        }
+    }
+}
+
+impl Failure {
+    /// Records that the failed goal was derived from the given goals, the nearest one first
+    fn below<'a>(mut self, goals: impl Iterator<Item = &'a Entailment>) -> Self {
+        self.path.extend(goals.cloned());
+        self
+    }
+}
+
+/// The name of the leaves of a proof tree whose goal is equivalent to one proven elsewhere in the tree
+pub const MEMO: &str = "Memo";
+
+/// The name of the nodes of a proof tree whose goal got stuck, but is proven once an existential
+/// is instantiated with a term of the goal. Their only child is the instantiated goal.
+pub const WITNESS: &str = "Witness";
+
+/// The values worth trying for an existential of a stuck goal: nil and the free variables, those
+/// next to the existential in an atom of the consequent first. If the existential is the address of
+/// a points-to atom and the antecedent only consists of cells, only their addresses are left.
+fn witnesses(goal: &Entailment, var: &Variable) -> Vec<Expr> {
+    let exists = Expr::Var(var.clone());
+    let atoms = match goal.consequent.get_spatial() {
+        SepConj(atoms) => atoms.as_slice(),
+        Emp => &[],
+    };
+    let mut witnesses: Vec<Expr> = std::iter::once(Expr::Nil)
+        .chain(goal.get_vars().into_iter().map(Expr::Var))
+        .collect();
+    let cells = match goal.antecedent.get_spatial() {
+        SepConj(cells) => cells.as_slice(),
+        Emp => &[],
+    };
+    // Any other atom of the antecedent may allocate cells without a name
+    let allocates = atoms
+        .iter()
+        .any(|atom| matches!(atom, PointsTo(address, _) if *address == exists));
+    if allocates && cells.iter().all(AtomSpatial::is_points_to) {
+        witnesses.retain(|witness| {
+            cells
+                .iter()
+                .any(|cell| matches!(cell, PointsTo(address, _) if address == witness))
+        });
+    }
+    let neighbours: Vec<&Expr> = atoms
+        .iter()
+        .filter(|atom| atom.exprs().any(|e| *e == exists))
+        .flat_map(AtomSpatial::exprs)
+        .collect();
+    witnesses.sort_by_key(|witness| !neighbours.contains(&witness));
+    witnesses
+}
+
+/// A goal to which a rule has been applied, but whose premisses are not all proven yet
+struct OpenGoal {
+    goal: Entailment,
+    /// The canonical form of the goal, under which it is remembered once proven
+    key: Entailment,
+    rule: &'static str,
+    /// The premisses still to prove, the next one last
+    pending: Vec<Entailment>,
+    children: Vec<ProofTree>,
+}
+
+/// The result of looking at a single goal
+enum Step {
+    /// A rule was applied, its premisses are still to be proven
+    Open(OpenGoal),
+    /// The goal needs no further work
+    Done(ProofTree),
+}
+
+/// The state of a proof search that all its threads share
+struct Shared {
+    governor: Governor,
+    /// Set as soon as any goal fails, since then the whole proof does
+    cancelled: AtomicBool,
+    /// The first goal that failed
+    failure: Mutex<Option<Failure>>,
+}
+
+impl Shared {
+    /// Records the failure and cancels the search, unless another failure came first
+    fn fail(&self, error: Failure) {
+        if !self.cancelled.swap(true, Ordering::SeqCst) {
+            if let Ok(mut failure) = self.failure.lock() {
+                *failure = Some(error);
+            }
+        }
+    }
+}
+
+/// The state of a single thread of a proof search
+struct Search<'a, 'b> {
+    shared: &'a Shared,
+    statistics: &'a mut Statistics,
+    trace: &'a mut Trace<'b>,
+    /// The canonical forms of all goals proven so far. The search stops at the first invalid goal,
+    /// so only valid ones need to be remembered.
+    proven: HashSet<Entailment>,
+}
+
+/// The actual decision procedure
+fn ps(
+    goal: Entailment,
+    limits: &Limits,
+    statistics: &mut Statistics,
+    trace: &mut Trace,
+) -> Result<ProofTree, Failure> {
+    let shared = Shared {
+        governor: Governor::new(*limits),
+        cancelled: AtomicBool::new(false),
+        failure: Mutex::new(None),
+    };
+    Search::new(&shared, statistics, trace).run(goal, 0)
+}
+
+/// Proves the goal with the given number of threads. Goals are expanded until a rule returns
+/// several premisses, which are then divided among the threads. `ancestors` are the goals the
+/// goal was derived from, starting with the first goal of the search.
+fn split(
+    goal: Entailment,
+    ancestors: &[&Entailment],
+    depth: usize,
+    threads: usize,
+    shared: &Shared,
+    statistics: &mut Statistics,
+) -> Result<ProofTree, Failure> {
+    let mut sink = io::sink();
+    let mut quiet = Trace::new(Verbosity::Quiet, false, &mut sink);
+    let mut search = Search::new(shared, statistics, &mut quiet);
+    if threads <= 1 {
+        return search
+            .run(goal, depth)
+            .map_err(|failure| failure.below(ancestors.iter().rev().cloned()))
+            .inspect_err(|failure| shared.fail(failure.clone()));
+    }
+    // The goals with a single premiss on the way to the case split
+    let mut chain: Vec<(Entailment, &'static str)> = Vec::new();
+    let (mut goal, mut depth) = (goal, depth);
+    let mut proof = loop {
+        let mut open = match search.expand(goal, depth) {
+            Ok(Step::Open(open)) => open,
+            Ok(Step::Done(proof)) => break proof,
+            Err(error) => {
+                let above = chain.iter().map(|(goal, _)| goal);
+                let failure =
+                    Failure::from(error).below(above.rev().chain(ancestors.iter().rev().cloned()));
+                shared.fail(failure.clone());
+                return Err(failure);
+            }
+        };
+        if open.pending.len() == 1 {
+            chain.push((open.goal, open.rule));
+            goal = open.pending.remove(0);
+            depth += 1;
+            continue;
+        }
+        if open.pending.is_empty() {
+            break ProofTree {
+                goal: open.goal,
+                rule: open.rule,
+                children: Vec::new(),
+            };
+        }
+        open.pending.reverse();
+        let groups = open.pending.len().min(threads);
+        let size = open.pending.len().div_ceil(groups);
+        let above: Vec<&Entailment> = (ancestors.iter().cloned())
+            .chain(chain.iter().map(|(goal, _)| goal))
+            .chain(std::iter::once(&open.goal))
+            .collect();
+        let above = above.as_slice();
+        let results = thread::scope(|scope| {
+            let handles: Vec<_> = open
+                .pending
+                .chunks(size)
+                .map(|premisses| {
+                    scope.spawn(move || {
+                        let mut statistics = Statistics::default();
+                        let proofs = premisses
+                            .iter()
+                            .map(|premiss| {
+                                split(
+                                    premiss.clone(),
+                                    above,
+                                    depth + 1,
+                                    threads / groups,
+                                    shared,
+                                    &mut statistics,
+                                )
+                            })
+                            .collect::<Result<Vec<_>, Failure>>();
+                        (proofs, statistics)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join())
+                .collect::<Vec<_>>()
+        });
+        for result in results {
+            let (proofs, statistics) = result
+                .map_err(|_| Error::Internal("A thread of the proof search panicked".into()))?;
+            search.statistics.merge(&statistics);
+            open.children.extend(proofs?);
+        }
+        break ProofTree {
+            goal: open.goal,
+            rule: open.rule,
+            children: open.children,
+        };
+    };
+    while let Some((goal, rule)) = chain.pop() {
+        proof = ProofTree {
+            goal,
+            rule,
+            children: vec![proof],
+        };
+    }
+    Ok(proof)
+}
+
+impl<'a, 'b> Search<'a, 'b> {
+    fn new(shared: &'a Shared, statistics: &'a mut Statistics, trace: &'a mut Trace<'b>) -> Self {
+        Search {
+            shared,
+            statistics,
+            trace,
+            proven: HashSet::new(),
+        }
+    }
+
+    /// Proves the goal found at the given depth. The premisses are proven depth first and left
+    /// to right, but with an explicit stack of the open goals on the path to the current goal instead
+    /// of recursion. Goals equivalent to an already proven one become [`MEMO`] leaves.
+    fn run(&mut self, goal: Entailment, depth: usize) -> Result<ProofTree, Failure> {
+        let mut path: Vec<OpenGoal> = Vec::new();
+        let mut current = match self.expand(goal, depth)? {
+            Step::Open(open) => open,
+            Step::Done(proof) => return Ok(proof),
+        };
+        loop {
+            if let Some(premiss) = current.pending.pop() {
+                match self.expand(premiss, depth + path.len() + 1) {
+                    Ok(Step::Open(next)) => path.push(mem::replace(&mut current, next)),
+                    Ok(Step::Done(proof)) => current.children.push(proof),
+                    Err(error) => {
+                        let above = path.iter().rev().map(|open| &open.goal);
+                        let goals = std::iter::once(&current.goal).chain(above);
+                        return Err(Failure::from(error).below(goals));
+                    }
+                }
+            } else {
+                self.proven.insert(current.key);
+                let proof = ProofTree {
+                    goal: current.goal,
+                    rule: current.rule,
+                    children: current.children,
+                };
+                match path.pop() {
+                    Some(mut parent) => {
+                        parent.children.push(proof);
+                        current = parent;
+                    }
+                    None => return Ok(proof),
+                }
+            }
+        }
+    }
+
+    /// Applies the first rule that has premisses for the goal, unless an equivalent goal is proven
+    fn expand(&mut self, goal: Entailment, depth: usize) -> Result<Step, Error> {
+        if self.shared.cancelled.load(Ordering::Relaxed) {
+            return Err(Error::Internal("The proof search was cancelled".into()));
+        }
+        let governor = &self.shared.governor;
+        governor.check(depth).map_err(Error::ResourceLimit)?;
+        self.statistics.goals += 1;
+        self.trace.goal(depth, &goal);
+        let key = goal.canonical();
+        if self.proven.contains(&key) {
+            self.statistics.memo_hits += 1;
+            self.trace.memo(depth);
+            return Ok(Step::Done(ProofTree {
+                goal,
+                rule: MEMO,
+                children: Vec::new(),
+            }));
+        }
+        let facts = Congruence::of(&goal);
+        for (index, &rule) in RULES.iter().enumerate() {
+            if rule.predicate(&goal, &facts) {
+                let premisses = rule.premisses(goal.clone(), &facts);
+                // Unguarded rules are tried on every goal, reporting them would only add noise
+                if premisses.is_some() || rule.guarded() {
+                    self.trace
+                        .rule(depth, rule.name(), premisses.as_ref().map(Vec::len));
+                }
+                if let Some(mut new_goals) = premisses {
+                    if new_goals.len() == 1 && new_goals[0] == goal {
+                        return Err(Error::Internal(format!(
+                            "Rule {} made no progress on {}",
+                            rule.name(),
+                            goal
+                        )));
+                    }
+                    governor.applied();
+                    self.statistics.applications[index].1 += 1;
+                    self.trace.premisses(depth, &new_goals);
+                    new_goals.reverse();
+                    return Ok(Step::Open(OpenGoal {
+                        goal,
+                        key,
+                        rule: rule.name(),
+                        pending: new_goals,
+                        children: Vec::new(),
+                    }));
+                }
+            }
+        }
+        self.trace.stuck(depth);
+        match self.instantiate(&goal, depth)? {
+            Some(instance) => {
+                self.proven.insert(key);
+                Ok(Step::Done(ProofTree {
+                    goal,
+                    rule: WITNESS,
+                    children: vec![instance],
+                }))
+            }
+            None => Err(Error::Invalid(Box::new(goal))),
+        }
+    }
+
+    /// The rules only instantiate an existential whose value is forced, so for a stuck goal every
+    /// free variable and nil is tried as the value of its first existential until one instance is
+    /// proven. Instances with an existential of their own try again once they get stuck.
+    fn instantiate(&mut self, goal: &Entailment, depth: usize) -> Result<Option<ProofTree>, Error> {
+        let var = match goal.consequent.get_existentials().first() {
+            Some(var) => var.clone(),
+            None => return Ok(None),
+        };
+        for witness in witnesses(goal, &var) {
+            self.statistics.witnesses += 1;
+            self.trace.witness(depth, &var, &witness);
+            let instance = Entailment {
+                antecedent: goal.antecedent.clone(),
+                consequent: goal.consequent.instantiate(&var, &witness),
+            };
+            match self.run(instance, depth + 1) {
+                Ok(proof) => return Ok(Some(proof)),
+                Err(Failure {
+                    error: Error::Invalid(_),
+                    ..
+                }) => continue,
+                Err(failure) => return Err(failure.error),
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::{
+        prove, prove_parallel, prove_with, prove_with_statistics, ps, verdict, Failure, Reason,
+        Statistics, Verdict, MEMO,
+    };
+    use crate::datastructures::{
+        AtomSpatial::{PointsTo, LS},
+        Entailment, Expr,
+        Expr::Nil,
+        Field, Formula,
+        Op::{AtomEq, AtomNeq},
+        Pure::{And, True},
+        Record,
+        Spatial::{Emp, SepConj},
+    };
+    use crate::error::Error;
+    use crate::generator::{Generator, GeneratorConfig};
+    use crate::limits::{Limit, Limits};
+    use crate::parser::parse;
+    use crate::trace::{Trace, Verbosity};
+    use std::{io, time::Duration};
+
+    #[test]
+    pub fn test_ps() {
+        let mut sink = io::sink();
+        let mut quiet = Trace::new(Verbosity::Quiet, false, &mut sink);
+        let valid = Entailment {
+            antecedent: Formula(
+                And(vec![AtomNeq(Expr::new_var("x"), Expr::new_var("y"))]),
+                SepConj(vec![
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("y"))),
+                    PointsTo(Expr::new_var("y"), Record::next(Nil)),
+                ]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![LS(Field::next(), Expr::new_var("x"), Nil)]),
+                Vec::new(),
+            ),
+        };
+        match prove(&valid) {
+            Ok(Verdict::Valid(proof)) => {
+                assert_eq!(valid, proof.goal);
+                let mut statistics = Statistics::default();
+                assert_eq!(
+                    Ok(proof.clone()),
+                    ps(valid, &Limits::default(), &mut statistics, &mut quiet)
+                );
+                assert_eq!(proof.size(), statistics.goals);
+                let applications = statistics.applications.iter().map(|(_, n)| n);
+                assert_eq!(
+                    proof.size(),
+                    applications.sum::<usize>() + statistics.memo_hits
+                );
+            }
+            other => panic!("Expected a valid verdict but got {:?}", other),
+        }
+
+        let invalid = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![
+                    PointsTo(Expr::new_var("x"), Record::next(Nil)),
+                    PointsTo(Expr::new_var("y"), Record::next(Nil)),
+                ]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                And(vec![AtomEq(Expr::new_var("x"), Expr::new_var("y"))]),
+                SepConj(vec![PointsTo(Expr::new_var("y"), Record::next(Nil))]),
+                Vec::new(),
+            ),
+        };
+        match prove(&invalid) {
+            Ok(Verdict::Invalid(_, Some(model))) => assert!(model.falsifies(&invalid)),
+            other => panic!("Expected an invalid verdict but got {:?}", other),
+        }
+        match ps(
+            invalid.clone(),
+            &Limits::default(),
+            &mut Statistics::default(),
+            &mut quiet,
+        ) {
+            Err(Failure {
+                error: Error::Invalid(stuck),
+                path,
+            }) => {
+                assert!(stuck.is_normal_form());
+                assert_eq!(Some(&invalid), path.last());
+            }
+            other => panic!("Expected an invalid entailment but got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn test_countermodel() -> Result<(), Error> {
+        // The stuck goal neither mentions a nor c, which the substitutions eliminated
+        let goal =
+            parse("True|SepConj[ls(Nil,a),ls(c,a)] |- True|SepConj[c->c,ls(a,b),ls(Nil,c)]")?;
+        match prove(&goal)? {
+            Verdict::Invalid(stuck, Some(model)) => {
+                assert_ne!(goal.get_vars(), stuck.get_vars());
+                let vars: Vec<_> = model.stack.keys().cloned().collect();
+                assert_eq!(goal.get_vars(), vars);
+                assert!(model.falsifies(&goal));
+            }
+            other => panic!("Expected an invalid verdict but got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_trees() -> Result<(), Error> {
+        let valid = [
+            "True|SepConj[x->[left:y,right:z],tree(y),tree(z)] |- True|SepConj[tree(x)]",
+            "True|SepConj[tree(x),x->y] |- True|SepConj[x->y]",
+            "True|SepConj[tree(x),tree(x)] |- True|Emp",
+            "And[Neq(y,Nil)]|SepConj[tree(y),x->[left:Nil,right:y,data:z]] |- True|SepConj[tree(x)]",
+        ];
+        for input in valid.iter() {
+            match prove(&parse(input)?)? {
+                Verdict::Valid(_) => {}
+                other => panic!("Expected {} to be valid but got {:?}", input, other),
+            }
+        }
+        let invalid = [
+            "True|SepConj[tree(x)] |- True|SepConj[ls[left](x,Nil)]",
+            "And[Neq(x,Nil)]|SepConj[tree(x)] |- True|SepConj[x->[left:Nil,right:Nil]]",
+            "True|SepConj[x->[left:y],tree(y)] |- True|SepConj[tree(x)]",
+        ];
+        for input in invalid.iter() {
+            let goal = parse(input)?;
+            match prove(&goal)? {
+                Verdict::Invalid(_, Some(model)) => assert!(model.falsifies(&goal)),
+                other => panic!("Expected {} to be invalid but got {:?}", input, other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_predicates() -> Result<(), Error> {
+        let lseg = "pred lseg(a,b) := emp & a=b | exists c. a->c * lseg(c,b) & a!=b;";
+        let dll = "pred dll(a,b,p,q) := emp & a=q & b=p \
+            | exists c. a->[next:c,prev:p] * dll(c,b,a,q) & a!=q & b!=p;";
+        let valid = [
+            format!(
+                "{} True|SepConj[lseg(x,y),lseg(y,Nil)] |- True|SepConj[lseg(x,Nil)]",
+                lseg
+            ),
+            format!("{} True|SepConj[lseg(x,y)] |- True|SepConj[ls(x,y)]", lseg),
+            format!("{} True|SepConj[ls(x,y)] |- True|SepConj[lseg(x,y)]", lseg),
+            format!(
+                "{} And[Neq(x,z),Neq(x,p)]|SepConj[x->[next:z,prev:p]] |- True|SepConj[dll(x,x,p,z)]",
+                dll
+            ),
+        ];
+        for input in valid.iter() {
+            match prove(&parse(input)?)? {
+                Verdict::Valid(_) => {}
+                other => panic!("Expected {} to be valid but got {:?}", input, other),
+            }
+        }
+        let invalid = [
+            format!(
+                "{} True|SepConj[lseg(x,y),lseg(y,z)] |- True|SepConj[lseg(x,z)]",
+                lseg
+            ),
+            format!("{} True|SepConj[lseg(x,y)] |- True|SepConj[x->y]", lseg),
+            format!(
+                "{} True|SepConj[x->[next:z,prev:p]] |- True|SepConj[dll(x,x,p,z)]",
+                dll
+            ),
+            format!(
+                "{} True|SepConj[dll(x,y,p,z),dll(z,w,y,Nil)] |- True|SepConj[dll(x,w,p,Nil)]",
+                dll
+            ),
+        ];
+        for input in invalid.iter() {
+            let goal = parse(input)?;
+            match prove(&goal)? {
+                Verdict::Invalid(_, Some(model)) => assert!(model.falsifies(&goal)),
+                other => panic!("Expected {} to be invalid but got {:?}", input, other),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_existentials() -> Result<(), Error> {
+        let valid = [
+            "True|SepConj[x->y,ls(y,Nil)] |- exists z. True|SepConj[x->z,ls(z,Nil)]",
+            "And[Neq(x,y)]|SepConj[x->y,y->Nil] |- exists z. True|SepConj[ls(x,z),z->Nil]",
+            "True|SepConj[x->y] |- exists z, w. And[Eq(z,y),Neq(w,x)]|SepConj[x->z]",
+            "True|SepConj[ls(x,y)] |- exists z. True|SepConj[ls(x,z)]",
+            // No rule forces the witness, so the stuck goal has to try them
+            "True|SepConj[ls(x,y)] |- exists z. True|SepConj[ls(x,z),ls(z,y)]",
+            "True|SepConj[ls(a,a)] |- exists d. True|SepConj[tree(d)]",
+            "And[Neq(x,y)]|SepConj[x->y] |- exists z. True|SepConj[ls(x,z),ls(z,y)]",
+        ];
+        for input in valid.iter() {
+            match prove(&parse(input)?)? {
+                Verdict::Valid(_) => {}
+                other => panic!("Expected {} to be valid but got {:?}", input, other),
+            }
+        }
+        let invalid = [
+            "True|SepConj[x->y,ls(y,Nil)] |- exists z. True|SepConj[x->z,z->Nil]",
+            "True|SepConj[x->y] |- exists z. And[Neq(z,y)]|SepConj[x->z]",
+            // The segments may have other lengths than the cells of the consequent
+            "And[Neq(x,y)]|SepConj[ls(x,y)] |- exists z. True|SepConj[x->z,z->y]",
+            "And[Neq(x,Nil)]|SepConj[ls(x,Nil)] |- exists z. True|SepConj[x->z,z->Nil]",
+            "And[Neq(x,y)]|SepConj[ls(x,y)] |- exists z,w. True|SepConj[x->z,z->w,ls(w,y)]",
+            "And[Neq(x,y)]|SepConj[ls[fa](x,y)] |- exists z. True|SepConj[x->[fa:z],z->[fa:y]]",
+            // For x=y the cell is a cycle, which no segment covers
+            "True|SepConj[x->y] |- exists z. True|SepConj[ls(x,z),ls(z,y)]",
+        ];
+        for input in invalid.iter() {
+            let goal = parse(input)?;
+            match prove(&goal)? {
+                Verdict::Invalid(_, Some(model)) => assert!(model.falsifies(&goal)),
+                other => panic!("Expected {} to be invalid but got {:?}", input, other),
+            }
+        }
+        // Without a countermodel the existentials of the stuck goal might just lack the right witness
+        let stuck = parse("True|SepConj[x->y] |- exists z. True|SepConj[x->z]")?;
+        assert_eq!(
+            Verdict::Unknown(Reason::Existentials(stuck.clone())),
+            verdict(
+                &stuck,
+                Err(Failure::from(Error::Invalid(Box::new(stuck.clone()))))
+            )?
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_verdict() -> Result<(), Error> {
+        let stuck = |goal: &Entailment| Err(Failure::from(Error::Invalid(Box::new(goal.clone()))));
+        // Invalid only with a state that falsifies the entailment
+        let invalid = parse("True|SepConj[x->y] |- True|Emp")?;
+        match verdict(&invalid, stuck(&invalid))? {
+            Verdict::Invalid(goal, Some(model)) => {
+                assert_eq!(invalid, goal);
+                assert!(model.falsifies(&invalid));
+            }
+            other => panic!("Expected an invalid verdict but got {:?}", other),
+        }
+        // The rules may miss a proof, which a valid entailment has no countermodel to reveal
+        let valid = parse("True|SepConj[x->y] |- True|SepConj[x->y]")?;
+        assert_eq!(
+            Verdict::Unknown(Reason::NoCountermodel(valid.clone())),
+            verdict(&valid, stuck(&valid))?
+        );
+        // A countermodel of the stuck goal has to falsify the entailment as well
+        let entailment = parse("True|SepConj[x->y] |- True|SepConj[x->y]")?;
+        let unrelated = parse("True|SepConj[x->y] |- True|Emp")?;
+        let failure = Failure::from(Error::Invalid(Box::new(unrelated.clone())));
+        assert_eq!(
+            Verdict::Unknown(Reason::NoCountermodel(unrelated)),
+            verdict(&entailment, Err(failure))?
+        );
+        assert_eq!(
+            Err(Error::Internal("no progress".to_string())),
+            verdict(
+                &valid,
+                Err(Failure::from(Error::Internal("no progress".to_string())))
+            )
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_no_progress() {
+        // NilNotLVal used to return this goal unchanged forever
+        let no_progress = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![LS(
+                    Field::next(),
+                    Expr::new_var("x"),
+                    Expr::new_var("y"),
+                )]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![LS(
+                    Field::next(),
+                    Expr::new_var("x"),
+                    Expr::new_var("y"),
+                )]),
+                Vec::new(),
+            ),
+        };
+        match prove(&no_progress) {
+            Ok(Verdict::Valid(_)) => {}
+            other => panic!("Expected a valid verdict but got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn test_hypothesis_kept() {
+        // Found by the generator: Hypothesis consumed c != b, which the
+        // unrolled ls(c, c) still needed
+        let goal = Entailment {
+            antecedent: Formula(
+                And(vec![
+                    AtomNeq(Expr::new_var("c"), Expr::new_var("b")),
+                    AtomEq(Expr::new_var("b"), Expr::new_var("a")),
+                ]),
+                Emp,
+                Vec::new(),
+            ),
+            consequent: Formula(
+                And(vec![
+                    AtomNeq(Expr::new_var("a"), Expr::new_var("c")),
+                    AtomNeq(Expr::new_var("c"), Expr::new_var("b")),
+                ]),
+                SepConj(vec![LS(
+                    Field::next(),
+                    Expr::new_var("c"),
+                    Expr::new_var("c"),
+                )]),
+                Vec::new(),
+            ),
+        };
+        match prove(&goal) {
+            Ok(Verdict::Valid(_)) => {}
+            other => panic!("Expected a valid verdict but got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn test_fresh_capture() {
+        // Unrolling ls(z,nil) used to introduce zx, capturing the user's variable
+        let captured = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![
+                    LS(Field::next(), Expr::new_var("z"), Nil),
+                    PointsTo(Expr::new_var("zx"), Record::next(Nil)),
+                ]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![PointsTo(Expr::new_var("zx"), Record::next(Nil))]),
+                Vec::new(),
+            ),
+        };
+        match prove(&captured) {
+            Ok(Verdict::Invalid(_, Some(model))) => assert!(model.falsifies(&captured)),
+            other => panic!("Expected an invalid verdict but got {:?}", other),
+        }
+    }
+
+    #[test]
+    pub fn test_memo() {
+        // Both cases of x = y end in x != nil | emp |- true | emp, up to renaming
+        let goal = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y")),
+                )]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y")),
+                )]),
+                Vec::new(),
+            ),
+        };
+        let mut statistics = Statistics::default();
+        let proof = match prove_with_statistics(&goal, &mut statistics) {
+            Ok(Verdict::Valid(proof)) => proof,
+            other => panic!("Expected a valid verdict but got {:?}", other),
+        };
+        assert_eq!(1, statistics.memo_hits);
+        let mut leaves = vec![&proof];
+        let mut memo = Vec::new();
+        while let Some(node) = leaves.pop() {
+            if node.rule == MEMO {
+                memo.push(&node.goal);
+            }
+            leaves.extend(&node.children);
+        }
+        assert_eq!(1, memo.len());
+        assert!(matches!(prove(memo[0]), Ok(Verdict::Valid(_))));
+    }
+
+    /// x1->x2 * ... * xn->nil |- ls(x1,nil), whose proof is deeper than n^2/2
+    fn chain(n: usize) -> Entailment {
+        let var = |i: usize| Expr::new_var(&format!("x{}", i));
+        let mut atoms: Vec<_> = (1..n)
+            .map(|i| PointsTo(var(i), Record::next(var(i + 1))))
+            .collect();
+        atoms.push(PointsTo(var(n), Record::next(Nil)));
+        Entailment {
+            antecedent: Formula(True, SepConj(atoms), Vec::new()),
+            consequent: Formula(
+                True,
+                SepConj(vec![LS(Field::next(), var(1), Nil)]),
+                Vec::new(),
+            ),
+        }
+    }
+
+    #[test]
+    pub fn test_limits() -> Result<(), Error> {
+        let goal = chain(4);
+        let mut sink = io::sink();
+        let mut quiet = Trace::new(Verbosity::Quiet, false, &mut sink);
+        let mut prove_within =
+            |limits: Limits| prove_with(&goal, &limits, &mut Statistics::default(), &mut quiet);
+        let proof = match prove_within(Limits::default())? {
+            Verdict::Valid(proof) => proof,
+            other => panic!("Expected a valid verdict but got {:?}", other),
+        };
+
+        let exact = Limits {
+            max_steps: Some(proof.size()),
+            max_depth: Some(proof.depth() - 1),
+            timeout: Some(Duration::from_secs(60)),
+        };
+        assert!(matches!(prove_within(exact)?, Verdict::Valid(_)));
+        let steps = Limits {
+            max_steps: Some(proof.size() - 1),
+            ..exact
+        };
+        assert_eq!(
+            Verdict::Unknown(Reason::Limit(Limit::Steps(proof.size() - 1))),
+            prove_within(steps)?
+        );
+        let depth = Limits {
+            max_depth: Some(proof.depth() - 2),
+            ..exact
+        };
+        assert_eq!(
+            Verdict::Unknown(Reason::Limit(Limit::Depth(proof.depth() - 2))),
+            prove_within(depth)?
+        );
+        let timeout = Limits {
+            timeout: Some(Duration::ZERO),
+            ..exact
+        };
+        assert_eq!(
+            Verdict::Unknown(Reason::Limit(Limit::Timeout(Duration::ZERO))),
+            prove_within(timeout)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_parallel() -> Result<(), Error> {
+        let config = GeneratorConfig {
+            variables: 4,
+            ..GeneratorConfig::default()
+        };
+        for goal in Generator::new(config, 3).take(200) {
+            let mut statistics = Statistics::default();
+            match (
+                prove(&goal)?,
+                prove_parallel(&goal, &Limits::default(), 4, &mut statistics)?,
+            ) {
+                (Verdict::Valid(_), Verdict::Valid(proof)) => {
+                    assert_eq!(goal, proof.goal);
+                    assert_eq!(proof.size(), statistics.goals);
+                }
+                (Verdict::Invalid(_, _), Verdict::Invalid(_, Some(model))) => {
+                    assert!(model.falsifies(&goal))
+                }
+                (sequential, parallel) => panic!(
+                    "{} is {:?} sequentially but {:?} in parallel",
+                    goal, sequential, parallel
+                ),
+            }
+        }
+        let limits = Limits {
+            max_steps: Some(10),
+            ..Limits::default()
+        };
+        assert_eq!(
+            Verdict::Unknown(Reason::Limit(Limit::Steps(10))),
+            prove_parallel(&chain(6), &limits, 4, &mut Statistics::default())?
+        );
+        Ok(())
+    }
+
+    #[test]
+    pub fn test_deep_proof() {
+        // The recursive search needed a stack frame per goal on the path to the current goal, and
+        // so did cloning and comparing the proof
+        let deep = std::thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(|| match prove(&chain(40)) {
+                Ok(Verdict::Valid(proof)) => {
+                    assert!(proof.clone() == proof);
+                    proof.depth()
+                }
+                other => panic!("Expected a valid verdict but got {:?}", other),
+            });
+        let depth = deep.unwrap().join().unwrap();
+        assert!(depth > 800, "The proof has only depth {}", depth);
+    }
+}
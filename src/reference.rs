@@ -0,0 +1,565 @@
+use crate::countermodel::Countermodel;
+use crate::datastructures::{
+    AtomSpatial,
+    AtomSpatial::{PointsTo, Pred, Tree, DLS, LS},
+    Entailment, Expr, Field, Predicate,
+    Spatial::SepConj,
+    Variable,
+};
+use crate::semantics::{
+    eval, eval_record, satisfies, satisfies_op, satisfies_pure, Cell, Heap, Stack, Value,
+};
+use std::collections::BTreeMap;
+
+/// Slow but obviously correct decision procedure used as a reference for the rule based one.
+/// It enumerates all models of the antecedent over a bounded number of locations and checks the
+/// consequent in each of them with the model checker. The default bound gives every variable and
+/// every list segment of the antecedent its own location, which suffices as list segments never
+/// need to be longer than two cells to falsify an entailment without existentials. Every existential
+/// of the consequent may name one more cell inside a segment, tree or predicate, so it adds a
+/// location for each of them.
+pub fn decide(goal: &Entailment) -> Result<(), Countermodel> {
+    decide_bounded(goal, default_bound(goal))
+}
+
+/// The number of locations needed to find a countermodel for the given entailment:
+/// one for every variable, segment, leaf of a tree with two leaves and existential of a
+/// user-defined predicate, and one for every existential of the consequent and inductive atom.
+pub fn default_bound(goal: &Entailment) -> usize {
+    let named = goal.consequent.get_existentials().len();
+    let anonymous = match goal.antecedent.get_spatial() {
+        SepConj(atoms) => (atoms.iter())
+            .map(|atom| match atom {
+                PointsTo(..) => 0,
+                Tree(_) => 2 + named,
+                Pred(definition, _) => definition.existentials.len() + named,
+                _ => 1 + named,
+            })
+            .sum(),
+        _ => 0,
+    };
+    goal.get_vars().len() + anonymous
+}
+
+/// Decides the entailment for all states with at most `locations` different locations.
+pub fn decide_bounded(goal: &Entailment, locations: usize) -> Result<(), Countermodel> {
+    let vars = goal.get_vars();
+    let mut enumerator = Enumerator {
+        goal,
+        vars: &vars,
+        locations,
+        stack: Stack::new(),
+    };
+    match enumerator.stacks(0, 0) {
+        Some(model) => Err(model),
+        None => Ok(()),
+    }
+}
+
+struct Enumerator<'a> {
+    goal: &'a Entailment,
+    vars: &'a [Variable],
+    locations: usize,
+    stack: Stack,
+}
+
+impl<'a> Enumerator<'a> {
+    /// Assigns every variable to nil or a location. Locations are handed out in increasing order
+    /// as unused locations are indistinguishable.
+    fn stacks(&mut self, index: usize, used: usize) -> Option<Countermodel> {
+        if index == self.vars.len() {
+            if !satisfies_pure(self.goal.antecedent.get_pure(), &self.stack) {
+                return None;
+            }
+            let atoms: &[AtomSpatial] = match self.goal.antecedent.get_spatial() {
+                SepConj(atoms) => atoms,
+                _ => &[],
+            };
+            return self.heaps(atoms, &mut Heap::new(), used);
+        }
+
+        let mut values = vec![Value::Nil];
+        values.extend((1..=(used + 1).min(self.locations)).map(Value::Loc));
+        for value in values {
+            self.stack.insert(self.vars[index].clone(), value);
+            let used = match value {
+                Value::Loc(loc) => used.max(loc),
+                Value::Nil => used,
+            };
+            if let Some(model) = self.stacks(index + 1, used) {
+                return Some(model);
+            }
+        }
+        self.stack.remove(&self.vars[index]);
+        None
+    }
+
+    /// Enumerates the heaps on which the given atoms hold and checks the consequent on each of them.
+    fn heaps(&self, atoms: &[AtomSpatial], heap: &mut Heap, used: usize) -> Option<Countermodel> {
+        match atoms.split_first() {
+            None => {
+                if satisfies(&self.goal.antecedent, &self.stack, heap)
+                    && !satisfies(&self.goal.consequent, &self.stack, heap)
+                {
+                    Some(Countermodel {
+                        stack: self.stack.clone(),
+                        heap: heap.clone(),
+                    })
+                } else {
+                    None
+                }
+            }
+            Some((PointsTo(l, r), rest)) => {
+                let (l, r) = (eval(l, &self.stack)?, eval_record(r, &self.stack)?);
+                match l {
+                    Value::Loc(loc) if !heap.contains_key(&loc) => {
+                        heap.insert(loc, r);
+                        let result = self.heaps(rest, heap, used);
+                        heap.remove(&loc);
+                        result
+                    }
+                    _ => None,
+                }
+            }
+            Some((LS(field, l, r), rest)) => {
+                let (l, r) = (eval(l, &self.stack)?, eval(r, &self.stack)?);
+                self.segment(field, l, r, rest, heap, used)
+            }
+            Some((DLS(first, last, prev, next), rest)) => {
+                let stack = &self.stack;
+                let (first, last) = (eval(first, stack)?, eval(last, stack)?);
+                let (prev, next) = (eval(prev, stack)?, eval(next, stack)?);
+                self.doubly([first, last, prev, next], rest, heap, used)
+            }
+            Some((Tree(root), rest)) => {
+                let root = eval(root, &self.stack)?;
+                self.tree(&mut vec![root], rest, heap, used)
+            }
+            Some((Pred(definition, args), rest)) => {
+                let mut env = Stack::new();
+                for (param, arg) in definition.params.iter().zip(args) {
+                    env.insert(param.clone(), eval(arg, &self.stack)?);
+                }
+                self.predicate(definition, &mut env, rest, heap, used)
+            }
+        }
+    }
+
+    /// Enumerates all acyclic paths along `field` from `current` to `end` in the remaining heap.
+    /// Their cells have no other fields, as further fields can not falsify the consequent.
+    fn segment(
+        &self,
+        field: &Field,
+        current: Value,
+        end: Value,
+        rest: &[AtomSpatial],
+        heap: &mut Heap,
+        used: usize,
+    ) -> Option<Countermodel> {
+        if current == end {
+            return self.heaps(rest, heap, used);
+        }
+        let loc = match current {
+            Value::Loc(loc) if !heap.contains_key(&loc) => loc,
+            _ => return None,
+        };
+
+        let mut values = vec![Value::Nil];
+        values.extend((1..=(used + 1).min(self.locations)).map(Value::Loc));
+        for next in values {
+            heap.insert(loc, Cell(BTreeMap::from([(field.clone(), next)])));
+            let used = match next {
+                Value::Loc(next) => used.max(next),
+                Value::Nil => used,
+            };
+            let result = self.segment(field, next, end, rest, heap, used);
+            heap.remove(&loc);
+            if result.is_some() {
+                return result;
+            }
+        }
+        None
+    }
+}
+
+impl Enumerator<'_> {
+    /// Enumerates all doubly linked segments `[first, last, prev, next]` in the remaining heap.
+    fn doubly(
+        &self,
+        [first, last, prev, next]: [Value; 4],
+        rest: &[AtomSpatial],
+        heap: &mut Heap,
+        used: usize,
+    ) -> Option<Countermodel> {
+        match (first == next, last == prev) {
+            (true, true) => return self.heaps(rest, heap, used),
+            (false, false) => {}
+            _ => return None,
+        }
+        let loc = match first {
+            Value::Loc(loc) if !heap.contains_key(&loc) => loc,
+            _ => return None,
+        };
+
+        let mut values = vec![Value::Nil];
+        values.extend((1..=(used + 1).min(self.locations)).map(Value::Loc));
+        for successor in values {
+            let fields = [(Field::next(), successor), (Field::prev(), prev)];
+            heap.insert(loc, Cell(BTreeMap::from(fields)));
+            let used = match successor {
+                Value::Loc(successor) => used.max(successor),
+                Value::Nil => used,
+            };
+            let result = self.doubly([successor, last, first, next], rest, heap, used);
+            heap.remove(&loc);
+            if result.is_some() {
+                return result;
+            }
+        }
+        None
+    }
+
+    /// Enumerates all forests with the given roots in the remaining heap, the last root first.
+    fn tree(
+        &self,
+        roots: &mut Vec<Value>,
+        rest: &[AtomSpatial],
+        heap: &mut Heap,
+        used: usize,
+    ) -> Option<Countermodel> {
+        let loc = match roots.pop() {
+            None => return self.heaps(rest, heap, used),
+            Some(Value::Nil) => {
+                let result = self.tree(roots, rest, heap, used);
+                roots.push(Value::Nil);
+                return result;
+            }
+            Some(Value::Loc(loc)) if !heap.contains_key(&loc) => loc,
+            Some(root) => {
+                roots.push(root);
+                return None;
+            }
+        };
+
+        // Fresh locations are handed out in increasing order, also to the right child.
+        // A child cannot be a cell that already belongs to this or another tree.
+        let candidates = |used: usize, heap: &Heap, roots: &[Value], sibling: Value| {
+            let fresh = (1..=(used + 1).min(self.locations)).map(Value::Loc);
+            let free = |child: &Value| match child {
+                Value::Loc(cell) => {
+                    *cell != loc && !heap.contains_key(cell) && !roots.contains(child)
+                }
+                Value::Nil => true,
+            };
+            (std::iter::once(Value::Nil).chain(fresh))
+                .filter(|child| free(child) && (*child == Value::Nil || *child != sibling))
+                .collect::<Vec<_>>()
+        };
+        let grow = |used: usize, child: Value| match child {
+            Value::Loc(child) => used.max(child),
+            Value::Nil => used,
+        };
+        let mut result = None;
+        'children: for left in candidates(used, heap, roots, Value::Nil) {
+            for right in candidates(grow(used, left), heap, roots, left) {
+                let fields = [(Field::left(), left), (Field::right(), right)];
+                heap.insert(loc, Cell(BTreeMap::from(fields)));
+                roots.push(right);
+                roots.push(left);
+                result = self.tree(roots, rest, heap, grow(grow(used, left), right));
+                roots.truncate(roots.len() - 2);
+                heap.remove(&loc);
+                if result.is_some() {
+                    break 'children;
+                }
+            }
+        }
+        roots.push(Value::Loc(loc));
+        result
+    }
+
+    /// Enumerates all unfoldings of a user-defined predicate whose parameters are bound in `env`
+    fn predicate(
+        &self,
+        definition: &Predicate,
+        env: &mut Stack,
+        rest: &[AtomSpatial],
+        heap: &mut Heap,
+        used: usize,
+    ) -> Option<Countermodel> {
+        if definition.base.iter().all(|op| satisfies_op(op, env)) {
+            return self.heaps(rest, heap, used);
+        }
+        match env.get(&definition.params[0]) {
+            Some(Value::Loc(loc)) if !heap.contains_key(loc) => {}
+            _ => return None,
+        }
+        self.existentials(definition, 0, env, rest, heap, used)
+    }
+
+    /// Assigns the existentials of the nonempty case from the `index`th on and unfolds it
+    fn existentials(
+        &self,
+        definition: &Predicate,
+        index: usize,
+        env: &mut Stack,
+        rest: &[AtomSpatial],
+        heap: &mut Heap,
+        used: usize,
+    ) -> Option<Countermodel> {
+        if index < definition.existentials.len() {
+            let var = &definition.existentials[index];
+            let mut values = vec![Value::Nil];
+            values.extend((1..=(used + 1).min(self.locations)).map(Value::Loc));
+            for value in values {
+                env.insert(var.clone(), value);
+                let used = match value {
+                    Value::Loc(value) => used.max(value),
+                    Value::Nil => used,
+                };
+                let result = self.existentials(definition, index + 1, env, rest, heap, used);
+                if result.is_some() {
+                    return result;
+                }
+            }
+            return None;
+        }
+
+        if !definition.step.iter().all(|op| satisfies_op(op, env)) {
+            return None;
+        }
+        let loc = match eval(&Expr::Var(definition.params[0].clone()), env)? {
+            Value::Loc(loc) => loc,
+            Value::Nil => return None,
+        };
+        let mut next = Stack::new();
+        for (param, arg) in definition.params.iter().zip(&definition.call) {
+            next.insert(param.clone(), eval(arg, env)?);
+        }
+        heap.insert(loc, eval_record(&definition.record, env)?);
+        let result = self.predicate(definition, &mut next, rest, heap, used);
+        heap.remove(&loc);
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decide, decide_bounded, default_bound};
+    use crate::datastructures::{
+        AtomSpatial::{Pred, DLS, LS},
+        Entailment, Formula, Predicate,
+        Spatial::SepConj,
+    };
+    use crate::error::Error;
+    use crate::generator::{Generator, GeneratorConfig};
+    use crate::graph;
+    use crate::parser::parse;
+    use crate::prover::{prove, Verdict};
+    use std::sync::Arc;
+
+    /// Checks the verdict of the prover against the reference. Invalid goals need a countermodel
+    /// that falsifies them and the prover must not leave a goal undecided.
+    fn check_verdict(goal: &Entailment) {
+        let expected = decide(goal);
+        match prove(goal) {
+            Ok(Verdict::Valid(_)) => assert!(expected.is_ok(), "{}", goal),
+            Ok(Verdict::Invalid(_, Some(model))) => {
+                assert!(expected.is_err(), "{}", goal);
+                assert!(model.falsifies(goal), "{}", goal);
+            }
+            actual => panic!("{}: {:?}", goal, actual),
+        }
+    }
+
+    /// Compares the prover with the reference on the goals generated from the seed
+    fn cross_check(config: GeneratorConfig, seed: u64, count: usize) {
+        for goal in Generator::new(config, seed).take(count) {
+            check_verdict(&goal);
+        }
+    }
+
+    #[test]
+    fn test_differential() {
+        cross_check(GeneratorConfig::default(), 0x5eed_a11c, 1000);
+    }
+
+    #[test]
+    fn test_records() {
+        let config = GeneratorConfig {
+            variables: 4,
+            segments: 3,
+            fields: 3,
+            ..GeneratorConfig::default()
+        };
+        for goal in Generator::new(config, 0xf1e1d).take(1000) {
+            let expected = decide(&goal).is_ok();
+            let actual = matches!(prove(&goal), Ok(Verdict::Valid(_)));
+            assert_eq!(expected, actual, "{}", goal);
+            assert_eq!(Ok(expected), graph::decide(&goal), "{}", goal);
+        }
+    }
+
+    #[test]
+    fn test_dls() {
+        let config = GeneratorConfig {
+            variables: 3,
+            points_to: 2,
+            segments: 0,
+            dls: 2,
+            ..GeneratorConfig::default()
+        };
+        cross_check(config, 0xd15, 500);
+    }
+
+    #[test]
+    fn test_trees() {
+        let config = GeneratorConfig {
+            variables: 2,
+            points_to: 2,
+            segments: 1,
+            trees: 2,
+            ..GeneratorConfig::default()
+        };
+        cross_check(config, 0x7ee, 500);
+    }
+
+    #[test]
+    fn test_existentials() {
+        let config = GeneratorConfig {
+            existentials: 2,
+            ..GeneratorConfig::default()
+        };
+        cross_check(config, 0xe815, 1000);
+    }
+
+    #[test]
+    fn test_bound() -> Result<(), Error> {
+        // x and y, a cell of the segment and one more for z to name
+        let goal = parse("True|SepConj[ls(x,y)] |- exists z. True|SepConj[x->z,ls(z,y)]")?;
+        assert_eq!(4, default_bound(&goal));
+
+        let config = GeneratorConfig {
+            variables: 2,
+            segments: 1,
+            existentials: 3,
+            ..GeneratorConfig::default()
+        };
+        for goal in Generator::new(config, 0xb0d).take(300) {
+            let larger = decide_bounded(&goal, default_bound(&goal) + 2);
+            assert_eq!(decide(&goal).is_ok(), larger.is_ok(), "{}", goal);
+        }
+        Ok(())
+    }
+
+    /// Segments against chains of cells and segments through existentials, which random goals
+    /// hardly ever produce
+    #[test]
+    fn test_existential_chains() -> Result<(), Error> {
+        let links = ["{}->{}", "ls({},{})", "{}->[fa:{}]", "ls[fa]({},{})"];
+        let existentials = ["z", "w", "v"];
+        for end in ["y", "Nil"].iter() {
+            for antecedent in ["ls({x},{e})", "ls[fa]({x},{e})", "x->u,ls(u,{e})"].iter() {
+                let antecedent = antecedent.replace("{x}", "x").replace("{e}", end);
+                for pure in ["True".to_string(), format!("And[Neq(x,{})]", end)].iter() {
+                    for length in 1..=existentials.len() + 1 {
+                        for choice in 0..links.len().pow(length as u32) {
+                            let bound = &existentials[..length - 1];
+                            let nodes: Vec<&str> = std::iter::once("x")
+                                .chain(bound.iter().cloned())
+                                .chain(std::iter::once(*end))
+                                .collect();
+                            let atoms: Vec<String> = (0..length)
+                                .map(|i| {
+                                    let link =
+                                        links[choice / links.len().pow(i as u32) % links.len()];
+                                    link.replacen("{}", nodes[i], 1)
+                                        .replacen("{}", nodes[i + 1], 1)
+                                })
+                                .collect();
+                            let exists = match bound {
+                                [] => String::new(),
+                                _ => format!("exists {}. ", bound.join(",")),
+                            };
+                            let input = format!(
+                                "{}|SepConj[{}] |- {}True|SepConj[{}]",
+                                pure,
+                                antecedent,
+                                exists,
+                                atoms.join(",")
+                            );
+                            check_verdict(&parse(&input)?);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The definition of the predicate declared by the input and used by the atom
+    fn declared(declaration: &str, atom: &str) -> Arc<Predicate> {
+        let input = format!("{} True|Emp |- True|SepConj[{}]", declaration, atom);
+        match parse(&input).map(|goal| goal.consequent) {
+            Ok(Formula(_, SepConj(atoms), _)) => match &atoms[0] {
+                Pred(definition, _) => definition.clone(),
+                _ => panic!("{} is no user-defined predicate", atom),
+            },
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_predicates() {
+        let lseg = declared(
+            "pred lseg(a,b) := emp & a=b | exists c. a->c * lseg(c,b) & a!=b;",
+            "lseg(x,y)",
+        );
+        let dll = declared(
+            "pred dll(a,b,p,q) := emp & a=q & b=p | exists c. a->[next:c,prev:p] * dll(c,b,a,q) & a!=q & b!=p;",
+            "dll(x,y,x,y)",
+        );
+        // Replaces the built-in segments by their user-defined counterparts
+        let rewrite = |goal: &Entailment| {
+            let formula = |formula: &Formula| {
+                let atoms = match formula.get_spatial() {
+                    SepConj(atoms) => atoms.iter().map(|atom| match atom {
+                        LS(_, l, r) => Pred(lseg.clone(), vec![l.clone(), r.clone()]),
+                        DLS(a, b, c, d) => {
+                            Pred(dll.clone(), vec![a, b, c, d].into_iter().cloned().collect())
+                        }
+                        _ => atom.clone(),
+                    }),
+                    _ => return formula.clone(),
+                };
+                Formula(
+                    formula.get_pure().clone(),
+                    SepConj(atoms.collect()),
+                    formula.2.clone(),
+                )
+            };
+            Entailment {
+                antecedent: formula(&goal.antecedent),
+                consequent: formula(&goal.consequent),
+            }
+        };
+        let dls = GeneratorConfig {
+            segments: 1,
+            dls: 1,
+            ..GeneratorConfig::default()
+        };
+        let configs = [(GeneratorConfig::default(), 0x15e6), (dls, 0xd11)];
+        for (config, seed) in configs.iter() {
+            for builtin in Generator::new(config.clone(), *seed).take(300) {
+                let goal = rewrite(&builtin);
+                let expected = decide(&builtin);
+                assert_eq!(expected.is_ok(), decide(&goal).is_ok(), "{}", goal);
+                match prove(&goal) {
+                    Ok(Verdict::Valid(_)) => assert!(expected.is_ok(), "{}", goal),
+                    Ok(Verdict::Invalid(_, _)) => assert!(expected.is_err(), "{}", goal),
+                    actual => panic!("{}: {:?}", goal, actual),
+                }
+            }
+        }
+    }
+}
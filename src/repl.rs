@@ -0,0 +1,212 @@
+use crate::{
+    congruence::Congruence,
+    datastructures::Entailment,
+    error::Error,
+    parser::parse,
+    printer::Unicode,
+    prover::{prove, Verdict, RULES},
+};
+use std::fmt::Write;
+
+pub const HELP: &str = "Commands:
+  <entailment>   start proving the entailment, e.g. True|SepConj[x->Nil] |- True|SepConj[x->Nil]
+  goals          show the open goals, the first one is the current goal
+  rules          show the rules whose predicate holds on the current goal and which apply
+  apply <rule>   replace the current goal by the premisses of the rule
+  auto           let the decision procedure discharge all open goals
+  undo           revert the last apply or auto
+  help           show this message
+  quit           leave the repl";
+
+/// The state of an interactive proof: the open goals and their earlier versions for undo
+#[derive(Default)]
+pub struct Session {
+    goals: Vec<Entailment>,
+    history: Vec<Vec<Entailment>>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Executes a single line of input and returns the answer, or None if the session should end
+    pub fn execute(&mut self, line: &str) -> Option<String> {
+        let line = line.trim();
+        let (command, argument) = match line.find(char::is_whitespace) {
+            Some(end) => (&line[..end], line[end..].trim()),
+            None => (line, ""),
+        };
+        let answer = match (command, argument) {
+            ("", _) => String::new(),
+            ("quit", "") | ("exit", "") => return None,
+            ("help", "") => HELP.to_string(),
+            ("goals", "") => self.show_goals(),
+            ("rules", "") => self.rules(),
+            ("apply", rule) => self.apply(rule),
+            ("auto", "") => self.auto(),
+            ("undo", "") => self.undo(),
+            _ => self.start(line),
+        };
+        Some(answer)
+    }
+
+    fn start(&mut self, input: &str) -> String {
+        match parse(input) {
+            Ok(goal) => {
+                self.history.clear();
+                self.goals = vec![goal];
+                self.show_goals()
+            }
+            Err(error) => format!("{}\nType `help` for a list of commands.", error),
+        }
+    }
+
+    fn show_goals(&self) -> String {
+        if self.goals.is_empty() {
+            return "No open goals.".to_string();
+        }
+        let mut answer = String::new();
+        for (i, goal) in self.goals.iter().enumerate() {
+            let _ = writeln!(answer, "{}: {}", i + 1, Unicode(goal));
+        }
+        answer.pop();
+        answer
+    }
+
+    fn rules(&self) -> String {
+        let goal = match self.goals.first() {
+            Some(goal) => goal,
+            None => return "No open goals.".to_string(),
+        };
+        let facts = Congruence::of(goal);
+        let holds: Vec<_> = RULES
+            .iter()
+            .filter(|rule| rule.predicate(goal, &facts))
+            .collect();
+        let applicable: Vec<&str> = holds
+            .iter()
+            .filter(|rule| rule.premisses(goal.clone(), &facts).is_some())
+            .map(|rule| rule.name())
+            .collect();
+        let holds: Vec<&str> = holds.iter().map(|rule| rule.name()).collect();
+        format!(
+            "Predicate holds: {}\nApplicable rules: {}",
+            holds.join(", "),
+            applicable.join(", ")
+        )
+    }
+
+    fn apply(&mut self, name: &str) -> String {
+        let rule = match RULES
+            .iter()
+            .find(|rule| rule.name().eq_ignore_ascii_case(name))
+        {
+            Some(rule) => rule,
+            None => return format!("There is no rule named `{}`.", name),
+        };
+        let goal = match self.goals.first() {
+            Some(goal) => goal.clone(),
+            None => return "No open goals.".to_string(),
+        };
+        let facts = Congruence::of(&goal);
+        if !rule.predicate(&goal, &facts) {
+            return format!("The predicate of {} does not hold.", rule.name());
+        }
+        match rule.premisses(goal, &facts) {
+            Some(premisses) => {
+                self.history.push(self.goals.clone());
+                self.goals.splice(0..1, premisses);
+                self.show_goals()
+            }
+            None => format!("{} does not apply to the current goal.", rule.name()),
+        }
+    }
+
+    /// Discharges the open goals one by one and stops at the first invalid one
+    fn auto(&mut self) -> String {
+        if self.goals.is_empty() {
+            return "No open goals.".to_string();
+        }
+        self.history.push(self.goals.clone());
+        let mut answer = String::new();
+        while let Some(goal) = self.goals.first() {
+            match prove(goal) {
+                Ok(Verdict::Valid(proof)) => {
+                    let _ = writeln!(
+                        answer,
+                        "Proved in {} steps: {}",
+                        proof.size(),
+                        Unicode(goal)
+                    );
+                    self.goals.remove(0);
+                }
+                Ok(Verdict::Invalid(stuck, model)) => {
+                    let _ = write!(answer, "The goal is invalid, stuck at: {}", Unicode(&stuck));
+                    if let Some(model) = model {
+                        let _ = write!(answer, "\nCountermodel: {}", model);
+                    }
+                    return answer;
+                }
+                Ok(Verdict::Unknown(reason)) => return answer + &Error::from(reason).to_string(),
+                Err(error) => return answer + &error.to_string(),
+            }
+        }
+        answer + "No open goals."
+    }
+
+    fn undo(&mut self) -> String {
+        match self.history.pop() {
+            Some(goals) => {
+                self.goals = goals;
+                self.show_goals()
+            }
+            None => "Nothing to undo.".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Session;
+
+    fn execute(session: &mut Session, line: &str) -> String {
+        session.execute(line).expect("The session ended early")
+    }
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::new();
+        assert_eq!(
+            "1: true | x->nil  |-  true | x->nil",
+            execute(&mut session, "True|SepConj[x->Nil] |- True|SepConj[x->Nil]")
+        );
+        assert!(execute(&mut session, "rules").ends_with("Applicable rules: NilNotLVal, Frame"));
+        assert_eq!(
+            "Tautology does not apply to the current goal.",
+            execute(&mut session, "apply Tautology")
+        );
+        assert_eq!(
+            "1: true | emp  |-  true | emp",
+            execute(&mut session, "apply frame")
+        );
+        assert_eq!(
+            "The predicate of NilNotLVal does not hold.",
+            execute(&mut session, "apply NilNotLVal")
+        );
+        execute(&mut session, "apply Cleanup");
+        assert_eq!("No open goals.", execute(&mut session, "apply Tautology"));
+        execute(&mut session, "undo");
+        execute(&mut session, "undo");
+        assert!(execute(&mut session, "undo").starts_with("1: true | x->nil"));
+        assert_eq!("Nothing to undo.", execute(&mut session, "undo"));
+        assert!(execute(&mut session, "auto").ends_with("No open goals."));
+
+        assert!(
+            execute(&mut session, "True|SepConj[x->y] |- True|Emp").starts_with("1: true | x->y")
+        );
+        assert!(execute(&mut session, "auto").contains("Countermodel: Stack: [x = l1"));
+        assert!(execute(&mut session, "Sepconj").starts_with("Could not parse input"));
+        assert_eq!(None, session.execute("quit"));
+    }
+}
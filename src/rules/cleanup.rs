@@ -1,3 +1,4 @@
+use crate::congruence::Congruence;
 use crate::datastructures::{
     Entailment,
     Pure::{And, True},
@@ -8,11 +9,15 @@ use crate::datastructures::{
 pub struct Cleanup;
 
 impl Rule for Cleanup {
-    fn predicate(&self, _goal: &Entailment) -> bool {
+    fn name(&self) -> &'static str {
+        "Cleanup"
+    }
+
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
         true
     }
 
-    fn premisses(&self, goal: Entailment) -> Option<Vec<Entailment>> {
+    fn premisses(&self, goal: Entailment, _facts: &Congruence) -> Option<Vec<Entailment>> {
         let (mut antecedent, mut consequent) = goal.destroy();
         let change_apv = if let And(apv) = antecedent.get_pure() {
             apv.is_empty()
@@ -1,31 +1,33 @@
-use crate::datastructures::{Entailment, Op::AtomNeq, Pure::And, Rule};
+use crate::congruence::Congruence;
+use crate::datastructures::{Entailment, Rule};
 
-/// Π ∧ E!=E | Σ  |-  Π' | Σ'
+/// Π ∧ E1!=E2 | Σ  |-  Π' | Σ'  where Π implies E1=E2
 pub struct Contradiction;
 impl Rule for Contradiction {
-    fn predicate(&self, _goal: &Entailment) -> bool {
+    fn name(&self) -> &'static str {
+        "Contradiction"
+    }
+
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
         true
     }
 
-    fn premisses(&self, goal: Entailment) -> Option<Vec<Entailment>> {
-        if let And(pure_sub) = goal.antecedent.get_pure() {
-            if pure_sub.iter().any(|x| match x {
-                AtomNeq(l, r) => l == r,
-                _ => false,
-            }) {
-                return Some(vec![]);
-            }
+    fn premisses(&self, _goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>> {
+        if facts.is_contradictory() {
+            Some(vec![])
+        } else {
+            None
         }
-        None
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::Contradiction;
+    use crate::congruence::Congruence;
     use crate::datastructures::{
         Entailment, Expr, Formula,
-        Op::AtomNeq,
+        Op::{AtomEq, AtomNeq},
         Pure::{And, True},
         Rule,
         Spatial::Emp,
@@ -37,11 +39,13 @@ mod test {
             antecedent: Formula(
                 And(vec![AtomNeq(Expr::new_var("y"), Expr::new_var("y"))]),
                 Emp,
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses = Contradiction.premisses(goal);
+        let facts = Congruence::of(&goal);
+        let premisses = Contradiction.premisses(goal, &facts);
         if let Some(prem) = premisses {
             assert_eq!(0, prem.len());
         } else {
@@ -52,15 +56,34 @@ mod test {
             antecedent: Formula(
                 And(vec![AtomNeq(Expr::new_var("y"), Expr::new_var("x"))]),
                 Emp,
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses = Contradiction.premisses(goal2);
+        let facts = Congruence::of(&goal2);
+        let premisses = Contradiction.premisses(goal2, &facts);
         if premisses.is_some() {
             return Err("Expected second test to fail!".to_string());
         }
 
+        // The contradiction only follows from the equalities
+        let goal3 = Entailment {
+            antecedent: Formula(
+                And(vec![
+                    AtomEq(Expr::new_var("x"), Expr::new_var("y")),
+                    AtomNeq(Expr::new_var("y"), Expr::new_var("x")),
+                ]),
+                Emp,
+                Vec::new(),
+            ),
+            consequent: Formula(True, Emp, Vec::new()),
+        };
+        let facts = Congruence::of(&goal3);
+        if Contradiction.premisses(goal3, &facts) != Some(vec![]) {
+            return Err("Expected third test to succeed!".to_string());
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,94 @@
+use crate::{
+    congruence::Congruence,
+    datastructures::{
+        Entailment,
+        Op::{AtomEq, AtomNeq},
+        Rule,
+        Spatial::SepConj,
+    },
+    misc::find_and_remove,
+};
+
+/// Π | Σ  |-  Π' | Σ' ==> Π | Σ  |-  Π' | P(E) * Σ' for an inductive atom P(E), i.e. an ls, a dls or an
+/// occurrence of a user-defined predicate, if Π implies the pure part base(E) of the empty case of P
+pub struct EmptyPred;
+
+impl Rule for EmptyPred {
+    fn name(&self) -> &'static str {
+        "EmptyPred"
+    }
+
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
+        true
+    }
+
+    fn premisses(&self, goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>> {
+        let (antecedent, mut consequent) = goal.destroy();
+
+        if let SepConj(spatial_vec) = consequent.get_spatial_mut() {
+            let empty = find_and_remove(spatial_vec, |x| match x.inductive() {
+                Some((definition, args)) => definition.base_case(&args).iter().all(|op| match op {
+                    AtomEq(l, r) => facts.equal(l, r),
+                    AtomNeq(l, r) => facts.distinct(l, r),
+                }),
+                None => false,
+            });
+            if empty.is_some() {
+                return Some(vec![Entailment {
+                    antecedent,
+                    consequent,
+                }]);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EmptyPred;
+    use crate::congruence::Congruence;
+    use crate::datastructures::Rule;
+    use crate::error::Error;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_emptypred() -> Result<(), Error> {
+        let sentinel = "pred sll(a,b) := emp & a=b & b!=nil | exists c. a->c * sll(c,b) & a!=b;";
+        let goal = parse(&format!(
+            "{} And[Neq(y,Nil)]|Emp |- True|SepConj[x->y,sll(y,y)]",
+            sentinel
+        ))?;
+        let expected = parse("And[Neq(y,Nil)]|Emp |- True|SepConj[x->y]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), EmptyPred.premisses(goal, &facts));
+
+        // Without y!=nil the sentinel might be nil
+        let nil = parse(&format!("{} True|Emp |- True|SepConj[sll(y,y)]", sentinel))?;
+        let facts = Congruence::of(&nil);
+        assert_eq!(None, EmptyPred.premisses(nil, &facts));
+
+        // ls and dls are handled as if they were declared
+        let goal = parse("True|Emp |- True|SepConj[ls(x,x)]")?;
+        let expected = parse("True|Emp |- True|SepConj[]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), EmptyPred.premisses(goal, &facts));
+        let nonempty = parse("True|Emp |- True|SepConj[ls(x,y)]")?;
+        let facts = Congruence::of(&nonempty);
+        assert_eq!(None, EmptyPred.premisses(nonempty, &facts));
+
+        let goal = parse("True|Emp |- True|SepConj[dls(x,y,y,x),x->y]")?;
+        let expected = parse("True|Emp |- True|SepConj[x->y]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), EmptyPred.premisses(goal, &facts));
+        // Both ends have to meet, otherwise the segment is either nonempty or unsatisfiable
+        let nonempty = parse("True|Emp |- True|SepConj[dls(x,y,x,y)]")?;
+        let facts = Congruence::of(&nonempty);
+        assert_eq!(None, EmptyPred.premisses(nonempty, &facts));
+        let half = parse("True|Emp |- True|SepConj[dls(x,y,y,z)]")?;
+        let facts = Congruence::of(&half);
+        assert_eq!(None, EmptyPred.premisses(half, &facts));
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{
+    congruence::Congruence,
+    datastructures::{AtomSpatial::Tree, Entailment, Expr::Nil, Rule, Spatial::SepConj},
+    misc::find_and_remove,
+};
+
+/// Π | Σ  |-  Π' | Σ' ==> Π | Σ  |-  Π' | tree(nil) * Σ'
+pub struct EmptyTree;
+
+impl Rule for EmptyTree {
+    fn name(&self) -> &'static str {
+        "EmptyTree"
+    }
+
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
+        true
+    }
+
+    fn premisses(&self, goal: Entailment, _facts: &Congruence) -> Option<Vec<Entailment>> {
+        let (antecedent, mut consequent) = goal.destroy();
+
+        if let SepConj(spatial_vec) = consequent.get_spatial_mut() {
+            if find_and_remove(spatial_vec, |x| *x == Tree(Nil)).is_some() {
+                return Some(vec![Entailment {
+                    antecedent,
+                    consequent,
+                }]);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EmptyTree;
+    use crate::congruence::Congruence;
+    use crate::datastructures::Rule;
+    use crate::error::Error;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_emptytree() -> Result<(), Error> {
+        let goal = parse("True|Emp |- True|SepConj[x->y,tree(Nil)]")?;
+        let expected = parse("True|Emp |- True|SepConj[x->y]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), EmptyTree.premisses(goal, &facts));
+
+        let nonempty = parse("True|Emp |- True|SepConj[tree(x)]")?;
+        let facts = Congruence::of(&nonempty);
+        assert_eq!(None, EmptyTree.premisses(nonempty, &facts));
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use crate::{
+    congruence::Congruence,
     datastructures::{Entailment, Op::AtomEq, Pure::And, Rule},
     misc::find_and_remove,
 };
@@ -6,11 +7,15 @@ use crate::{
 /// Π | Σ  |-  Π' | Σ' ==>  Π ∧ E=E | Σ  |-  Π' | Σ'
 pub struct EqReflexiveL;
 impl Rule for EqReflexiveL {
-    fn predicate(&self, _goal: &Entailment) -> bool {
+    fn name(&self) -> &'static str {
+        "EqReflexiveL"
+    }
+
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
         true
     }
 
-    fn premisses(&self, goal: Entailment) -> Option<Vec<Entailment>> {
+    fn premisses(&self, goal: Entailment, _facts: &Congruence) -> Option<Vec<Entailment>> {
         let (mut antecedent, consequent) = goal.destroy();
 
         if let And(pure_vec) = antecedent.get_pure_mut() {
@@ -33,6 +38,7 @@ impl Rule for EqReflexiveL {
 #[cfg(test)]
 mod test {
     use super::EqReflexiveL;
+    use crate::congruence::Congruence;
     use crate::datastructures::{
         Entailment, Expr,
         Expr::Nil,
@@ -49,15 +55,17 @@ mod test {
             antecedent: Formula(
                 And(vec![AtomEq(Nil, Nil), AtomNeq(Nil, Expr::new_var("x"))]),
                 Emp,
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
         let goal1_expected = Entailment {
-            antecedent: Formula(And(vec![AtomNeq(Nil, Expr::new_var("x"))]), Emp),
-            consequent: Formula(True, Emp),
+            antecedent: Formula(And(vec![AtomNeq(Nil, Expr::new_var("x"))]), Emp, Vec::new()),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses1 = EqReflexiveL.premisses(goal1);
+        let facts = Congruence::of(&goal1);
+        let premisses1 = EqReflexiveL.premisses(goal1, &facts);
         if let Some(prem) = premisses1 {
             assert_eq!(1, prem.len());
             assert_eq!(goal1_expected, prem[0]);
@@ -69,15 +77,17 @@ mod test {
             antecedent: Formula(
                 And(vec![AtomEq(Nil, Nil), AtomEq(Nil, Expr::new_var("x"))]),
                 Emp,
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
         let goal2_expected = Entailment {
-            antecedent: Formula(And(vec![AtomEq(Nil, Expr::new_var("x"))]), Emp),
-            consequent: Formula(True, Emp),
+            antecedent: Formula(And(vec![AtomEq(Nil, Expr::new_var("x"))]), Emp, Vec::new()),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses2 = EqReflexiveL.premisses(goal2);
+        let facts = Congruence::of(&goal2);
+        let premisses2 = EqReflexiveL.premisses(goal2, &facts);
         if let Some(prem) = premisses2 {
             assert_eq!(1, prem.len());
             assert_eq!(goal2_expected, prem[0]);
@@ -89,15 +99,17 @@ mod test {
             antecedent: Formula(
                 And(vec![AtomEq(Expr::new_var("x"), Expr::new_var("x"))]),
                 Emp,
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
         let goal3_expected = Entailment {
-            antecedent: Formula(And(vec![]), Emp),
-            consequent: Formula(True, Emp),
+            antecedent: Formula(And(vec![]), Emp, Vec::new()),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses3 = EqReflexiveL.premisses(goal3);
+        let facts = Congruence::of(&goal3);
+        let premisses3 = EqReflexiveL.premisses(goal3, &facts);
         if let Some(prem) = premisses3 {
             assert_eq!(1, prem.len());
             assert_eq!(goal3_expected, prem[0]);
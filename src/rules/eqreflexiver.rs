@@ -1,3 +1,4 @@
+use crate::congruence::Congruence;
 use crate::datastructures::{Entailment, Op::AtomEq, Pure::And, Rule};
 use crate::misc::find_and_remove;
 
@@ -5,11 +6,15 @@ use crate::misc::find_and_remove;
 pub struct EqReflexiveR;
 
 impl Rule for EqReflexiveR {
-    fn predicate(&self, _goal: &Entailment) -> bool {
+    fn name(&self) -> &'static str {
+        "EqReflexiveR"
+    }
+
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
         true
     }
 
-    fn premisses(&self, goal: Entailment) -> Option<Vec<Entailment>> {
+    fn premisses(&self, goal: Entailment, _facts: &Congruence) -> Option<Vec<Entailment>> {
         let (antecedent, mut consequent) = goal.destroy();
 
         if let And(pure_vec) = consequent.get_pure_mut() {
@@ -32,6 +37,7 @@ impl Rule for EqReflexiveR {
 #[cfg(test)]
 mod test {
     use super::EqReflexiveR;
+    use crate::congruence::Congruence;
     use crate::datastructures::{
         Entailment, Expr,
         Expr::Nil,
@@ -45,18 +51,20 @@ mod test {
     #[test]
     fn test_eq_reflexive_l() -> Result<(), String> {
         let goal1 = Entailment {
-            antecedent: Formula(True, Emp),
+            antecedent: Formula(True, Emp, Vec::new()),
             consequent: Formula(
                 And(vec![AtomEq(Nil, Nil), AtomNeq(Nil, Expr::new_var("x"))]),
                 Emp,
+                Vec::new(),
             ),
         };
         let goal1_expected = Entailment {
-            antecedent: Formula(True, Emp),
-            consequent: Formula(And(vec![AtomNeq(Nil, Expr::new_var("x"))]), Emp),
+            antecedent: Formula(True, Emp, Vec::new()),
+            consequent: Formula(And(vec![AtomNeq(Nil, Expr::new_var("x"))]), Emp, Vec::new()),
         };
 
-        let premisses1 = EqReflexiveR.premisses(goal1);
+        let facts = Congruence::of(&goal1);
+        let premisses1 = EqReflexiveR.premisses(goal1, &facts);
         if let Some(prem) = premisses1 {
             assert_eq!(1, prem.len());
             assert_eq!(goal1_expected, prem[0]);
@@ -65,18 +73,20 @@ mod test {
         }
 
         let goal2 = Entailment {
-            antecedent: Formula(True, Emp),
+            antecedent: Formula(True, Emp, Vec::new()),
             consequent: Formula(
                 And(vec![AtomEq(Nil, Nil), AtomEq(Nil, Expr::new_var("x"))]),
                 Emp,
+                Vec::new(),
             ),
         };
         let goal2_expected = Entailment {
-            antecedent: Formula(True, Emp),
-            consequent: Formula(And(vec![AtomEq(Nil, Expr::new_var("x"))]), Emp),
+            antecedent: Formula(True, Emp, Vec::new()),
+            consequent: Formula(And(vec![AtomEq(Nil, Expr::new_var("x"))]), Emp, Vec::new()),
         };
 
-        let premisses2 = EqReflexiveR.premisses(goal2);
+        let facts = Congruence::of(&goal2);
+        let premisses2 = EqReflexiveR.premisses(goal2, &facts);
         if let Some(prem) = premisses2 {
             assert_eq!(1, prem.len());
             assert_eq!(goal2_expected, prem[0]);
@@ -85,18 +95,20 @@ mod test {
         }
 
         let goal3 = Entailment {
-            antecedent: Formula(True, Emp),
+            antecedent: Formula(True, Emp, Vec::new()),
             consequent: Formula(
                 And(vec![AtomEq(Expr::new_var("x"), Expr::new_var("x"))]),
                 Emp,
+                Vec::new(),
             ),
         };
         let goal3_expected = Entailment {
-            antecedent: Formula(True, Emp),
-            consequent: Formula(And(vec![]), Emp),
+            antecedent: Formula(True, Emp, Vec::new()),
+            consequent: Formula(And(vec![]), Emp, Vec::new()),
         };
 
-        let premisses3 = EqReflexiveR.premisses(goal3);
+        let facts = Congruence::of(&goal3);
+        let premisses3 = EqReflexiveR.premisses(goal3, &facts);
         if let Some(prem) = premisses3 {
             assert_eq!(1, prem.len());
             assert_eq!(goal3_expected, prem[0]);
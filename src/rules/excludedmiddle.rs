@@ -0,0 +1,174 @@
+use crate::congruence::Congruence;
+use crate::datastructures::{
+    Entailment, Expr,
+    Expr::{Nil, Var},
+    Formula,
+    Op::{AtomEq, AtomNeq},
+    Pure::And,
+    Rule,
+    Spatial::SepConj,
+};
+
+/// Π ∧ E1=E2 | Σ  |-  Π' | Σ' and Π ∧ E1!=E2 | Σ  |-  Π' | Σ' ==> Π | Σ  |-  Π' | Σ'
+/// Splits on the first pair of antecedent expressions that is not known to be distinct
+/// until the antecedent is in normal form.
+pub struct ExcludedMiddle;
+
+impl ExcludedMiddle {
+    fn undecided_pair(goal: &Entailment, facts: &Congruence) -> Option<(Expr, Expr)> {
+        let antecedent = &goal.antecedent;
+        if let SepConj(atom_spatials) = antecedent.get_spatial() {
+            if (atom_spatials.iter()).any(|atom| !atom.is_points_to() && !atom.is_tree()) {
+                return None;
+            }
+        }
+
+        let mut exprs = vec![Nil];
+        let vars = antecedent
+            .get_pure_vars()
+            .into_iter()
+            .chain(antecedent.get_spatial_vars())
+            .flatten();
+        for var in vars {
+            if !exprs.contains(&Var(var.clone())) {
+                exprs.push(Var(var));
+            }
+        }
+
+        for (i, l) in exprs.iter().enumerate() {
+            for r in exprs.iter().skip(i + 1) {
+                if !facts.distinct(l, r) {
+                    return Some((l.clone(), r.clone()));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Rule for ExcludedMiddle {
+    fn name(&self) -> &'static str {
+        "ExcludedMiddle"
+    }
+
+    fn predicate(&self, goal: &Entailment, _facts: &Congruence) -> bool {
+        !goal.is_normal_form()
+    }
+
+    fn guarded(&self) -> bool {
+        true
+    }
+
+    fn premisses(&self, goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>> {
+        let (l, r) = Self::undecided_pair(&goal, facts)?;
+        let (antecedent, consequent) = goal.destroy();
+        let (ant_pure, ant_spatial) = antecedent.destroy();
+
+        let mut premisses = Vec::with_capacity(2);
+        for op in [AtomEq(l.clone(), r.clone()), AtomNeq(l, r)] {
+            let new_pure = match ant_pure.clone() {
+                And(mut pure_vec) => {
+                    pure_vec.push(op);
+                    And(pure_vec)
+                }
+                _ => And(vec![op]),
+            };
+            premisses.push(Entailment {
+                antecedent: Formula(new_pure, ant_spatial.clone(), Vec::new()),
+                consequent: consequent.clone(),
+            });
+        }
+        Some(premisses)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ExcludedMiddle;
+    use crate::congruence::Congruence;
+    use crate::datastructures::{
+        AtomSpatial::PointsTo,
+        Entailment, Expr,
+        Expr::Nil,
+        Formula,
+        Op::{AtomEq, AtomNeq},
+        Pure::{And, True},
+        Record, Rule,
+        Spatial::SepConj,
+    };
+
+    #[test]
+    fn test_excluded_middle() -> Result<(), String> {
+        let normal = Entailment {
+            antecedent: Formula(
+                And(vec![
+                    AtomNeq(Expr::new_var("x"), Nil),
+                    AtomNeq(Expr::new_var("y"), Nil),
+                    AtomNeq(Expr::new_var("y"), Expr::new_var("x")),
+                ]),
+                SepConj(vec![PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y")),
+                )]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, SepConj(vec![]), Vec::new()),
+        };
+        assert!(!ExcludedMiddle.predicate(&normal, &Congruence::of(&normal)));
+        let facts = Congruence::of(&normal);
+        if ExcludedMiddle.premisses(normal, &facts).is_some() {
+            return Err("Expected first test to fail!".to_string());
+        }
+
+        let goal = Entailment {
+            antecedent: Formula(
+                And(vec![AtomNeq(Expr::new_var("x"), Nil)]),
+                SepConj(vec![PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y")),
+                )]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, SepConj(vec![]), Vec::new()),
+        };
+        assert!(ExcludedMiddle.predicate(&goal, &Congruence::of(&goal)));
+
+        let expected = vec![
+            Entailment {
+                antecedent: Formula(
+                    And(vec![
+                        AtomNeq(Expr::new_var("x"), Nil),
+                        AtomEq(Nil, Expr::new_var("y")),
+                    ]),
+                    SepConj(vec![PointsTo(
+                        Expr::new_var("x"),
+                        Record::next(Expr::new_var("y")),
+                    )]),
+                    Vec::new(),
+                ),
+                consequent: Formula(True, SepConj(vec![]), Vec::new()),
+            },
+            Entailment {
+                antecedent: Formula(
+                    And(vec![
+                        AtomNeq(Expr::new_var("x"), Nil),
+                        AtomNeq(Nil, Expr::new_var("y")),
+                    ]),
+                    SepConj(vec![PointsTo(
+                        Expr::new_var("x"),
+                        Record::next(Expr::new_var("y")),
+                    )]),
+                    Vec::new(),
+                ),
+                consequent: Formula(True, SepConj(vec![]), Vec::new()),
+            },
+        ];
+        let facts = Congruence::of(&goal);
+        if let Some(premisses) = ExcludedMiddle.premisses(goal, &facts) {
+            assert_eq!(expected, premisses);
+            Ok(())
+        } else {
+            Err("Expected second test to succeed!".to_string())
+        }
+    }
+}
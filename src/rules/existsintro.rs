@@ -0,0 +1,171 @@
+use crate::congruence::Congruence;
+use crate::datastructures::{
+    AtomSpatial::LS,
+    Entailment, Expr, Formula, Op,
+    Op::{AtomEq, AtomNeq},
+    Pure::And,
+    Rule,
+    Spatial::SepConj,
+    Variable,
+};
+
+/// Instantiates an existential of the consequent whose witness is determined by the goal:
+/// Π | Σ  |-  ∃z'. (Π' | Σ')[E/z] ==> Π | Σ  |-  ∃z,z'. Π' ∧ z=E | Σ'
+/// and, if Σ is empty or in normal form has no cell at E, the segment has to be empty:
+/// Π | Σ  |-  ∃z'. (Π' | Σ')[E/z] ==> Π | Σ  |-  ∃z,z'. Π' | ls_f(E,z) * Σ'
+/// An existential that only occurs in disequalities is dropped, since an unused location is a witness:
+/// Π | Σ  |-  ∃z'. Π' | Σ' ==> Π | Σ  |-  ∃z,z'. Π' ∧ z!=E1 ∧ ... ∧ z!=En | Σ'
+pub struct ExistsIntro;
+
+impl ExistsIntro {
+    /// An existential and the value it has to take
+    fn witness(goal: &Entailment) -> Option<(Variable, Expr)> {
+        let consequent = &goal.consequent;
+        let bound = |e: &Expr, other: &Expr| match e {
+            Expr::Var(var) if e != other && consequent.is_existential(e) => {
+                Some((var.clone(), other.clone()))
+            }
+            _ => None,
+        };
+        if let And(ops) = consequent.get_pure() {
+            let equal = ops.iter().find_map(|op| match op {
+                AtomEq(l, r) => bound(l, r).or_else(|| bound(r, l)),
+                AtomNeq(_, _) => None,
+            });
+            if equal.is_some() {
+                return equal;
+            }
+        }
+
+        let (cells, atoms) = match (goal.antecedent.get_spatial(), consequent.get_spatial()) {
+            (SepConj(cells), SepConj(atoms)) => (cells.as_slice(), atoms),
+            (_, SepConj(atoms)) => (&[][..], atoms),
+            _ => return None,
+        };
+        // Only in normal form the variables of the antecedent are known to differ from each other
+        let vars = goal.antecedent.get_pure_vars().unwrap_or_default();
+        let unallocated = |e: &Expr| {
+            let known = match e {
+                Expr::Var(var) => vars.contains(var),
+                Expr::Nil => true,
+            };
+            known && cells.iter().all(|cell| cell.exprs().next() != Some(e))
+        };
+        let empty = atoms.iter().find_map(|atom| match atom {
+            LS(_, start, end) if cells.is_empty() => {
+                bound(end, start).or_else(|| bound(start, end))
+            }
+            LS(_, start, end) if !consequent.is_existential(start) && unallocated(start) => {
+                bound(end, start)
+            }
+            _ => None,
+        });
+        match empty {
+            Some(witness) if cells.is_empty() || goal.is_normal_form() => Some(witness),
+            _ => None,
+        }
+    }
+
+    /// An existential that occurs neither in an equality, a disequality with itself nor a spatial atom
+    fn unused(consequent: &Formula) -> Option<Variable> {
+        let ops: &[Op] = match consequent.get_pure() {
+            And(ops) => ops,
+            _ => &[],
+        };
+        let atoms = match consequent.get_spatial() {
+            SepConj(atoms) => atoms.as_slice(),
+            _ => &[],
+        };
+        consequent
+            .get_existentials()
+            .iter()
+            .find(|var| {
+                let var = Expr::Var((*var).clone());
+                let used = ops.iter().any(|op| match op {
+                    AtomEq(l, r) => *l == var || *r == var,
+                    AtomNeq(l, r) => *l == var && *r == var,
+                });
+                !used && atoms.iter().all(|atom| atom.exprs().all(|e| *e != var))
+            })
+            .cloned()
+    }
+}
+
+impl Rule for ExistsIntro {
+    fn name(&self) -> &'static str {
+        "ExistsIntro"
+    }
+
+    fn predicate(&self, goal: &Entailment, _facts: &Congruence) -> bool {
+        !goal.consequent.get_existentials().is_empty()
+    }
+
+    fn guarded(&self) -> bool {
+        true
+    }
+
+    fn premisses(&self, goal: Entailment, _facts: &Congruence) -> Option<Vec<Entailment>> {
+        if let Some((var, witness)) = Self::witness(&goal) {
+            return Some(vec![Entailment {
+                consequent: goal.consequent.instantiate(&var, &witness),
+                antecedent: goal.antecedent,
+            }]);
+        }
+
+        let unused = Expr::Var(Self::unused(&goal.consequent)?);
+        let (antecedent, mut consequent) = goal.destroy();
+        if let And(ops) = consequent.get_pure_mut() {
+            ops.retain(|op| match op {
+                AtomEq(l, r) | AtomNeq(l, r) => *l != unused && *r != unused,
+            });
+        }
+        consequent
+            .get_existentials_mut()
+            .retain(|var| Expr::Var(var.clone()) != unused);
+        Some(vec![Entailment {
+            antecedent,
+            consequent,
+        }])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ExistsIntro;
+    use crate::congruence::Congruence;
+    use crate::datastructures::Rule;
+    use crate::error::Error;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_existsintro() -> Result<(), Error> {
+        let equal = parse("True|SepConj[x->y] |- exists z. And[Eq(y,z)]|SepConj[x->z]")?;
+        let expected = parse("True|SepConj[x->y] |- And[Eq(y,y)]|SepConj[x->y]")?;
+        let facts = Congruence::of(&equal);
+        assert_eq!(Some(vec![expected]), ExistsIntro.premisses(equal, &facts));
+
+        let unused = parse("True|Emp |- exists z, w. And[Neq(z,x),Neq(w,w)]|Emp")?;
+        let expected = parse("True|Emp |- exists w. And[Neq(w,w)]|Emp")?;
+        let facts = Congruence::of(&unused);
+        assert_eq!(Some(vec![expected]), ExistsIntro.premisses(unused, &facts));
+
+        let normal = "And[Neq(x,Nil),Neq(y,Nil),Neq(x,y)]|SepConj[x->y]";
+        let empty = parse(&format!(
+            "{} |- exists z. True|SepConj[x->y,ls(y,z)]",
+            normal
+        ))?;
+        let expected = parse(&format!("{} |- True|SepConj[x->y,ls(y,y)]", normal))?;
+        let facts = Congruence::of(&empty);
+        assert_eq!(Some(vec![expected]), ExistsIntro.premisses(empty, &facts));
+
+        // The segment may or may not contain the cell of x
+        let allocated = parse(&format!("{} |- exists z. True|SepConj[ls(x,z)]", normal))?;
+        let facts = Congruence::of(&allocated);
+        assert_eq!(None, ExistsIntro.premisses(allocated, &facts));
+        // Without normal form y might be x
+        let aliased = parse("True|SepConj[x->y] |- exists z. True|SepConj[x->y,ls(y,z)]")?;
+        let facts = Congruence::of(&aliased);
+        assert_eq!(None, ExistsIntro.premisses(aliased, &facts));
+        Ok(())
+    }
+}
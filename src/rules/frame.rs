@@ -1,18 +1,89 @@
+use crate::congruence::Congruence;
 use crate::datastructures::{
-    AtomSpatial::{PointsTo, LS},
-    Entailment, Rule,
+    AtomSpatial,
+    AtomSpatial::{PointsTo, Pred, Tree, DLS, LS},
+    Entailment, Expr, Formula, Rule,
     Spatial::SepConj,
+    Variable,
 };
 
-/// Π | Σ  |-  Π' | Σ' ==> Π | S * Σ  |-  Π' | S * Σ'
+/// Π | Σ  |-  Π' | Σ' ==> Π | S * Σ  |-  Π' | S' * Σ'
+/// where S' is S, or a points-to with a subset of the fields of S.
+/// Before, the existentials of a points-to S' are unified with the cell S it has to describe:
+/// Π | S * Σ  |-  ∃z'. (Π' | S' * Σ')[E/z] ==> Π | S * Σ  |-  ∃z,z'. Π' | S' * Σ'
+/// where the cell S is the one at the address of S' or, for an existential address,
+/// the only cell that S' may describe.
 pub struct Frame;
 
+impl Frame {
+    /// The values the existentials of a consequent points-to atom must take, since the normal form
+    /// of the antecedent determines the cell the atom describes
+    fn witnesses(antecedent: &Formula, consequent: &Formula) -> Option<Vec<(Variable, Expr)>> {
+        let existentials = consequent.get_existentials();
+        let (cells, atoms) = match (antecedent.get_spatial(), consequent.get_spatial()) {
+            (SepConj(cells), SepConj(atoms)) if !existentials.is_empty() => (cells, atoms),
+            _ => return None,
+        };
+        for atom in atoms {
+            let (address, record) = match atom {
+                PointsTo(address, record) => (address, record),
+                _ => continue,
+            };
+            let mut candidates = cells.iter().filter_map(|cell| match cell {
+                PointsTo(l, r) if consequent.is_existential(address) || l == address => {
+                    Some((l, r))
+                }
+                _ => None,
+            });
+            // The inner cells of trees are not known, so they could be described as well
+            let cell = if !consequent.is_existential(address) {
+                candidates.next()
+            } else if cells.iter().any(AtomSpatial::is_tree) {
+                None
+            } else {
+                let mut candidates =
+                    candidates.filter(|(_, r)| r.may_contain(record, existentials));
+                match (candidates.next(), candidates.next()) {
+                    (Some(cell), None) => Some(cell),
+                    _ => None,
+                }
+            };
+            let (l, r) = match cell {
+                Some(cell) => cell,
+                None => continue,
+            };
+            let mut witnesses: Vec<(Variable, Expr)> = Vec::new();
+            let values =
+                (record.0.iter()).filter_map(|(field, value)| Some((value, r.get(field)?)));
+            for (value, known) in std::iter::once((address, l)).chain(values) {
+                if let Expr::Var(var) = value {
+                    if existentials.contains(var) && witnesses.iter().all(|(v, _)| v != var) {
+                        witnesses.push((var.clone(), known.clone()));
+                    }
+                }
+            }
+            if !witnesses.is_empty() {
+                return Some(witnesses);
+            }
+        }
+        None
+    }
+}
+
 impl Rule for Frame {
-    fn predicate(&self, goal: &Entailment) -> bool {
+    fn name(&self) -> &'static str {
+        "Frame"
+    }
+
+    fn predicate(&self, goal: &Entailment, _facts: &Congruence) -> bool {
         goal.is_normal_form()
     }
 
-    fn premisses(&self, goal: Entailment) -> Option<Vec<Entailment>> {
+    fn guarded(&self) -> bool {
+        true
+    }
+
+    fn premisses(&self, goal: Entailment, _facts: &Congruence) -> Option<Vec<Entailment>> {
         let (mut antecedent, mut consequent) = goal.destroy();
 
         let mut indices = None;
@@ -21,21 +92,24 @@ impl Rule for Frame {
                 'outer: for (i, o_spat) in spatial_ant_vec.iter().enumerate() {
                     '_inner: for (j, i_spat) in spatial_cons_vec.iter().enumerate() {
                         match o_spat {
-                            LS(o_l, o_r) => {
-                                if let LS(i_l, i_r) = i_spat {
-                                    if (*o_l == *i_l && *o_r == *i_r)
-                                        || (*o_l == *i_r && *o_r == *i_l)
-                                    {
+                            LS(o_f, o_l, o_r) => {
+                                if let LS(i_f, i_l, i_r) = i_spat {
+                                    if *o_f == *i_f && *o_l == *i_l && *o_r == *i_r {
                                         indices = Some((i, j));
                                         break 'outer;
                                     }
                                 }
                             }
+                            DLS(..) | Tree(_) | Pred(..) => {
+                                if o_spat == i_spat {
+                                    indices = Some((i, j));
+                                    break 'outer;
+                                }
+                            }
+                            // The cell may have more fields than the consequent asks for
                             PointsTo(o_l, o_r) => {
                                 if let PointsTo(i_l, i_r) = i_spat {
-                                    if (*o_l == *i_l && *o_r == *i_r)
-                                        || (*o_l == *i_r && *o_r == *i_l)
-                                    {
+                                    if *o_l == *i_l && o_r.contains(i_r) {
                                         indices = Some((i, j));
                                         break 'outer;
                                     }
@@ -60,51 +134,103 @@ impl Rule for Frame {
             }
         }
 
-        None
+        let witnesses = Self::witnesses(&antecedent, &consequent)?;
+        let consequent = (witnesses.iter()).fold(consequent, |formula, (var, witness)| {
+            formula.instantiate(var, witness)
+        });
+        Some(vec![Entailment {
+            antecedent,
+            consequent,
+        }])
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::Frame;
+    use crate::congruence::Congruence;
     use crate::datastructures::{
         AtomSpatial::{PointsTo, LS},
         Entailment, Expr,
         Expr::Nil,
-        Formula,
+        Field, Formula,
         Pure::True,
-        Rule,
+        Record, Rule,
         Spatial::SepConj,
     };
+    use crate::parser::parse;
 
     #[test]
     fn test_hypothesis() -> Result<(), String> {
         let goal1 = Entailment {
-            antecedent: Formula(True, SepConj(vec![LS(Expr::new_var("x"), Nil)])),
-            consequent: Formula(True, SepConj(vec![PointsTo(Expr::new_var("x"), Nil)])),
+            antecedent: Formula(
+                True,
+                SepConj(vec![LS(Field::next(), Expr::new_var("x"), Nil)]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![PointsTo(Expr::new_var("x"), Record::next(Nil))]),
+                Vec::new(),
+            ),
         };
 
-        let premisses1 = Frame.premisses(goal1);
+        let facts = Congruence::of(&goal1);
+        let premisses1 = Frame.premisses(goal1, &facts);
         if premisses1.is_some() {
             return Err("Expected first test to fail!".to_string());
         }
 
+        let goal_swapped = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("y")),
+                )]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![PointsTo(
+                    Expr::new_var("y"),
+                    Record::next(Expr::new_var("x")),
+                )]),
+                Vec::new(),
+            ),
+        };
+
+        let facts = Congruence::of(&goal_swapped);
+        if Frame.premisses(goal_swapped, &facts).is_some() {
+            return Err("Expected second test to fail!".to_string());
+        }
+
         let goal2 = Entailment {
             antecedent: Formula(
                 True,
                 SepConj(vec![
-                    LS(Expr::new_var("x"), Nil),
-                    PointsTo(Expr::new_var("z"), Nil),
+                    LS(Field::next(), Expr::new_var("x"), Nil),
+                    PointsTo(Expr::new_var("z"), Record::next(Nil)),
                 ]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![LS(Field::next(), Expr::new_var("x"), Nil)]),
+                Vec::new(),
             ),
-            consequent: Formula(True, SepConj(vec![LS(Expr::new_var("x"), Nil)])),
         };
         let goal2_expected = Entailment {
-            antecedent: Formula(True, SepConj(vec![PointsTo(Expr::new_var("z"), Nil)])),
-            consequent: Formula(True, SepConj(vec![])),
+            antecedent: Formula(
+                True,
+                SepConj(vec![PointsTo(Expr::new_var("z"), Record::next(Nil))]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, SepConj(vec![]), Vec::new()),
         };
 
-        let premisses2 = Frame.premisses(goal2);
+        let facts = Congruence::of(&goal2);
+        let premisses2 = Frame.premisses(goal2, &facts);
         if let Some(prem) = premisses2 {
             assert_eq!(1, prem.len());
             assert_eq!(goal2_expected, prem[0]);
@@ -113,4 +239,63 @@ mod test {
             Err("Expected third test to succeed!".to_string())
         }
     }
+
+    #[test]
+    fn test_records() -> Result<(), crate::error::Error> {
+        let goal = parse("True|SepConj[x->[next:y,data:z]] |- True|SepConj[x->[next:y]]")?;
+        let expected = parse("True|SepConj[] |- True|SepConj[]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), Frame.premisses(goal, &facts));
+
+        let missing = parse("True|SepConj[x->[next:y]] |- True|SepConj[x->[next:y,data:z]]")?;
+        let facts = Congruence::of(&missing);
+        assert_eq!(None, Frame.premisses(missing, &facts));
+        let link = parse("True|SepConj[ls[prev](x,y)] |- True|SepConj[ls(x,y)]")?;
+        let facts = Congruence::of(&link);
+        assert_eq!(None, Frame.premisses(link, &facts));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dls() -> Result<(), crate::error::Error> {
+        let goal = parse("True|SepConj[dls(x,y,z,Nil),z->x] |- True|SepConj[dls(x,y,z,Nil)]")?;
+        let expected = parse("True|SepConj[z->x] |- True|SepConj[]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), Frame.premisses(goal, &facts));
+
+        let ends = parse("True|SepConj[dls(x,y,z,Nil)] |- True|SepConj[dls(x,y,Nil,Nil)]")?;
+        let facts = Congruence::of(&ends);
+        assert_eq!(None, Frame.premisses(ends, &facts));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tree() -> Result<(), crate::error::Error> {
+        let goal = parse("True|SepConj[tree(x),tree(y)] |- True|SepConj[tree(y)]")?;
+        let expected = parse("True|SepConj[tree(x)] |- True|SepConj[]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), Frame.premisses(goal, &facts));
+        Ok(())
+    }
+
+    #[test]
+    fn test_existentials() -> Result<(), crate::error::Error> {
+        let goal = parse("True|SepConj[x->y,ls(y,Nil)] |- exists z. True|SepConj[x->z,ls(z,Nil)]")?;
+        let expected = parse("True|SepConj[x->y,ls(y,Nil)] |- True|SepConj[x->y,ls(y,Nil)]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), Frame.premisses(goal, &facts));
+
+        // The only cell that fits determines the existential address
+        let goal =
+            parse("True|SepConj[x->[data:y],y->[data:x]] |- exists z. True|SepConj[z->[data:x]]")?;
+        let expected = parse("True|SepConj[x->[data:y],y->[data:x]] |- True|SepConj[y->[data:x]]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), Frame.premisses(goal, &facts));
+
+        // Either cell may be the one
+        let ambiguous = parse("True|SepConj[x->y,y->y] |- exists z. True|SepConj[z->y]")?;
+        let facts = Congruence::of(&ambiguous);
+        assert_eq!(None, Frame.premisses(ambiguous, &facts));
+        Ok(())
+    }
 }
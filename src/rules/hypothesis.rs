@@ -1,3 +1,4 @@
+use crate::congruence::Congruence;
 use crate::datastructures::{
     Entailment,
     Op::{AtomEq, AtomNeq},
@@ -5,59 +6,34 @@ use crate::datastructures::{
     Rule,
 };
 
-/// Π | Σ  |-  Π' | Σ' ==> Π ∧ P | Σ  |-  Π' ∧ P | Σ'
+/// Π | Σ  |-  Π' | Σ' ==> Π | Σ  |-  Π' ∧ P | Σ'  where Π implies P
 pub struct Hypothesis;
 
 impl Rule for Hypothesis {
-    fn predicate(&self, _goal: &Entailment) -> bool {
-        true
+    fn name(&self) -> &'static str {
+        "Hypothesis"
     }
 
-    fn premisses(&self, goal: Entailment) -> Option<Vec<Entailment>> {
-        let (mut antecedent, mut consequent) = goal.destroy();
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
+        true
+    }
 
-        let mut indices = None;
-        if let And(pure_ant_vec) = antecedent.get_pure() {
-            if let And(pure_cons_vec) = consequent.get_pure() {
-                'outer: for (i, o_op) in pure_ant_vec.iter().enumerate() {
-                    '_inner: for (j, i_op) in pure_cons_vec.iter().enumerate() {
-                        match o_op {
-                            AtomEq(o_l, o_r) => {
-                                if let AtomEq(i_l, i_r) = i_op {
-                                    if (*o_l == *i_l && *o_r == *i_r)
-                                        || (*o_l == *i_r && *o_r == *i_l)
-                                    {
-                                        indices = Some((i, j));
-                                        break 'outer;
-                                    }
-                                }
-                            }
-                            AtomNeq(o_l, o_r) => {
-                                if let AtomNeq(i_l, i_r) = i_op {
-                                    if (*o_l == *i_l && *o_r == *i_r)
-                                        || (*o_l == *i_r && *o_r == *i_l)
-                                    {
-                                        indices = Some((i, j));
-                                        break 'outer;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        };
+    fn premisses(&self, goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>> {
+        let (antecedent, mut consequent) = goal.destroy();
 
-        if let Some((i, j)) = indices {
-            if let And(pure_ant_vec) = antecedent.get_pure_mut() {
-                if let And(pure_cons_vec) = consequent.get_pure_mut() {
-                    pure_ant_vec.remove(i);
-                    pure_cons_vec.remove(j);
-                    return Some(vec![Entailment {
-                        antecedent,
-                        consequent,
-                    }]);
-                }
+        // The hypothesis stays in the antecedent as other goals may still need it
+        if let And(pure_cons_vec) = consequent.get_pure_mut() {
+            let implied = pure_cons_vec.iter().position(|op| match op {
+                // Reflexive equalities are left to EqReflexiveR
+                AtomEq(l, r) => l != r && facts.equal(l, r),
+                AtomNeq(l, r) => facts.distinct(l, r),
+            });
+            if let Some(j) = implied {
+                pure_cons_vec.remove(j);
+                return Some(vec![Entailment {
+                    antecedent,
+                    consequent,
+                }]);
             }
         }
 
@@ -68,6 +44,7 @@ impl Rule for Hypothesis {
 #[cfg(test)]
 mod test {
     use super::Hypothesis;
+    use crate::congruence::Congruence;
     use crate::datastructures::{
         Entailment, Expr,
         Expr::Nil,
@@ -87,14 +64,17 @@ mod test {
                     AtomNeq(Nil, Expr::new_var("z")),
                 ]),
                 Emp,
+                Vec::new(),
             ),
             consequent: Formula(
                 And(vec![AtomEq(Nil, Nil), AtomNeq(Nil, Expr::new_var("x"))]),
                 Emp,
+                Vec::new(),
             ),
         };
 
-        let premisses1 = Hypothesis.premisses(goal1);
+        let facts = Congruence::of(&goal1);
+        let premisses1 = Hypothesis.premisses(goal1, &facts);
         if premisses1.is_some() {
             return Err("Expected first test to fail!".to_string());
         }
@@ -106,18 +86,28 @@ mod test {
                     AtomNeq(Nil, Expr::new_var("z")),
                 ]),
                 Emp,
+                Vec::new(),
             ),
             consequent: Formula(
                 And(vec![AtomEq(Nil, Nil), AtomEq(Nil, Expr::new_var("x"))]),
                 Emp,
+                Vec::new(),
             ),
         };
         let goal2_expected = Entailment {
-            antecedent: Formula(And(vec![AtomNeq(Nil, Expr::new_var("z"))]), Emp),
-            consequent: Formula(And(vec![AtomEq(Nil, Nil)]), Emp),
+            antecedent: Formula(
+                And(vec![
+                    AtomEq(Expr::new_var("x"), Nil),
+                    AtomNeq(Nil, Expr::new_var("z")),
+                ]),
+                Emp,
+                Vec::new(),
+            ),
+            consequent: Formula(And(vec![AtomEq(Nil, Nil)]), Emp, Vec::new()),
         };
 
-        let premisses2 = Hypothesis.premisses(goal2);
+        let facts = Congruence::of(&goal2);
+        let premisses2 = Hypothesis.premisses(goal2, &facts);
         if let Some(prem) = premisses2 {
             assert_eq!(1, prem.len());
             assert_eq!(goal2_expected, prem[0]);
@@ -126,4 +116,26 @@ mod test {
             Err("Expected third test to succeed!".to_string())
         }
     }
+
+    #[test]
+    fn test_hypothesis_keeps_antecedent() {
+        let goal = Entailment {
+            antecedent: Formula(And(vec![AtomEq(Expr::new_var("x"), Nil)]), Emp, Vec::new()),
+            consequent: Formula(
+                And(vec![
+                    AtomEq(Nil, Expr::new_var("x")),
+                    AtomEq(Expr::new_var("x"), Nil),
+                ]),
+                Emp,
+                Vec::new(),
+            ),
+        };
+        let expected = Entailment {
+            antecedent: Formula(And(vec![AtomEq(Expr::new_var("x"), Nil)]), Emp, Vec::new()),
+            consequent: Formula(And(vec![AtomEq(Expr::new_var("x"), Nil)]), Emp, Vec::new()),
+        };
+
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), Hypothesis.premisses(goal, &facts));
+    }
 }
@@ -1,27 +1,37 @@
 mod cleanup;
 mod contradition;
-mod emptyls;
+mod emptypred;
+mod emptytree;
 mod eqreflexivel;
 mod eqreflexiver;
+mod excludedmiddle;
+mod existsintro;
 mod frame;
 mod hypothesis;
 mod nilnotlval;
-mod nonemptyls;
+mod nonemptypred;
 mod starpartial;
 mod substitution;
 mod tautology;
+mod treeroot;
+mod unfoldtree;
 mod unrollcollapse;
 
 pub use cleanup::Cleanup;
 pub use contradition::Contradiction;
-pub use emptyls::EmptyLs;
+pub use emptypred::EmptyPred;
+pub use emptytree::EmptyTree;
 pub use eqreflexivel::EqReflexiveL;
 pub use eqreflexiver::EqReflexiveR;
+pub use excludedmiddle::ExcludedMiddle;
+pub use existsintro::ExistsIntro;
 pub use frame::Frame;
 pub use hypothesis::Hypothesis;
 pub use nilnotlval::NilNotLVal;
-pub use nonemptyls::NonEmptyLS;
+pub use nonemptypred::NonEmptyPred;
 pub use starpartial::StarPartial;
 pub use substitution::Substitution;
 pub use tautology::Tautology;
+pub use treeroot::TreeRoot;
+pub use unfoldtree::UnfoldTree;
 pub use unrollcollapse::UnrollCollapse;
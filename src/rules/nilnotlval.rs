@@ -1,85 +1,51 @@
+use crate::congruence::Congruence;
 use crate::datastructures::{
-    AtomSpatial::PointsTo,
-    Entailment,
-    Expr::{Nil, Var},
-    Formula,
-    Op::AtomNeq,
-    Pure::And,
-    Rule,
+    AtomSpatial::PointsTo, Entailment, Expr, Expr::Nil, Formula, Op::AtomNeq, Pure::And, Rule,
     Spatial::SepConj,
 };
 
 /// Π ∧ E1!=nil | E1->E2 * Σ  |-  Π' | Σ' ==> Π | E1->E2 * Σ  |-  Π' | Σ'
 pub struct NilNotLVal;
 
-impl Rule for NilNotLVal {
-    fn predicate(&self, goal: &Entailment) -> bool {
-        let mut add_new = false;
-        let antecedent = &goal.antecedent;
-        if let SepConj(atom_spatials) = antecedent.get_spatial() {
-            let points_to_facts = atom_spatials.iter().filter(move |x| x.is_points_to());
-
-            if let And(pure_ops) = antecedent.get_pure() {
-                for points_to_fact in points_to_facts {
-                    if let PointsTo(l, _) = points_to_fact {
-                        if pure_ops.iter().any(move |op| match op {
-                            AtomNeq(le, re) => (le == l && re == &Nil) || (re == l && le == &Nil),
-                            _ => false,
-                        }) {
-                            continue;
-                        } else {
-                            add_new = true;
-                            break;
-                        }
-                    }
-                }
-            } else {
-                add_new = true;
-            }
+impl NilNotLVal {
+    /// The first left value of a points-to fact that is not yet known to differ from nil
+    fn missing(goal: &Entailment, facts: &Congruence) -> Option<Expr> {
+        match goal.antecedent.get_spatial() {
+            SepConj(atom_spatials) => atom_spatials.iter().find_map(|atom| match atom {
+                PointsTo(l, _) if !facts.distinct(l, &Nil) => Some(l.clone()),
+                _ => None,
+            }),
+            _ => None,
         }
-        add_new
+    }
+}
+
+impl Rule for NilNotLVal {
+    fn name(&self) -> &'static str {
+        "NilNotLVal"
+    }
+
+    fn predicate(&self, goal: &Entailment, facts: &Congruence) -> bool {
+        Self::missing(goal, facts).is_some()
+    }
+
+    fn guarded(&self) -> bool {
+        true
     }
 
-    fn premisses(
-        &self,
-        goal: crate::datastructures::Entailment,
-    ) -> Option<Vec<crate::datastructures::Entailment>> {
+    fn premisses(&self, goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>> {
+        // A Nil left value adds Nil!=Nil which is then found by Contradiction
+        let points_to_to_add = Self::missing(&goal, facts)?;
         let (antecedent, consequent) = goal.destroy();
         let (mut ant_pure, ant_spatial) = antecedent.destroy();
-
-        let points_to_to_add = if let SepConj(points_to_facts) = &ant_spatial {
-            if let Some(PointsTo(nonnil, _)) = points_to_facts.iter().find(|ptf| {
-                if let PointsTo(l, _) = ptf {
-                    if let And(pure_ops) = &ant_pure {
-                        !pure_ops.iter().any(|op| match op {
-                            AtomNeq(le, re) => (le == l && re == &Nil) || (re == l && le == &Nil),
-                            _ => false,
-                        })
-                    } else {
-                        true
-                    }
-                } else {
-                    false
-                }
-            }) {
-                nonnil.clone()
-            } else {
-                Nil
-            }
+        if let And(pure_ops) = &mut ant_pure {
+            pure_ops.push(AtomNeq(points_to_to_add, Nil));
         } else {
-            Nil
-        };
-
-        if let Var(_) = points_to_to_add {
-            if let And(pure_ops) = &mut ant_pure {
-                pure_ops.push(AtomNeq(points_to_to_add, Nil));
-            } else {
-                ant_pure = And(vec![AtomNeq(points_to_to_add, Nil)]);
-            }
+            ant_pure = And(vec![AtomNeq(points_to_to_add, Nil)]);
         }
 
         Some(vec![Entailment {
-            antecedent: Formula(ant_pure, ant_spatial),
+            antecedent: Formula(ant_pure, ant_spatial, Vec::new()),
             consequent,
         }])
     }
@@ -88,14 +54,15 @@ impl Rule for NilNotLVal {
 #[cfg(test)]
 mod test {
     use super::NilNotLVal;
+    use crate::congruence::Congruence;
     use crate::datastructures::{
-        AtomSpatial::PointsTo,
+        AtomSpatial::{PointsTo, LS},
         Entailment, Expr,
         Expr::Nil,
-        Formula,
+        Field, Formula,
         Op::AtomNeq,
         Pure::{And, True},
-        Rule,
+        Record, Rule,
         Spatial::{Emp, SepConj},
     };
 
@@ -108,27 +75,29 @@ mod test {
                     AtomNeq(Expr::new_var("x"), Nil),
                 ]),
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        assert_eq!(false, NilNotLVal.predicate(&goal_not_applicable));
+        assert!(!NilNotLVal.predicate(&goal_not_applicable, &Congruence::of(&goal_not_applicable)));
 
         let goal1 = Entailment {
             antecedent: Formula(
                 And(vec![AtomNeq(Expr::new_var("y"), Nil)]),
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        assert!(NilNotLVal.predicate(&goal1));
+        assert!(NilNotLVal.predicate(&goal1, &Congruence::of(&goal1)));
 
         let goal_expected1 = Entailment {
             antecedent: Formula(
@@ -137,14 +106,16 @@ mod test {
                     AtomNeq(Expr::new_var("x"), Nil),
                 ]),
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses = NilNotLVal.premisses(goal1);
+        let facts = Congruence::of(&goal1);
+        let premisses = NilNotLVal.premisses(goal1, &facts);
         if let Some(prem) = premisses {
             assert_eq!(1, prem.len());
             assert_eq!(goal_expected1, prem[0]);
@@ -156,33 +127,76 @@ mod test {
             antecedent: Formula(
                 True,
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        assert!(NilNotLVal.predicate(&goal2));
+        assert!(NilNotLVal.predicate(&goal2, &Congruence::of(&goal2)));
 
         let goal_expected2 = Entailment {
             antecedent: Formula(
                 And(vec![AtomNeq(Expr::new_var("y"), Nil)]),
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses = NilNotLVal.premisses(goal2);
+        let facts = Congruence::of(&goal2);
+        let premisses = NilNotLVal.premisses(goal2, &facts);
         if let Some(prem) = premisses {
             assert_eq!(1, prem.len());
             assert_eq!(goal_expected2, prem[0]);
-            Ok(())
         } else {
-            Err(())
+            return Err(());
         }
+
+        let goal_nil = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![PointsTo(Nil, Record::next(Expr::new_var("x")))]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, Emp, Vec::new()),
+        };
+
+        assert!(NilNotLVal.predicate(&goal_nil, &Congruence::of(&goal_nil)));
+
+        let goal_expected_nil = Entailment {
+            antecedent: Formula(
+                And(vec![AtomNeq(Nil, Nil)]),
+                SepConj(vec![PointsTo(Nil, Record::next(Expr::new_var("x")))]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, Emp, Vec::new()),
+        };
+
+        let facts = Congruence::of(&goal_nil);
+        let premisses = NilNotLVal.premisses(goal_nil, &facts);
+        if let Some(prem) = premisses {
+            assert_eq!(1, prem.len());
+            assert_eq!(goal_expected_nil, prem[0]);
+        } else {
+            return Err(());
+        }
+
+        let goal_no_points_to = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![LS(Field::next(), Expr::new_var("x"), Nil)]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, Emp, Vec::new()),
+        };
+
+        assert!(!NilNotLVal.predicate(&goal_no_points_to, &Congruence::of(&goal_no_points_to)));
+        Ok(())
     }
 }
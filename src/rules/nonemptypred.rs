@@ -0,0 +1,267 @@
+use crate::{
+    congruence::Congruence,
+    datastructures::{
+        AtomSpatial,
+        AtomSpatial::PointsTo,
+        Entailment, Expr, Formula, Op,
+        Op::{AtomEq, AtomNeq},
+        Pure::{And, True},
+        Record, Rule,
+        Spatial::SepConj,
+    },
+    misc::find_and_remove,
+};
+
+/// Π | Σ  |-  Π' | P(call(E,u)) * Σ' ==> Π | E1->record(E,u) * Σ  |-  Π' | P(E) * Σ'
+/// for an inductive atom P(E), i.e. an ls, a dls or an occurrence of a user-defined predicate,
+/// if Π implies the pure part step(E,u) of the nonempty case of P, where the existentials u of
+/// the definition are read off the cell. The parts of step(E,u) that mention existentials z of the
+/// consequent are left to their witnesses instead, if no other atom of the consequent may describe the cell:
+/// Π | Σ  |-  ∃z. Π' ∧ step(E,u) | P(call(E,u)) * Σ' ==> Π | E1->record(E,u) * Σ  |-  ∃z. Π' | P(E) * Σ'
+pub struct NonEmptyPred;
+
+impl NonEmptyPred {
+    /// Whether none of the other atoms of the consequent may describe the cell at `address`
+    fn covers_alone<'a>(
+        antecedent: &Formula,
+        consequent: &Formula,
+        mut others: impl Iterator<Item = &'a AtomSpatial>,
+        address: &Expr,
+        cell: &Record,
+    ) -> bool {
+        // The variables of the antecedent are distinct in normal form, the others may alias
+        let vars = antecedent.get_pure_vars().unwrap_or_default();
+        let known = |e: &Expr| match e {
+            Expr::Var(var) => vars.contains(var),
+            Expr::Nil => true,
+        };
+        others.all(|atom| match atom {
+            PointsTo(Expr::Nil, _) => true,
+            PointsTo(l, r) if consequent.is_existential(l) => {
+                !cell.may_contain(r, consequent.get_existentials())
+            }
+            PointsTo(l, _) => l != address && known(l),
+            _ => false,
+        })
+    }
+}
+
+impl Rule for NonEmptyPred {
+    fn name(&self) -> &'static str {
+        "NonEmptyPred"
+    }
+
+    fn predicate(&self, goal: &Entailment, _facts: &Congruence) -> bool {
+        goal.is_normal_form()
+    }
+
+    fn guarded(&self) -> bool {
+        true
+    }
+
+    fn premisses(&self, goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>> {
+        let (cons_spatials, ant_spatials) =
+            match (goal.consequent.get_spatial(), goal.antecedent.get_spatial()) {
+                (SepConj(cons_spatials), SepConj(ant_spatials)) => (cons_spatials, ant_spatials),
+                _ => return None,
+            };
+
+        let mut found = None;
+        'outer: for (i, atom) in cons_spatials.iter().enumerate() {
+            let (definition, args) = match atom.inductive() {
+                Some(inductive) => inductive,
+                None => continue,
+            };
+            for cell in ant_spatials {
+                let (record, existentials) = match cell {
+                    PointsTo(l, record) if *l == args[0] => {
+                        match definition.match_record(&args, record) {
+                            Some(existentials) => (record, existentials),
+                            None => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                let (_, call, step) = definition.step_case(&args, &existentials);
+                let open: Vec<Op> = (step.into_iter())
+                    .filter(|op| match op {
+                        AtomEq(l, r) => !facts.equal(l, r),
+                        AtomNeq(l, r) => !facts.distinct(l, r),
+                    })
+                    .collect();
+                let witnessed = |op: &Op| match op {
+                    AtomEq(l, r) | AtomNeq(l, r) => {
+                        goal.consequent.is_existential(l) || goal.consequent.is_existential(r)
+                    }
+                };
+                if !open.iter().all(witnessed) {
+                    continue;
+                }
+                let others = (cons_spatials.iter().enumerate())
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, atom)| atom);
+                if !open.is_empty()
+                    && !Self::covers_alone(
+                        &goal.antecedent,
+                        &goal.consequent,
+                        others,
+                        &args[0],
+                        record,
+                    )
+                {
+                    continue 'outer;
+                }
+                let rest = AtomSpatial::instance(&definition, call);
+                found = Some((atom.clone(), cell.clone(), rest, open));
+                break 'outer;
+            }
+        }
+
+        let (atom, cell, rest, open) = found?;
+        let (mut antecedent, mut consequent) = goal.destroy();
+        if let SepConj(ant_spatials) = antecedent.get_spatial_mut() {
+            find_and_remove(ant_spatials, |spatial| *spatial == cell);
+        }
+        if let SepConj(cons_spatials) = consequent.get_spatial_mut() {
+            find_and_remove(cons_spatials, |spatial| *spatial == atom);
+            cons_spatials.push(rest);
+        }
+        // The witnesses have to respect that the atom is nonempty
+        if !open.is_empty() {
+            match consequent.get_pure_mut() {
+                And(ops) => ops.extend(open),
+                pure @ True => *pure = And(open),
+            }
+        }
+        Some(vec![Entailment {
+            antecedent,
+            consequent,
+        }])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NonEmptyPred;
+    use crate::congruence::Congruence;
+    use crate::datastructures::Rule;
+    use crate::error::Error;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_nonemptypred() -> Result<(), Error> {
+        let dll = "pred dll(a,b,p,q) := emp & a=q & b=p \
+            | exists c. a->[next:c,prev:p] * dll(c,b,a,q) & a!=q & b!=p;";
+        let normal = "And[Neq(x,Nil),Neq(y,Nil),Neq(x,y)]";
+        let goal = parse(&format!(
+            "{} {}|SepConj[x->[next:y,prev:Nil],y->[next:Nil,prev:x]] |- True|SepConj[dll(x,y,Nil,Nil)]",
+            dll, normal
+        ))?;
+        let expected = parse(&format!(
+            "{} {}|SepConj[y->[next:Nil,prev:x]] |- True|SepConj[dll(y,y,x,Nil)]",
+            dll, normal
+        ))?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), NonEmptyPred.premisses(goal, &facts));
+
+        // The backward link of the first cell has to be the predecessor of the segment
+        let prev = parse(&format!(
+            "{} {}|SepConj[x->[next:y,prev:y]] |- True|SepConj[dll(x,y,Nil,Nil)]",
+            dll, normal
+        ))?;
+        let facts = Congruence::of(&prev);
+        assert_eq!(None, NonEmptyPred.premisses(prev, &facts));
+        // Without x!=y the segment might be empty
+        let empty = parse(&format!(
+            "{} And[Neq(x,Nil)]|SepConj[x->[next:y,prev:y]] |- True|SepConj[dll(x,y,y,y)]",
+            dll
+        ))?;
+        let facts = Congruence::of(&empty);
+        assert_eq!(None, NonEmptyPred.premisses(empty, &facts));
+
+        // dls is handled as if it was declared like dll
+        let goal = parse(&format!(
+            "{}|SepConj[x->[next:y,prev:Nil],y->[next:Nil,prev:x]] |- True|SepConj[dls(x,y,Nil,Nil)]",
+            normal
+        ))?;
+        let expected = parse(&format!(
+            "{}|SepConj[y->[next:Nil,prev:x]] |- True|SepConj[dls(y,y,x,Nil)]",
+            normal
+        ))?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), NonEmptyPred.premisses(goal, &facts));
+        let prev = parse(&format!(
+            "{}|SepConj[x->[next:y,prev:y]] |- True|SepConj[dls(x,y,Nil,Nil)]",
+            normal
+        ))?;
+        let facts = Congruence::of(&prev);
+        assert_eq!(None, NonEmptyPred.premisses(prev, &facts));
+        let empty = parse(
+            "And[Neq(x,Nil),Neq(y,Nil)]|SepConj[x->[next:y,prev:y]] |- True|SepConj[dls(x,y,y,y)]",
+        )?;
+        let facts = Congruence::of(&empty);
+        assert_eq!(None, NonEmptyPred.premisses(empty, &facts));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ls() -> Result<(), Error> {
+        let goal = parse(
+            "And[Neq(x,Nil),Neq(y,z)]|SepConj[z->x,ls(x,Nil)] |- And[Neq(x,Nil)]|SepConj[ls(z,y),ls(y,Nil)]",
+        )?;
+        let expected = parse(
+            "And[Neq(x,Nil),Neq(y,z)]|SepConj[ls(x,Nil)] |- And[Neq(x,Nil)]|SepConj[ls(y,Nil),ls(x,y)]",
+        )?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), NonEmptyPred.premisses(goal, &facts));
+
+        // Without z!=Nil the segment from z might be empty
+        let empty = parse(
+            "And[Neq(x,Nil)]|SepConj[z->x,ls(x,Nil)] |- And[Neq(x,Nil)]|SepConj[ls(z,Nil),ls(y,Nil)]",
+        )?;
+        let facts = Congruence::of(&empty);
+        assert_eq!(None, NonEmptyPred.premisses(empty, &facts));
+        // Without a cell at z there is nothing to match the segment from z with
+        let unallocated = parse(
+            "And[Neq(x,Nil),Neq(y,z)]|SepConj[ls(x,Nil)] |- And[Neq(x,Nil)]|SepConj[ls(z,Nil),ls(y,Nil)]",
+        )?;
+        let facts = Congruence::of(&unallocated);
+        assert_eq!(None, NonEmptyPred.premisses(unallocated, &facts));
+
+        // Further fields of the cell are irrelevant, but it needs the link field
+        let goal =
+            parse("And[Neq(x,Nil)]|SepConj[x->[data:z,next:y]] |- True|SepConj[ls[next](x,Nil)]")?;
+        let expected = parse("And[Neq(x,Nil)]|SepConj[] |- True|SepConj[ls(y,Nil)]")?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), NonEmptyPred.premisses(goal, &facts));
+        let unlinked =
+            parse("And[Neq(x,Nil)]|SepConj[x->[data:y]] |- True|SepConj[ls[next](x,Nil)]")?;
+        let facts = Congruence::of(&unlinked);
+        assert_eq!(None, NonEmptyPred.premisses(unlinked, &facts));
+        Ok(())
+    }
+
+    #[test]
+    fn test_existentials() -> Result<(), Error> {
+        let normal = "And[Neq(x,Nil),Neq(y,Nil),Neq(x,y)]";
+        let goal = parse(&format!(
+            "{}|SepConj[x->y] |- exists z. True|SepConj[ls(x,z)]",
+            normal
+        ))?;
+        let expected = parse(&format!(
+            "{}|SepConj[] |- exists z. And[Neq(x,z)]|SepConj[ls(y,z)]",
+            normal
+        ))?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), NonEmptyPred.premisses(goal, &facts));
+
+        // The cell of x may belong to the points-to atom instead
+        let shared = parse(&format!(
+            "{}|SepConj[x->y] |- exists z, w. True|SepConj[ls(x,z),w->y]",
+            normal
+        ))?;
+        let facts = Congruence::of(&shared);
+        assert_eq!(None, NonEmptyPred.premisses(shared, &facts));
+        Ok(())
+    }
+}
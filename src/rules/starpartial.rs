@@ -1,101 +1,62 @@
+use crate::congruence::Congruence;
 use crate::datastructures::{
-    AtomSpatial::PointsTo, Entailment, Expr::Nil, Formula, Op::AtomNeq, Pure::And, Rule,
+    AtomSpatial::{PointsTo, Tree},
+    Entailment,
+    Expr::Nil,
+    Formula, Op,
+    Op::AtomNeq,
+    Pure::And,
+    Rule,
     Spatial::SepConj,
 };
 
 /// Π ∧ E1!=E3 | E1->E2 * E3->E4 * Σ  |-  Π' | Σ' ==> Π | E1->E2 * E3->E4 * Σ  |-  Π' | Σ'
+/// The roots of trees that are known to be nonempty are allocated as well.
 pub struct StarPartial;
 
-impl Rule for StarPartial {
-    fn predicate(&self, goal: &Entailment) -> bool {
-        let mut add_new = false;
-        let antecedent = &goal.antecedent;
-        if let SepConj(atom_spatials) = antecedent.get_spatial() {
-            if atom_spatials.len() < 2 {
-                return false;
-            }
-
-            if let And(pure_ops) = antecedent.get_pure() {
-                let first_vec = atom_spatials.as_slice();
-                'outer: for first_ptf in first_vec {
-                    'inner: for second_ptf in atom_spatials {
-                        if let PointsTo(l1, _) = first_ptf {
-                            if let PointsTo(l2, _) = second_ptf {
-                                if *l1 == *l2 {
-                                    continue 'inner;
-                                }
-
-                                if pure_ops.iter().any(move |op| match op {
-                                    AtomNeq(le, re) => {
-                                        (le == l1 && re == l2) || (re == l1 && le == l2)
-                                    }
-                                    _ => false,
-                                }) {
-                                    continue 'inner;
-                                } else {
-                                    add_new = true;
-                                    break 'outer;
-                                }
-                            }
-                        }
-                    }
+impl StarPartial {
+    /// The first disequality between left values of two points-to facts that is not yet known
+    fn missing(goal: &Entailment, facts: &Congruence) -> Option<Op> {
+        let atom_spatials = match goal.antecedent.get_spatial() {
+            SepConj(atom_spatials) => atom_spatials,
+            _ => return None,
+        };
+        let left_values: Vec<_> = atom_spatials
+            .iter()
+            .filter_map(|atom| match atom {
+                PointsTo(l, _) => Some(l),
+                Tree(root) if facts.distinct(root, &Nil) => Some(root),
+                _ => None,
+            })
+            .collect();
+        for (i, l1) in left_values.iter().enumerate() {
+            for l2 in &left_values[i + 1..] {
+                if !facts.distinct(l1, l2) {
+                    return Some(AtomNeq((*l1).clone(), (*l2).clone()));
                 }
-            } else {
-                add_new = true;
             }
         }
-        add_new
+        None
+    }
+}
+
+impl Rule for StarPartial {
+    fn name(&self) -> &'static str {
+        "StarPartial"
+    }
+
+    fn predicate(&self, goal: &Entailment, facts: &Congruence) -> bool {
+        Self::missing(goal, facts).is_some()
+    }
+
+    fn guarded(&self) -> bool {
+        true
     }
 
-    fn premisses(
-        &self,
-        goal: crate::datastructures::Entailment,
-    ) -> Option<Vec<crate::datastructures::Entailment>> {
+    fn premisses(&self, goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>> {
+        let neq_to_add = Self::missing(&goal, facts)?;
         let (antecedent, consequent) = goal.destroy();
         let (mut ant_pure, ant_spatial) = antecedent.destroy();
-        let mut neq_to_add = AtomNeq(Nil, Nil);
-
-        if let SepConj(atom_spatials) = &ant_spatial {
-            if let And(pure_ops) = &ant_pure {
-                'outer: for first_ptf in atom_spatials.as_slice() {
-                    'inner: for second_ptf in atom_spatials.as_slice() {
-                        if let PointsTo(l1, _) = first_ptf {
-                            if let PointsTo(l2, _) = second_ptf {
-                                if *l1 == *l2 {
-                                    continue 'inner;
-                                }
-
-                                if pure_ops.iter().any(move |op| match op {
-                                    AtomNeq(le, re) => {
-                                        (le == l1 && re == l2) || (re == l1 && le == l2)
-                                    }
-                                    _ => false,
-                                }) {
-                                    continue 'inner;
-                                } else {
-                                    neq_to_add = AtomNeq(l1.clone(), l2.clone());
-                                    break 'outer;
-                                }
-                            }
-                        }
-                    }
-                }
-            } else {
-                let mut first = None;
-                let mut second = None;
-                for atom_spatial in atom_spatials {
-                    if let PointsTo(l, _) = atom_spatial {
-                        if first.is_some() {
-                            second = Some(l.clone());
-                            break;
-                        }
-                        first = Some(l.clone());
-                    }
-                }
-                neq_to_add = AtomNeq(first.unwrap(), second.unwrap());
-            }
-        }
-
         if let And(pure_ops) = &mut ant_pure {
             pure_ops.push(neq_to_add);
         } else {
@@ -103,7 +64,7 @@ impl Rule for StarPartial {
         }
 
         Some(vec![Entailment {
-            antecedent: Formula(ant_pure, ant_spatial),
+            antecedent: Formula(ant_pure, ant_spatial, Vec::new()),
             consequent,
         }])
     }
@@ -112,14 +73,15 @@ impl Rule for StarPartial {
 #[cfg(test)]
 mod test {
     use super::StarPartial;
+    use crate::congruence::Congruence;
     use crate::datastructures::{
-        AtomSpatial::PointsTo,
+        AtomSpatial::{PointsTo, LS},
         Entailment, Expr,
         Expr::Nil,
-        Formula,
+        Field, Formula,
         Op::AtomNeq,
         Pure::{And, True},
-        Rule,
+        Record, Rule,
         Spatial::{Emp, SepConj},
     };
 
@@ -132,27 +94,29 @@ mod test {
                     AtomNeq(Expr::new_var("y"), Expr::new_var("x")),
                 ]),
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        assert_eq!(false, StarPartial.predicate(&goal_not_applicable));
+        assert!(!StarPartial.predicate(&goal_not_applicable, &Congruence::of(&goal_not_applicable)));
 
         let goal1 = Entailment {
             antecedent: Formula(
                 And(vec![AtomNeq(Expr::new_var("z"), Nil)]),
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        assert!(StarPartial.predicate(&goal1));
+        assert!(StarPartial.predicate(&goal1, &Congruence::of(&goal1)));
 
         let goal_expected1 = Entailment {
             antecedent: Formula(
@@ -161,14 +125,16 @@ mod test {
                     AtomNeq(Expr::new_var("y"), Expr::new_var("x")),
                 ]),
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses = StarPartial.premisses(goal1);
+        let facts = Congruence::of(&goal1);
+        let premisses = StarPartial.premisses(goal1, &facts);
         if let Some(prem) = premisses {
             assert_eq!(1, prem.len());
             assert_eq!(goal_expected1, prem[0]);
@@ -180,30 +146,88 @@ mod test {
             antecedent: Formula(
                 True,
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        assert!(StarPartial.predicate(&goal2));
+        assert!(StarPartial.predicate(&goal2, &Congruence::of(&goal2)));
 
         let goal_expected2 = Entailment {
             antecedent: Formula(
                 And(vec![AtomNeq(Expr::new_var("y"), Expr::new_var("x"))]),
                 SepConj(vec![
-                    PointsTo(Expr::new_var("y"), Expr::new_var("x")),
-                    PointsTo(Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("y"), Record::next(Expr::new_var("x"))),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
                 ]),
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses = StarPartial.premisses(goal2);
+        let facts = Congruence::of(&goal2);
+        let premisses = StarPartial.premisses(goal2, &facts);
         if let Some(prem) = premisses {
             assert_eq!(1, prem.len());
             assert_eq!(goal_expected2, prem[0]);
+        } else {
+            return Err(());
+        }
+
+        let goal_only_ls = Entailment {
+            antecedent: Formula(
+                True,
+                SepConj(vec![
+                    LS(Field::next(), Expr::new_var("y"), Expr::new_var("x")),
+                    LS(Field::next(), Expr::new_var("x"), Expr::new_var("z")),
+                ]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, Emp, Vec::new()),
+        };
+
+        let facts = Congruence::of(&goal_only_ls);
+        if StarPartial.premisses(goal_only_ls, &facts).is_some() {
+            return Err(());
+        }
+
+        let goal_same_lval = Entailment {
+            antecedent: Formula(
+                And(vec![AtomNeq(Expr::new_var("x"), Nil)]),
+                SepConj(vec![
+                    PointsTo(Expr::new_var("x"), Record::next(Nil)),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
+                ]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, Emp, Vec::new()),
+        };
+
+        assert!(StarPartial.predicate(&goal_same_lval, &Congruence::of(&goal_same_lval)));
+
+        let goal_expected_same_lval = Entailment {
+            antecedent: Formula(
+                And(vec![
+                    AtomNeq(Expr::new_var("x"), Nil),
+                    AtomNeq(Expr::new_var("x"), Expr::new_var("x")),
+                ]),
+                SepConj(vec![
+                    PointsTo(Expr::new_var("x"), Record::next(Nil)),
+                    PointsTo(Expr::new_var("x"), Record::next(Expr::new_var("z"))),
+                ]),
+                Vec::new(),
+            ),
+            consequent: Formula(True, Emp, Vec::new()),
+        };
+
+        let facts = Congruence::of(&goal_same_lval);
+        let premisses = StarPartial.premisses(goal_same_lval, &facts);
+        if let Some(prem) = premisses {
+            assert_eq!(1, prem.len());
+            assert_eq!(goal_expected_same_lval, prem[0]);
             Ok(())
         } else {
             Err(())
@@ -1,69 +1,61 @@
 use crate::{
+    congruence::Congruence,
     datastructures::{
         AtomSpatial,
-        AtomSpatial::{PointsTo, LS},
-        Entailment, Expr,
-        Expr::{Nil, Var},
-        Formula, Op,
+        AtomSpatial::{PointsTo, Pred, Tree, DLS, LS},
+        Entailment, Formula, Op,
         Op::{AtomEq, AtomNeq},
         Pure,
         Pure::{And, True},
         Rule, Spatial,
         Spatial::{Emp, SepConj},
     },
-    misc::find_and_remove,
 };
 
 /// Π[E/x] | Σ[E/x]  |-  Π'[E/x] | Σ'[E/x] ==> Π ∧ x=E | Σ  |-  Π' | Σ'
+///
+/// All equalities of the antecedent are eliminated at once: every expression is replaced by the
+/// representative of its equivalence class, which is nil if the class contains it.
 pub struct Substitution;
 
 impl Substitution {
-    fn subst_impl(subst: &(String, Expr), x: &Expr) -> Expr {
-        match x {
-            Var(v) => {
-                if v.0 == subst.0 {
-                    subst.1.clone()
-                } else {
-                    Var(v.clone())
-                }
-            }
-            Nil => Nil,
-        }
-    }
-
-    fn subst_pure(subst: &(String, Expr), p: &mut Pure) -> Pure {
+    fn subst_pure(facts: &Congruence, p: &Pure) -> Pure {
         match p {
-            And(pure_sub) => {
-                let pure_vec: Vec<Op> = pure_sub
-                    .iter_mut()
-                    .map(|x| match x {
-                        AtomEq(l, r) => {
-                            AtomEq(Self::subst_impl(&subst, l), Self::subst_impl(&subst, r))
-                        }
-                        AtomNeq(l, r) => {
-                            AtomNeq(Self::subst_impl(&subst, l), Self::subst_impl(&subst, r))
-                        }
-                    })
-                    .collect();
-                And(pure_vec)
-            }
+            And(pure_sub) => And(pure_sub
+                .iter()
+                .map(|x| match x {
+                    AtomEq(l, r) => AtomEq(facts.representative(l), facts.representative(r)),
+                    AtomNeq(l, r) => AtomNeq(facts.representative(l), facts.representative(r)),
+                })
+                .collect()),
             True => True,
         }
     }
 
-    fn subst_atom_spatial(subst: &(String, Expr), sp: &mut AtomSpatial) -> AtomSpatial {
+    fn subst_atom_spatial(facts: &Congruence, sp: &AtomSpatial) -> AtomSpatial {
         match sp {
-            PointsTo(v, e) => PointsTo(Self::subst_impl(subst, v), Self::subst_impl(subst, e)),
-            LS(v, e) => LS(Self::subst_impl(subst, v), Self::subst_impl(subst, e)),
+            PointsTo(v, e) => PointsTo(facts.representative(v), e.map(|e| facts.representative(e))),
+            LS(f, v, e) => LS(f.clone(), facts.representative(v), facts.representative(e)),
+            DLS(a, b, c, d) => DLS(
+                facts.representative(a),
+                facts.representative(b),
+                facts.representative(c),
+                facts.representative(d),
+            ),
+            Tree(root) => Tree(facts.representative(root)),
+            Pred(definition, args) => Pred(
+                definition.clone(),
+                args.iter().map(|e| facts.representative(e)).collect(),
+            ),
         }
     }
 
-    fn subst_spatial(subst: &(String, Expr), sp: &mut Spatial) -> Spatial {
+    fn subst_spatial(facts: &Congruence, sp: &Spatial) -> Spatial {
         match sp {
             SepConj(atom_spatials) => SepConj(
                 atom_spatials
-                    .iter_mut()
-                    .map(move |asp| Self::subst_atom_spatial(subst, asp))
+                    .iter()
+                    .map(|asp| Self::subst_atom_spatial(facts, asp))
                     .collect::<Vec<AtomSpatial>>(),
             ),
 
@@ -71,59 +63,54 @@ impl Substitution {
         }
     }
 
-    fn substitute(subst: (String, Expr), goal: Entailment) -> Entailment {
-        let (mut antecedent, mut consequent) = goal.destroy();
-
-        let new_pure_ant = Self::subst_pure(&subst, antecedent.get_pure_mut());
-
-        let new_spatial_ant = Self::subst_spatial(&subst, antecedent.get_spatial_mut());
-
-        let new_pure_cons = Self::subst_pure(&subst, consequent.get_pure_mut());
-
-        let new_spatial_cons = Self::subst_spatial(&subst, consequent.get_spatial_mut());
-
-        Entailment {
-            antecedent: Formula(new_pure_ant, new_spatial_ant),
-            consequent: Formula(new_pure_cons, new_spatial_cons),
-        }
+    fn subst_formula(facts: &Congruence, formula: &Formula) -> Formula {
+        Formula(
+            Self::subst_pure(facts, formula.get_pure()),
+            Self::subst_spatial(facts, formula.get_spatial()),
+            formula.get_existentials().to_vec(),
+        )
     }
 }
+
 impl Rule for Substitution {
-    fn predicate(&self, _goal: &Entailment) -> bool {
-        true
+    fn name(&self) -> &'static str {
+        "Substitution"
     }
 
-    fn premisses(&self, mut goal: Entailment) -> Option<Vec<Entailment>> {
-        if let And(pure_sub) = goal.antecedent.get_pure_mut() {
-            if let Some(elem) = find_and_remove(pure_sub, move |x| x.is_eq()) {
-                let mut subst = ("".to_string(), Nil);
-
-                if let AtomEq(l, r) = elem {
-                    if let Var(v) = l {
-                        subst = (v.0, r.clone());
-                    } else if let Var(v) = r {
-                        subst = (v.0, l.clone());
-                    }
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
+        true
+    }
 
-                    return Some(vec![Self::substitute(subst, goal)]);
-                }
-            }
-        }
-        None
+    fn premisses(&self, goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>> {
+        let (antecedent, consequent) = goal.destroy();
+        let (pure, spatial) = antecedent.destroy();
+        let ops = match pure {
+            And(ops) if ops.iter().any(Op::is_eq) => ops,
+            _ => return None,
+        };
+        let disequalities = ops.into_iter().filter(|op| !op.is_eq()).collect();
+        Some(vec![Entailment {
+            antecedent: Self::subst_formula(
+                facts,
+                &Formula(And(disequalities), spatial, Vec::new()),
+            ),
+            consequent: Self::subst_formula(facts, &consequent),
+        }])
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::Substitution;
+    use crate::congruence::Congruence;
     use crate::datastructures::{
         AtomSpatial::{PointsTo, LS},
         Entailment, Expr,
         Expr::Nil,
-        Formula,
+        Field, Formula,
         Op::{AtomEq, AtomNeq},
         Pure::And,
-        Rule,
+        Record, Rule,
         Spatial::SepConj,
     };
 
@@ -135,32 +122,77 @@ mod test {
                     AtomEq(Expr::new_var("x"), Nil),
                     AtomNeq(Expr::new_var("y"), Expr::new_var("x")),
                 ]),
-                SepConj(vec![PointsTo(Expr::new_var("y"), Expr::new_var("x"))]),
+                SepConj(vec![PointsTo(
+                    Expr::new_var("y"),
+                    Record::next(Expr::new_var("x")),
+                )]),
+                Vec::new(),
             ),
             consequent: Formula(
                 And(vec![AtomNeq(Expr::new_var("z"), Expr::new_var("x"))]),
-                SepConj(vec![LS(Expr::new_var("x"), Nil)]),
+                SepConj(vec![LS(Field::next(), Expr::new_var("x"), Nil)]),
+                Vec::new(),
             ),
         };
 
         let goal_expected = Entailment {
             antecedent: Formula(
                 And(vec![AtomNeq(Expr::new_var("y"), Nil)]),
-                SepConj(vec![PointsTo(Expr::new_var("y"), Nil)]),
+                SepConj(vec![PointsTo(Expr::new_var("y"), Record::next(Nil))]),
+                Vec::new(),
             ),
             consequent: Formula(
                 And(vec![AtomNeq(Expr::new_var("z"), Nil)]),
-                SepConj(vec![LS(Nil, Nil)]),
+                SepConj(vec![LS(Field::next(), Nil, Nil)]),
+                Vec::new(),
             ),
         };
 
-        let premisses = Substitution.premisses(goal);
+        let facts = Congruence::of(&goal);
+        let premisses = Substitution.premisses(goal, &facts);
         if let Some(prem) = premisses {
             assert_eq!(1, prem.len());
             assert_eq!(goal_expected, prem[0]);
-            Ok(())
         } else {
-            Err("Expected third test to succeed!".to_string())
+            return Err("Expected third test to succeed!".to_string());
         }
+
+        // All equalities are eliminated in a single step
+        let chain = Entailment {
+            antecedent: Formula(
+                And(vec![
+                    AtomEq(Expr::new_var("z"), Expr::new_var("y")),
+                    AtomEq(Expr::new_var("y"), Expr::new_var("x")),
+                ]),
+                SepConj(vec![PointsTo(
+                    Expr::new_var("z"),
+                    Record::next(Expr::new_var("y")),
+                )]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                And(vec![]),
+                SepConj(vec![LS(Field::next(), Expr::new_var("y"), Nil)]),
+                Vec::new(),
+            ),
+        };
+        let expected = Entailment {
+            antecedent: Formula(
+                And(vec![]),
+                SepConj(vec![PointsTo(
+                    Expr::new_var("x"),
+                    Record::next(Expr::new_var("x")),
+                )]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                And(vec![]),
+                SepConj(vec![LS(Field::next(), Expr::new_var("x"), Nil)]),
+                Vec::new(),
+            ),
+        };
+        let facts = Congruence::of(&chain);
+        assert_eq!(Some(vec![expected]), Substitution.premisses(chain, &facts));
+        Ok(())
     }
 }
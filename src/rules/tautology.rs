@@ -1,14 +1,19 @@
+use crate::congruence::Congruence;
 use crate::datastructures::{Entailment, Pure::True, Rule, Spatial::Emp};
 
 ///  Π | emp  |-  true | emp
 pub struct Tautology;
 
 impl Rule for Tautology {
-    fn predicate(&self, _goal: &Entailment) -> bool {
+    fn name(&self) -> &'static str {
+        "Tautology"
+    }
+
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
         true
     }
 
-    fn premisses(&self, goal: Entailment) -> Option<Vec<Entailment>> {
+    fn premisses(&self, goal: Entailment, _facts: &Congruence) -> Option<Vec<Entailment>> {
         if let Emp = goal.antecedent.get_spatial() {
             if let Emp = *goal.consequent.get_spatial() {
                 if let True = *goal.consequent.get_pure() {
@@ -23,6 +28,7 @@ impl Rule for Tautology {
 #[cfg(test)]
 mod test {
     use super::Tautology;
+    use crate::congruence::Congruence;
     use crate::datastructures::{
         Entailment, Expr,
         Expr::Nil,
@@ -42,11 +48,13 @@ mod test {
                     AtomEq(Nil, Nil),
                 ]),
                 Emp,
+                Vec::new(),
             ),
-            consequent: Formula(True, Emp),
+            consequent: Formula(True, Emp, Vec::new()),
         };
 
-        let premisses = Tautology.premisses(goal1);
+        let facts = Congruence::of(&goal1);
+        let premisses = Tautology.premisses(goal1, &facts);
         if let Some(prem) = premisses {
             assert_eq!(0, prem.len());
         } else {
@@ -60,11 +68,13 @@ mod test {
                     AtomEq(Nil, Nil),
                 ]),
                 Emp,
+                Vec::new(),
             ),
-            consequent: Formula(True, SepConj(vec![])),
+            consequent: Formula(True, SepConj(vec![]), Vec::new()),
         };
 
-        let premisses = Tautology.premisses(goal2);
+        let facts = Congruence::of(&goal2);
+        let premisses = Tautology.premisses(goal2, &facts);
         if premisses.is_some() {
             return Err("Expected second test to fail!".to_string());
         }
@@ -0,0 +1,95 @@
+use crate::{
+    congruence::Congruence,
+    datastructures::{
+        AtomSpatial::Tree,
+        Entailment,
+        Expr::Nil,
+        Formula,
+        Op::{AtomEq, AtomNeq},
+        Pure::And,
+        Rule,
+        Spatial::SepConj,
+    },
+    misc::find_and_remove,
+};
+
+/// Π ∧ E=nil | Σ  |-  Π' | Σ' and Π ∧ E!=nil | tree(E) ∗ Σ  |-  Π' | Σ' ==> Π | tree(E) ∗ Σ  |-  Π' | Σ'
+/// Splits on the first tree in the antecedent whose root is not known to be allocated.
+/// Allocated trees stay opaque, the consequent can only take them apart by their named cells.
+pub struct TreeRoot;
+
+impl Rule for TreeRoot {
+    fn name(&self) -> &'static str {
+        "TreeRoot"
+    }
+
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
+        true
+    }
+
+    fn premisses(&self, goal: Entailment, facts: &Congruence) -> Option<Vec<Entailment>> {
+        let (antecedent, consequent) = goal.destroy();
+        let (ant_pure, ant_spatial) = antecedent.destroy();
+        let root = match &ant_spatial {
+            SepConj(spatial_vec) => spatial_vec.iter().find_map(|sp| match sp {
+                Tree(root) if !facts.distinct(root, &Nil) => Some(root.clone()),
+                _ => None,
+            })?,
+            _ => return None,
+        };
+
+        let mut premisses = Vec::with_capacity(2);
+        for op in [AtomEq(root.clone(), Nil), AtomNeq(root.clone(), Nil)] {
+            let mut spatial = ant_spatial.clone();
+            if let (AtomEq(..), SepConj(spatial_vec)) = (&op, &mut spatial) {
+                find_and_remove(spatial_vec, |sp| *sp == Tree(root.clone()));
+            }
+            let new_pure = match ant_pure.clone() {
+                And(mut pure_vec) => {
+                    pure_vec.push(op);
+                    And(pure_vec)
+                }
+                _ => And(vec![op]),
+            };
+            premisses.push(Entailment {
+                antecedent: Formula(new_pure, spatial, Vec::new()),
+                consequent: consequent.clone(),
+            });
+            // tree(nil) is empty
+            if root == Nil {
+                break;
+            }
+        }
+        Some(premisses)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TreeRoot;
+    use crate::congruence::Congruence;
+    use crate::datastructures::Rule;
+    use crate::error::Error;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_treeroot() -> Result<(), Error> {
+        let goal = parse("True|SepConj[tree(x),x->y] |- True|SepConj[tree(y)]")?;
+        let expected = vec![
+            parse("And[Eq(x,Nil)]|SepConj[x->y] |- True|SepConj[tree(y)]")?,
+            parse("And[Neq(x,Nil)]|SepConj[tree(x),x->y] |- True|SepConj[tree(y)]")?,
+        ];
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(expected), TreeRoot.premisses(goal, &facts));
+
+        let nil = parse("True|SepConj[tree(Nil)] |- True|Emp")?;
+        let expected = parse("And[Eq(Nil,Nil)]|SepConj[] |- True|Emp")?;
+        let facts = Congruence::of(&nil);
+        assert_eq!(Some(vec![expected]), TreeRoot.premisses(nil, &facts));
+
+        let allocated = parse("And[Neq(Nil,x)]|SepConj[tree(x)] |- True|Emp")?;
+        let facts = Congruence::of(&allocated);
+        assert_eq!(None, TreeRoot.premisses(allocated, &facts));
+        Ok(())
+    }
+}
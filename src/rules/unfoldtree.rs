@@ -0,0 +1,95 @@
+use crate::{
+    congruence::Congruence,
+    datastructures::{
+        AtomSpatial::{PointsTo, Tree},
+        Entailment, Field, Rule,
+        Spatial::SepConj,
+    },
+    misc::find_and_remove,
+};
+
+/// Π | Σ  |-  Π' | tree(E2) * tree(E3) * Σ' ==> Π | E1->[left: E2, right: E3, ...] * Σ  |-  Π' | tree(E1) * Σ'
+/// E1 is allocated, so in normal form it is known to be distinct from nil.
+pub struct UnfoldTree;
+
+impl Rule for UnfoldTree {
+    fn name(&self) -> &'static str {
+        "UnfoldTree"
+    }
+
+    fn predicate(&self, goal: &Entailment, _facts: &Congruence) -> bool {
+        goal.is_normal_form()
+    }
+
+    fn guarded(&self) -> bool {
+        true
+    }
+
+    fn premisses(&self, goal: Entailment, _facts: &Congruence) -> Option<Vec<Entailment>> {
+        let (cons_spatials, ant_spatials) =
+            match (goal.consequent.get_spatial(), goal.antecedent.get_spatial()) {
+                (SepConj(cons_spatials), SepConj(ant_spatials)) => (cons_spatials, ant_spatials),
+                _ => return None,
+            };
+        let (root, cell, children) = cons_spatials.iter().find_map(|tree| {
+            let root = match tree {
+                Tree(root) => root,
+                _ => return None,
+            };
+            ant_spatials.iter().find_map(|cell| match cell {
+                // Without both children the cell says nothing about the tree
+                PointsTo(l, record) if l == root => {
+                    let left = record.get(&Field::left())?;
+                    let right = record.get(&Field::right())?;
+                    Some((root.clone(), cell.clone(), [left.clone(), right.clone()]))
+                }
+                _ => None,
+            })
+        })?;
+
+        let (mut antecedent, mut consequent) = goal.destroy();
+        if let SepConj(ant_spatials) = antecedent.get_spatial_mut() {
+            find_and_remove(ant_spatials, |spatial| *spatial == cell);
+        }
+        if let SepConj(cons_spatials) = consequent.get_spatial_mut() {
+            find_and_remove(cons_spatials, |spatial| *spatial == Tree(root.clone()));
+            cons_spatials.extend(children.iter().cloned().map(Tree));
+        }
+        Some(vec![Entailment {
+            antecedent,
+            consequent,
+        }])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UnfoldTree;
+    use crate::congruence::Congruence;
+    use crate::datastructures::Rule;
+    use crate::error::Error;
+    use crate::parser::parse;
+
+    #[test]
+    fn test_unfoldtree() -> Result<(), Error> {
+        let normal = "And[Neq(x,Nil),Neq(y,Nil),Neq(x,y)]";
+        let goal = parse(&format!(
+            "{}|SepConj[x->[left:y,right:Nil,data:x],tree(y)] |- True|SepConj[tree(x)]",
+            normal
+        ))?;
+        let expected = parse(&format!(
+            "{}|SepConj[tree(y)] |- True|SepConj[tree(y),tree(Nil)]",
+            normal
+        ))?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(vec![expected]), UnfoldTree.premisses(goal, &facts));
+
+        let missing = parse(&format!(
+            "{}|SepConj[x->[left:y],tree(y)] |- True|SepConj[tree(x)]",
+            normal
+        ))?;
+        let facts = Congruence::of(&missing);
+        assert_eq!(None, UnfoldTree.premisses(missing, &facts));
+        Ok(())
+    }
+}
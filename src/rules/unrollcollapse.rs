@@ -1,126 +1,191 @@
 use crate::{
+    congruence::Congruence,
     datastructures::{
-        AtomSpatial::{PointsTo, LS},
-        Entailment,
-        Expr::Var,
-        Formula,
-        Op::{AtomEq, AtomNeq},
+        AtomSpatial::PointsTo,
+        Entailment, Expr,
+        Expr::{Nil, Var},
+        Formula, Op,
         Pure::And,
         Rule,
         Spatial::SepConj,
-        Variable,
     },
     misc::find_and_remove,
 };
 
-/// Π ∧ E1=E2 | Σ  |-  Π' | Σ' ==> Π ∧ E1!=E2 ∧ x!=E2 | E1->x ∗ x->E2 ∗ Σ  |-  Π' | Σ' ==> Π | ls(E1,E2) ∗ Σ  |-  Π' | Σ' (for a new x)
+/// Replaces the first inductive atom P(E1,...,En) in the antecedent, i.e. an ls, a dls or an occurrence of a
+/// user-defined predicate, by its empty case and by its unfoldings into i = 1, ..., m+k+1 cells with new
+/// existentials, the last of which ends in the empty case:
+/// Π ∧ base(E) | Σ and Π ∧ step(E) ∧ ... ∧ base(call^i(E)) | E1->record(E) ∗ ... ∗ Σ,
+/// where m is the number of parameters that the recursive occurrence changes and k the number of
+/// existentials of the consequent.
+/// Without existentials the consequent cannot tell the cells of a longer unfolding apart, as for ls (m=1)
+/// and dls (m=2). Each existential may name one more of them, so it needs one more unfolding.
 pub struct UnrollCollapse;
 
 impl Rule for UnrollCollapse {
-    fn predicate(&self, _goal: &Entailment) -> bool {
+    fn name(&self) -> &'static str {
+        "UnrollCollapse"
+    }
+
+    fn predicate(&self, _goal: &Entailment, _facts: &Congruence) -> bool {
         true
     }
 
-    fn premisses(&self, goal: Entailment) -> Option<Vec<Entailment>> {
+    fn premisses(&self, goal: Entailment, _facts: &Congruence) -> Option<Vec<Entailment>> {
+        let (definition, args) = match goal.antecedent.get_spatial() {
+            SepConj(spatial_vec) => spatial_vec.iter().find_map(|sp| sp.inductive())?,
+            _ => return None,
+        };
+        // Each parameter that changes from cell to cell needs one more unfolding to be covered
+        let moving = (definition.params.iter().zip(&definition.call))
+            .filter(|(param, arg)| Var((*param).clone()) != **arg)
+            .count();
+        let levels = moving + 1 + goal.consequent.get_existentials().len();
+        // The existentials have to be chosen before the goal is taken apart, so that they occur nowhere in it
+        let fresh = match &args[0] {
+            Var(root) => goal.fresh_vars(root, levels * definition.existentials.len()),
+            Nil => Vec::new(),
+        };
+
         let (antecedent, consequent) = goal.destroy();
         let (ant_pure, mut ant_spatial) = antecedent.destroy();
         if let SepConj(ref mut spatial_vec) = ant_spatial {
-            if let Some(ls) = find_and_remove(spatial_vec, |sp| sp.is_ls()) {
-                if let LS(l, r) = ls {
-                    if let Var(Variable(name_l)) = &l {
-                        let mut new_pure1 = ant_pure.clone();
-                        if let And(ref mut pure_vec) = new_pure1 {
-                            pure_vec.push(AtomEq(l.clone(), r.clone()));
-                        } else {
-                            new_pure1 = And(vec![AtomEq(l.clone(), r.clone())]);
-                        }
-                        let new_antecedent1 = Formula(new_pure1, ant_spatial.clone());
-                        let new_goal1 = Entailment {
-                            antecedent: new_antecedent1,
-                            consequent: consequent.clone(),
-                        };
-
-                        let new_x = Var(Variable(name_l.clone() + "x"));
-                        let mut new_pure2 = ant_pure;
-                        if let And(ref mut pure_vec) = new_pure2 {
-                            pure_vec.push(AtomNeq(l.clone(), r.clone()));
-                            pure_vec.push(AtomNeq(new_x.clone(), r.clone()));
-                        } else {
-                            new_pure2 = And(vec![
-                                AtomNeq(l.clone(), r.clone()),
-                                AtomNeq(new_x.clone(), r.clone()),
-                            ]);
-                        }
-                        ant_spatial = ant_spatial.add(PointsTo(l, new_x.clone()));
-                        ant_spatial = ant_spatial.add(PointsTo(new_x, r));
-                        let new_goal2 = Entailment {
-                            antecedent: Formula(new_pure2, ant_spatial),
-                            consequent,
-                        };
-
-                        return Some(vec![new_goal1, new_goal2]);
-                    }
-                }
-            }
+            find_and_remove(spatial_vec, |sp| sp.is_inductive());
         }
+        let premiss = |ops: Vec<Op>, spatial| {
+            let pure = match &ant_pure {
+                And(pure_vec) => And(pure_vec.iter().cloned().chain(ops).collect()),
+                _ => And(ops),
+            };
+            Entailment {
+                antecedent: Formula(pure, spatial, Vec::new()),
+                consequent: consequent.clone(),
+            }
+        };
 
-        None
+        let mut premisses = vec![premiss(definition.base_case(&args), ant_spatial.clone())];
+        // Nil is no lvalue, so P(nil,...) can only be empty
+        if args[0] == Nil {
+            return Some(premisses);
+        }
+        let mut existentials = fresh.into_iter().map(Var);
+        let (mut ops, mut spatial, mut current) = (Vec::new(), ant_spatial, args);
+        for _ in 0..levels {
+            let level: Vec<Expr> = existentials
+                .by_ref()
+                .take(definition.existentials.len())
+                .collect();
+            let (record, call, step) = definition.step_case(&current, &level);
+            spatial = spatial.add(PointsTo(current[0].clone(), record));
+            ops.extend(step);
+            current = call;
+            let mut last = ops.clone();
+            last.extend(definition.base_case(&current));
+            premisses.push(premiss(last, spatial.clone()));
+        }
+        Some(premisses)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::UnrollCollapse;
-    use crate::datastructures::{
-        AtomSpatial::{PointsTo, LS},
-        Entailment, Expr,
-        Expr::Nil,
-        Formula,
-        Op::{AtomEq, AtomNeq},
-        Pure::{And, True},
-        Rule,
-        Spatial::SepConj,
-    };
+    use crate::congruence::Congruence;
+    use crate::datastructures::Rule;
+    use crate::error::Error;
+    use crate::parser::parse;
+
+    fn parse_all(inputs: &[&str]) -> Result<Vec<crate::datastructures::Entailment>, Error> {
+        inputs.iter().map(|input| parse(input)).collect()
+    }
+
     #[test]
-    pub fn test_unrollcollapse() {
-        let goal = Entailment {
-            antecedent: Formula(True, SepConj(vec![LS(Expr::new_var("z"), Nil)])),
-            consequent: Formula(
-                And(vec![AtomEq(Nil, Expr::new_var("x"))]),
-                SepConj(vec![LS(Expr::new_var("x"), Nil)]),
-            ),
-        };
+    fn test_unrollcollapse() -> Result<(), Error> {
+        let goal = parse("True|SepConj[ls(z,Nil)] |- And[Eq(Nil,x)]|SepConj[ls(x,Nil)]")?;
+        let expected = parse_all(&[
+            "And[Eq(z,Nil)]|SepConj[] |- And[Eq(Nil,x)]|SepConj[ls(x,Nil)]",
+            "And[Neq(z,Nil),Eq(z'1,Nil)]|SepConj[z->z'1] |- And[Eq(Nil,x)]|SepConj[ls(x,Nil)]",
+            "And[Neq(z,Nil),Neq(z'1,Nil),Eq(z'2,Nil)]|SepConj[z->z'1,z'1->z'2] |- And[Eq(Nil,x)]|SepConj[ls(x,Nil)]",
+        ])?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(expected), UnrollCollapse.premisses(goal, &facts));
 
-        let expected = vec![
-            Entailment {
-                antecedent: Formula(And(vec![AtomEq(Expr::new_var("z"), Nil)]), SepConj(vec![])),
-                consequent: Formula(
-                    And(vec![AtomEq(Nil, Expr::new_var("x"))]),
-                    SepConj(vec![LS(Expr::new_var("x"), Nil)]),
-                ),
-            },
-            Entailment {
-                antecedent: Formula(
-                    And(vec![
-                        AtomNeq(Expr::new_var("z"), Nil),
-                        AtomNeq(Expr::new_var("zx"), Nil),
-                    ]),
-                    SepConj(vec![
-                        PointsTo(Expr::new_var("z"), Expr::new_var("zx")),
-                        PointsTo(Expr::new_var("zx"), Nil),
-                    ]),
-                ),
-                consequent: Formula(
-                    And(vec![AtomEq(Nil, Expr::new_var("x"))]),
-                    SepConj(vec![LS(Expr::new_var("x"), Nil)]),
-                ),
-            },
+        // Nil is no lvalue, so the segment can only be empty
+        let goal_nil = parse("True|SepConj[ls(Nil,z)] |- True|Emp")?;
+        let expected_nil = parse("And[Eq(Nil,z)]|SepConj[] |- True|Emp")?;
+        let facts = Congruence::of(&goal_nil);
+        assert_eq!(
+            Some(vec![expected_nil]),
+            UnrollCollapse.premisses(goal_nil, &facts)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dls() -> Result<(), Error> {
+        let goal = parse("True|SepConj[dls(x,y,p,Nil)] |- True|Emp")?;
+        let expected = parse_all(&[
+            "And[Eq(x,Nil),Eq(y,p)]|SepConj[] |- True|Emp",
+            "And[Neq(x,Nil),Neq(y,p),Eq(x'1,Nil),Eq(y,x)]|SepConj[x->[next:x'1,prev:p]] |- True|Emp",
+            "And[Neq(x,Nil),Neq(y,p),Neq(x'1,Nil),Neq(y,x),Eq(x'2,Nil),Eq(y,x'1)]\
+                |SepConj[x->[next:x'1,prev:p],x'1->[next:x'2,prev:x]] |- True|Emp",
+            "And[Neq(x,Nil),Neq(y,p),Neq(x'1,Nil),Neq(y,x),Neq(x'2,Nil),Neq(y,x'1),Eq(x'3,Nil),Eq(y,x'2)]\
+                |SepConj[x->[next:x'1,prev:p],x'1->[next:x'2,prev:x],x'2->[next:x'3,prev:x'1]] |- True|Emp",
+        ])?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(expected), UnrollCollapse.premisses(goal, &facts));
+
+        let goal_nil = parse("True|SepConj[dls(Nil,y,p,z)] |- True|Emp")?;
+        let expected_nil = parse("And[Eq(Nil,z),Eq(y,p)]|SepConj[] |- True|Emp")?;
+        let facts = Congruence::of(&goal_nil);
+        assert_eq!(
+            Some(vec![expected_nil]),
+            UnrollCollapse.premisses(goal_nil, &facts)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_predicates() -> Result<(), Error> {
+        let lseg = "pred lseg(a,b) := emp & a=b | exists c. a->c * lseg(c,b) & a!=b;";
+        let goal = parse(&format!("{} True|SepConj[lseg(x,y)] |- True|Emp", lseg))?;
+        let expected = parse_all(&[
+            "And[Eq(x,y)]|SepConj[] |- True|Emp",
+            "And[Neq(x,y),Eq(x'1,y)]|SepConj[x->x'1] |- True|Emp",
+            "And[Neq(x,y),Neq(x'1,y),Eq(x'2,y)]|SepConj[x->x'1,x'1->x'2] |- True|Emp",
+        ])?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(expected), UnrollCollapse.premisses(goal, &facts));
+        Ok(())
+    }
+
+    #[test]
+    fn test_existentials() -> Result<(), Error> {
+        // Every existential of the consequent may name one more cell
+        let goal = parse("True|SepConj[ls(x,y)] |- exists z. True|SepConj[x->z,ls(z,y)]")?;
+        let consequent = "exists z. True|SepConj[x->z,ls(z,y)]";
+        let expected = [
+            "And[Eq(x,y)]|SepConj[]",
+            "And[Neq(x,y),Eq(x'1,y)]|SepConj[x->x'1]",
+            "And[Neq(x,y),Neq(x'1,y),Eq(x'2,y)]|SepConj[x->x'1,x'1->x'2]",
+            "And[Neq(x,y),Neq(x'1,y),Neq(x'2,y),Eq(x'3,y)]|SepConj[x->x'1,x'1->x'2,x'2->x'3]",
         ];
+        let expected = (expected.iter())
+            .map(|antecedent| parse(&format!("{} |- {}", antecedent, consequent)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(Some(expected), UnrollCollapse.premisses(goal, &facts));
 
-        if let Some(premisses) = UnrollCollapse.premisses(goal) {
-            for (expected, actual) in expected.iter().zip(premisses) {
-                assert_eq!(*expected, actual);
-            }
-        }
+        let lseg = "pred lseg(a,b) := emp & a=b | exists c. a->c * lseg(c,b) & a!=b;";
+        let goal = parse(&format!(
+            "{} True|SepConj[lseg(x,y)] |- exists z. True|SepConj[x->z,lseg(z,y)]",
+            lseg
+        ))?;
+        let facts = Congruence::of(&goal);
+        assert_eq!(
+            Some(4),
+            UnrollCollapse.premisses(goal, &facts).map(|p| p.len())
+        );
+        Ok(())
     }
 }
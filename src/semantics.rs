@@ -0,0 +1,592 @@
+use crate::datastructures::{
+    AtomSpatial,
+    AtomSpatial::{PointsTo, Pred, Tree, DLS, LS},
+    Expr, Field, Formula, Op,
+    Op::{AtomEq, AtomNeq},
+    Pure,
+    Pure::{And, True},
+    Record, Spatial,
+    Spatial::{Emp, SepConj},
+    Variable, NEXT,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// A value of the concrete state: either nil or an (allocatable) location
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+pub enum Value {
+    Nil,
+    Loc(usize),
+}
+
+/// The values of the fields of an allocated location
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct Cell(pub BTreeMap<Field, Value>);
+
+pub type Stack = BTreeMap<Variable, Value>;
+pub type Heap = BTreeMap<usize, Cell>;
+
+/// Evaluates an expression in the given stack. Unbound variables have no value.
+pub fn eval(expr: &Expr, stack: &Stack) -> Option<Value> {
+    match expr {
+        Expr::Nil => Some(Value::Nil),
+        Expr::Var(v) => stack.get(v).cloned(),
+    }
+}
+
+/// Evaluates the values of a record to the cell it describes
+pub fn eval_record(record: &Record, stack: &Stack) -> Option<Cell> {
+    let mut cell = Cell::default();
+    for (field, value) in &record.0 {
+        cell.0.insert(field.clone(), eval(value, stack)?);
+    }
+    Some(cell)
+}
+
+/// Decides s, h ⊨ exists z. Π | Σ according to the standard semantics of separation logic,
+/// i.e. the heap has to be described exactly by the spatial part.
+pub fn satisfies(formula: &Formula, stack: &Stack, heap: &Heap) -> bool {
+    let existentials = formula.get_existentials();
+    if existentials.is_empty() {
+        return satisfies_pure(formula.get_pure(), stack)
+            && satisfies_spatial(formula.get_spatial(), stack, heap);
+    }
+    // Locations the state does not mention cannot be told apart, so besides nil and the
+    // mentioned ones every existential only needs an unused location of its own
+    let mut values: BTreeSet<Value> = stack.values().cloned().collect();
+    values.insert(Value::Nil);
+    for (loc, cell) in heap {
+        values.insert(Value::Loc(*loc));
+        values.extend(cell.0.values());
+    }
+    let unused = (values.iter())
+        .filter_map(|value| match value {
+            Value::Loc(loc) => Some(*loc),
+            Value::Nil => None,
+        })
+        .max()
+        .unwrap_or(0);
+    values.extend((1..=existentials.len()).map(|i| Value::Loc(unused + i)));
+    let values: Vec<Value> = values.into_iter().collect();
+    witness(formula, existentials, &values, &mut stack.clone(), heap)
+}
+
+/// Tries all values for the remaining existentials
+fn witness(
+    formula: &Formula,
+    existentials: &[Variable],
+    values: &[Value],
+    stack: &mut Stack,
+    heap: &Heap,
+) -> bool {
+    match existentials.split_first() {
+        None => {
+            satisfies_pure(formula.get_pure(), stack)
+                && satisfies_spatial(formula.get_spatial(), stack, heap)
+        }
+        Some((var, rest)) => values.iter().any(|value| {
+            stack.insert(var.clone(), *value);
+            witness(formula, rest, values, stack, heap)
+        }),
+    }
+}
+
+pub fn satisfies_pure(pure: &Pure, stack: &Stack) -> bool {
+    match pure {
+        True => true,
+        And(ops) => ops.iter().all(|op| satisfies_op(op, stack)),
+    }
+}
+
+pub fn satisfies_op(op: &Op, stack: &Stack) -> bool {
+    match op {
+        AtomEq(l, r) => match (eval(l, stack), eval(r, stack)) {
+            (Some(l), Some(r)) => l == r,
+            _ => false,
+        },
+        AtomNeq(l, r) => match (eval(l, stack), eval(r, stack)) {
+            (Some(l), Some(r)) => l != r,
+            _ => false,
+        },
+    }
+}
+
+pub fn satisfies_spatial(spatial: &Spatial, stack: &Stack, heap: &Heap) -> bool {
+    let atoms: &[AtomSpatial] = match spatial {
+        SepConj(atoms) => atoms,
+        Emp => &[],
+    };
+
+    // The footprint of every atom is uniquely determined by the heap, so the heap is split
+    // between the atoms iff their footprints are disjoint and cover the whole heap.
+    let mut footprint = BTreeSet::new();
+    for atom in atoms {
+        match footprint_of(atom, stack, heap) {
+            Some(locations) => {
+                for loc in locations {
+                    if !footprint.insert(loc) {
+                        return false;
+                    }
+                }
+            }
+            None => return false,
+        }
+    }
+    footprint.len() == heap.len()
+}
+
+/// The locations an atom occupies in the heap, if the atom can hold on a subheap.
+fn footprint_of(atom: &AtomSpatial, stack: &Stack, heap: &Heap) -> Option<Vec<usize>> {
+    match atom {
+        // The cell may have further fields than the record
+        PointsTo(l, r) => match eval(l, stack)? {
+            Value::Loc(loc) => {
+                let cell = heap.get(&loc)?;
+                for (field, value) in &r.0 {
+                    if cell.get(field) != Some(eval(value, stack)?) {
+                        return None;
+                    }
+                }
+                Some(vec![loc])
+            }
+            Value::Nil => None,
+        },
+        // ls_f(E,F) is the acyclic path from E to F: E=F ∧ emp ∨ E!=F ∧ E->[f:y] * ls_f(y,F)
+        LS(field, l, r) => {
+            let end = eval(r, stack)?;
+            let mut current = eval(l, stack)?;
+            let mut locations = Vec::new();
+            while current != end {
+                match current {
+                    Value::Loc(loc) if !locations.contains(&loc) => {
+                        locations.push(loc);
+                        current = heap.get(&loc)?.get(field)?;
+                    }
+                    _ => return None,
+                }
+            }
+            Some(locations)
+        }
+        // dls(E1,E2,E3,E4) is E1=E4 ∧ E2=E3 ∧ emp ∨ E1!=E4 ∧ E2!=E3 ∧ E1->[next:u,prev:E3] * dls(u,E2,E1,E4)
+        DLS(first, last, prev, next) => {
+            let (last, next) = (eval(last, stack)?, eval(next, stack)?);
+            let (mut current, mut prev) = (eval(first, stack)?, eval(prev, stack)?);
+            let mut locations = Vec::new();
+            loop {
+                match (current == next, last == prev) {
+                    (true, true) => return Some(locations),
+                    (false, false) => {}
+                    _ => return None,
+                }
+                match current {
+                    Value::Loc(loc) if !locations.contains(&loc) => {
+                        let cell = heap.get(&loc)?;
+                        if cell.get(&Field::prev()) != Some(prev) {
+                            return None;
+                        }
+                        locations.push(loc);
+                        prev = current;
+                        current = cell.get(&Field::next())?;
+                    }
+                    _ => return None,
+                }
+            }
+        }
+        // tree(E) is E=nil ∧ emp ∨ E!=nil ∧ E->[left:l,right:r] * tree(l) * tree(r)
+        Tree(root) => {
+            let mut roots = vec![eval(root, stack)?];
+            let mut locations = Vec::new();
+            while let Some(root) = roots.pop() {
+                match root {
+                    Value::Nil => {}
+                    Value::Loc(loc) if !locations.contains(&loc) => {
+                        let cell = heap.get(&loc)?;
+                        locations.push(loc);
+                        roots.push(cell.get(&Field::left())?);
+                        roots.push(cell.get(&Field::right())?);
+                    }
+                    _ => return None,
+                }
+            }
+            Some(locations)
+        }
+        // The cases of a user-defined predicate exclude each other, so its cells are followed
+        // like those of ls, binding the parameters and existentials in a stack of their own
+        Pred(definition, args) => {
+            let mut env = Stack::new();
+            for (param, arg) in definition.params.iter().zip(args) {
+                env.insert(param.clone(), eval(arg, stack)?);
+            }
+            let mut locations = Vec::new();
+            while !definition.base.iter().all(|op| satisfies_op(op, &env)) {
+                let loc = match env.get(&definition.params[0]) {
+                    Some(&Value::Loc(loc)) if !locations.contains(&loc) => loc,
+                    _ => return None,
+                };
+                let cell = heap.get(&loc)?;
+                locations.push(loc);
+                for (field, pattern) in &definition.record.0 {
+                    let value = cell.get(field)?;
+                    match pattern {
+                        Expr::Var(var) if definition.existentials.contains(var) => {
+                            let bound = *env.entry(var.clone()).or_insert(value);
+                            if bound != value {
+                                return None;
+                            }
+                        }
+                        _ if eval(pattern, &env)? != value => return None,
+                        _ => {}
+                    }
+                }
+                if !definition.step.iter().all(|op| satisfies_op(op, &env)) {
+                    return None;
+                }
+                let mut next = Stack::new();
+                for (param, arg) in definition.params.iter().zip(&definition.call) {
+                    next.insert(param.clone(), eval(arg, &env)?);
+                }
+                env = next;
+            }
+            Some(locations)
+        }
+    }
+}
+
+impl Cell {
+    /// The cell of `E1->E2`, which only has the field `next`
+    pub fn next(value: Value) -> Self {
+        Cell(BTreeMap::from([(Field::next(), value)]))
+    }
+
+    pub fn get(&self, field: &Field) -> Option<Value> {
+        self.0.get(field).cloned()
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.iter().next() {
+            Some((field, value)) if self.0.len() == 1 && field.0 == NEXT => write!(f, "{}", value),
+            _ => {
+                let fields: Vec<_> = (self.0.iter())
+                    .map(|(field, value)| format!("{}: {}", field, value))
+                    .collect();
+                write!(f, "[{}]", fields.join(", "))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Loc(loc) => write!(f, "l{}", loc),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{satisfies, satisfies_pure, Cell, Heap, Stack, Value};
+    use crate::datastructures::{
+        AtomSpatial::{PointsTo, LS},
+        Expr,
+        Expr::Nil,
+        Field, Formula,
+        Op::{AtomEq, AtomNeq},
+        Pure::{And, True},
+        Record,
+        Spatial::{Emp, SepConj},
+        Variable,
+    };
+    use crate::parser::parse;
+
+    fn stack(vars: &[(&str, Value)]) -> Stack {
+        vars.iter()
+            .map(|(name, value)| (Variable(name.to_string()), *value))
+            .collect()
+    }
+
+    fn heap(cells: &[(usize, Value)]) -> Heap {
+        cells
+            .iter()
+            .map(|&(loc, next)| (loc, Cell::next(next)))
+            .collect()
+    }
+
+    #[test]
+    fn test_pure() {
+        let s = stack(&[("x", Value::Loc(1)), ("y", Value::Nil)]);
+        assert!(satisfies_pure(&True, &s));
+        assert!(satisfies_pure(
+            &And(vec![
+                AtomEq(Expr::new_var("y"), Nil),
+                AtomNeq(Expr::new_var("x"), Expr::new_var("y")),
+            ]),
+            &s
+        ));
+        assert!(!satisfies_pure(
+            &And(vec![AtomEq(Expr::new_var("x"), Nil)]),
+            &s
+        ));
+        // Unbound variables are never satisfied
+        assert!(!satisfies_pure(
+            &And(vec![AtomEq(Expr::new_var("z"), Expr::new_var("z"))]),
+            &s
+        ));
+    }
+
+    #[test]
+    fn test_points_to_and_emp() {
+        let s = stack(&[("x", Value::Loc(1)), ("y", Value::Loc(2))]);
+        let h = heap(&[(1, Value::Loc(2))]);
+        let x_y = Formula(
+            True,
+            SepConj(vec![PointsTo(
+                Expr::new_var("x"),
+                Record::next(Expr::new_var("y")),
+            )]),
+            Vec::new(),
+        );
+        assert!(satisfies(&x_y, &s, &h));
+        assert!(!satisfies(&Formula(True, Emp, Vec::new()), &s, &h));
+        assert!(satisfies(&Formula(True, Emp, Vec::new()), &s, &Heap::new()));
+        assert!(satisfies(
+            &Formula(True, SepConj(vec![]), Vec::new()),
+            &s,
+            &Heap::new()
+        ));
+        // The heap has to be described exactly
+        assert!(!satisfies(
+            &x_y,
+            &s,
+            &heap(&[(1, Value::Loc(2)), (2, Value::Nil)])
+        ));
+        // Nil can not be dereferenced
+        assert!(!satisfies(
+            &Formula(
+                True,
+                SepConj(vec![PointsTo(Nil, Record::next(Expr::new_var("y")))]),
+                Vec::new()
+            ),
+            &s,
+            &h
+        ));
+    }
+
+    #[test]
+    fn test_sep_conj() {
+        let s = stack(&[("x", Value::Loc(1)), ("y", Value::Loc(1))]);
+        let h = heap(&[(1, Value::Nil)]);
+        let twice = Formula(
+            True,
+            SepConj(vec![
+                PointsTo(Expr::new_var("x"), Record::next(Nil)),
+                PointsTo(Expr::new_var("y"), Record::next(Nil)),
+            ]),
+            Vec::new(),
+        );
+        assert!(!satisfies(&twice, &s, &h));
+
+        let s = stack(&[("x", Value::Loc(1)), ("y", Value::Loc(2))]);
+        let h = heap(&[(1, Value::Nil), (2, Value::Nil)]);
+        assert!(satisfies(&twice, &s, &h));
+    }
+
+    #[test]
+    fn test_ls() {
+        let s = stack(&[
+            ("x", Value::Loc(1)),
+            ("y", Value::Loc(3)),
+            ("z", Value::Loc(2)),
+        ]);
+        let h = heap(&[(1, Value::Loc(2)), (2, Value::Loc(3))]);
+        let ls_xy = Formula(
+            True,
+            SepConj(vec![LS(
+                Field::next(),
+                Expr::new_var("x"),
+                Expr::new_var("y"),
+            )]),
+            Vec::new(),
+        );
+        assert!(satisfies(&ls_xy, &s, &h));
+        assert!(satisfies(
+            &Formula(
+                True,
+                SepConj(vec![
+                    LS(Field::next(), Expr::new_var("x"), Expr::new_var("z")),
+                    LS(Field::next(), Expr::new_var("z"), Expr::new_var("y")),
+                ]),
+                Vec::new()
+            ),
+            &s,
+            &h
+        ));
+        assert!(!satisfies(&ls_xy, &s, &heap(&[(1, Value::Loc(2))])));
+
+        // Empty segments
+        let ls_xx = Formula(
+            True,
+            SepConj(vec![LS(
+                Field::next(),
+                Expr::new_var("x"),
+                Expr::new_var("x"),
+            )]),
+            Vec::new(),
+        );
+        assert!(satisfies(&ls_xx, &s, &Heap::new()));
+        assert!(!satisfies(&ls_xx, &s, &h));
+
+        // Segments are acyclic
+        let cyclic = heap(&[(1, Value::Loc(2)), (2, Value::Loc(1))]);
+        assert!(!satisfies(
+            &Formula(
+                True,
+                SepConj(vec![LS(Field::next(), Expr::new_var("x"), Nil)]),
+                Vec::new()
+            ),
+            &s,
+            &cyclic
+        ));
+        // but may end in an allocated location
+        assert!(satisfies(
+            &Formula(
+                True,
+                SepConj(vec![
+                    LS(Field::next(), Expr::new_var("x"), Expr::new_var("z")),
+                    PointsTo(Expr::new_var("z"), Record::next(Expr::new_var("x"))),
+                ]),
+                Vec::new()
+            ),
+            &s,
+            &cyclic
+        ));
+    }
+
+    #[test]
+    fn test_records() -> Result<(), crate::error::Error> {
+        let formula =
+            |input: &str| parse(&format!("{} |- True|Emp", input)).map(|goal| goal.antecedent);
+        let field = |name: &str| Field(name.to_string());
+        let s = stack(&[("x", Value::Loc(1)), ("y", Value::Loc(2))]);
+        let mut h = Heap::new();
+        h.insert(
+            1,
+            Cell([(field("next"), Value::Loc(2)), (field("data"), Value::Nil)].into()),
+        );
+        h.insert(2, Cell([(field("prev"), Value::Loc(1))].into()));
+
+        assert!(satisfies(
+            &formula("True|SepConj[x->[next:y,data:Nil],y->[prev:x]]")?,
+            &s,
+            &h
+        ));
+        // Cells may have more fields than their points-to atoms
+        assert!(satisfies(
+            &formula("True|SepConj[x->[data:Nil],y->[]]")?,
+            &s,
+            &h
+        ));
+        assert!(!satisfies(
+            &formula("True|SepConj[x->[next:y,data:y],y->[]]")?,
+            &s,
+            &h
+        ));
+        assert!(!satisfies(
+            &formula("True|SepConj[x->[prev:y],y->[]]")?,
+            &s,
+            &h
+        ));
+        // Segments follow their link field, whatever the other fields of the cells are
+        assert!(satisfies(
+            &formula("True|SepConj[ls(x,y),ls[prev](y,x)]")?,
+            &s,
+            &h
+        ));
+        assert!(!satisfies(
+            &formula("True|SepConj[ls[prev](x,y),y->[]]")?,
+            &s,
+            &h
+        ));
+        // x and y form a doubly linked list from nil to nil once y links back to x
+        h.insert(
+            2,
+            Cell([(field("next"), Value::Nil), (field("prev"), Value::Loc(1))].into()),
+        );
+        h.get_mut(&1).unwrap().0.insert(field("prev"), Value::Nil);
+        assert!(satisfies(
+            &formula("True|SepConj[dls(x,y,Nil,Nil)]")?,
+            &s,
+            &h
+        ));
+        assert!(satisfies(
+            &formula("True|SepConj[dls(x,x,Nil,y),dls(y,y,x,Nil),dls(y,x,x,y)]")?,
+            &s,
+            &h
+        ));
+        assert!(!satisfies(
+            &formula("True|SepConj[dls(x,y,x,Nil)]")?,
+            &s,
+            &h
+        ));
+        // Only one end of the segment meets its outside
+        assert!(!satisfies(
+            &formula("True|SepConj[dls(x,x,Nil,y),y->[],dls(y,x,y,y)]")?,
+            &s,
+            &h
+        ));
+
+        // x is the root of a tree with the single child y on its left
+        let mut h = Heap::new();
+        let node = |left: Value, right: Value| {
+            Cell([(field("left"), left), (field("right"), right)].into())
+        };
+        h.insert(1, node(Value::Loc(2), Value::Nil));
+        h.insert(2, node(Value::Nil, Value::Nil));
+        assert!(satisfies(&formula("True|SepConj[tree(x)]")?, &s, &h));
+        assert!(satisfies(
+            &formula("True|SepConj[tree(Nil),x->[left:y],tree(y)]")?,
+            &s,
+            &h
+        ));
+        assert!(!satisfies(&formula("True|SepConj[tree(y)]")?, &s, &h));
+        // Subtrees cannot share cells
+        h.insert(1, node(Value::Loc(2), Value::Loc(2)));
+        assert!(!satisfies(&formula("True|SepConj[tree(x)]")?, &s, &h));
+        Ok(())
+    }
+
+    #[test]
+    fn test_existentials() -> Result<(), crate::error::Error> {
+        let consequent =
+            |input: &str| parse(&format!("True|Emp |- {}", input)).map(|goal| goal.consequent);
+        let s = stack(&[("x", Value::Loc(1)), ("y", Value::Loc(2))]);
+        let h = heap(&[(1, Value::Loc(2)), (2, Value::Nil)]);
+        assert!(satisfies(
+            &consequent("exists z. True|SepConj[x->z,z->Nil]")?,
+            &s,
+            &h
+        ));
+        assert!(satisfies(
+            &consequent("exists z. True|SepConj[ls(x,z),ls(z,Nil)]")?,
+            &s,
+            &h
+        ));
+        assert!(!satisfies(
+            &consequent("exists z. And[Neq(z,y)]|SepConj[x->z,ls(z,Nil)]")?,
+            &s,
+            &h
+        ));
+        // A witness may also be a location outside of the heap
+        assert!(satisfies(
+            &consequent("exists z. And[Neq(z,Nil),Neq(z,x),Neq(z,y)]|SepConj[ls(x,Nil)]")?,
+            &s,
+            &h
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,665 @@
+//! A front end for the `qf_shls_entl` division of [SL-COMP](https://sl-comp.github.io/).
+//!
+//! A benchmark declares the heap and a list segment predicate, asserts the antecedent `A` and
+//! the negated consequent `(not B)` and asks `(check-sat)`. The entailment `A |- B` is valid iff
+//! the benchmark is `unsat`. In SL-COMP pure atoms hold in every heap, whereas the prover reads a
+//! formula without spatial atoms as the empty heap. Hence only formulas whose heap is described by
+//! exactly one spatial conjunct are accepted, and pure atoms must not occur inside `sep`.
+use crate::{
+    datastructures::{
+        AtomSpatial,
+        AtomSpatial::{PointsTo, LS},
+        Entailment, Expr, Field, Formula, Op,
+        Op::{AtomEq, AtomNeq},
+        Pure, Record, Spatial, Variable, FRESH_SEPARATOR,
+    },
+    error::Error,
+    parser::is_name,
+};
+use combine::{
+    attempt, between, eof, many, many1, none_of, parser,
+    parser::{
+        char::{char, spaces, string},
+        repeat::skip_many,
+    },
+    satisfy,
+    stream::position::{self, SourcePosition},
+    EasyParser, ParseError, Parser, Stream,
+};
+use std::collections::BTreeMap;
+
+/// A symbol or a list together with the position it starts at
+#[derive(PartialEq, Eq, Debug, Clone)]
+struct SExpr {
+    position: SourcePosition,
+    kind: Kind,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone)]
+enum Kind {
+    Symbol(String),
+    List(Vec<SExpr>),
+}
+
+fn skip_whitespace<Input>() -> impl Parser<Input, Output = ()>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let comment = (char(';'), skip_many(none_of("\n".chars()))).map(|_| ());
+    (spaces(), skip_many((comment, spaces()))).map(|_| ())
+}
+
+fn parse_symbol<Input>() -> impl Parser<Input, Output = String>
+where
+    Input: Stream<Token = char>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let quoted = between(
+        char('|'),
+        char('|'),
+        many::<String, _, _>(none_of("|".chars())),
+    );
+    let string_literal = between(
+        char('"'),
+        char('"'),
+        many::<String, _, _>(none_of("\"".chars()).or(attempt(string("\"\"")).map(|_| '"'))),
+    )
+    .map(|content| format!("\"{}\"", content));
+    let simple = many1::<String, _, _>(satisfy(|c: char| {
+        !c.is_whitespace() && !"()|\";".contains(c)
+    }));
+    quoted.or(string_literal).or(simple)
+}
+
+fn parse_sexpr_<Input>() -> impl Parser<Input, Output = SExpr>
+where
+    Input: Stream<Token = char, Position = SourcePosition>,
+    Input::Error: ParseError<Input::Token, Input::Range, Input::Position>,
+{
+    let list = between(
+        (char('('), skip_whitespace()),
+        char(')'),
+        many(parse_sexpr()),
+    )
+    .map(Kind::List);
+    (
+        combine::position(),
+        parse_symbol().map(Kind::Symbol).or(list),
+    )
+        .skip(skip_whitespace())
+        .map(|(position, kind)| SExpr { position, kind })
+}
+
+parser! {
+    fn parse_sexpr[Input]()(Input) -> SExpr
+    where [Input: Stream<Token = char, Position = SourcePosition>]
+    {
+        parse_sexpr_()
+    }
+}
+
+fn read_sexprs(input: &str) -> Result<Vec<SExpr>, Error> {
+    (skip_whitespace(), many(parse_sexpr()), eof())
+        .map(|(_, sexprs, _)| sexprs)
+        .easy_parse(position::Stream::new(input))
+        .map(|(sexprs, _)| sexprs)
+        .map_err(|errors| Error::Parse {
+            line: errors.position.line,
+            column: errors.position.column,
+            message: errors
+                .errors
+                .iter()
+                .map(|error| error.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+        })
+}
+
+impl SExpr {
+    fn error(&self, message: String) -> Error {
+        Error::Parse {
+            line: self.position.line,
+            column: self.position.column,
+            message,
+        }
+    }
+
+    fn symbol(&self) -> Option<&str> {
+        match &self.kind {
+            Kind::Symbol(symbol) => Some(symbol),
+            Kind::List(_) => None,
+        }
+    }
+
+    /// The head symbol and the arguments of an application like `(f a b)`
+    fn application(&self) -> Option<(&str, &[SExpr])> {
+        match &self.kind {
+            Kind::List(list) => match list.split_first() {
+                Some((head, args)) => head.symbol().map(|head| (head, args)),
+                None => None,
+            },
+            Kind::Symbol(_) => None,
+        }
+    }
+
+    fn list(&self) -> Result<&[SExpr], Error> {
+        match &self.kind {
+            Kind::List(list) => Ok(list),
+            Kind::Symbol(symbol) => {
+                Err(self.error(format!("Expected a list but found {}", symbol)))
+            }
+        }
+    }
+
+    /// Matches the expression against a pattern in which symbols starting with `?` are placeholders
+    fn matches(&self, pattern: &SExpr, bindings: &mut BTreeMap<String, String>) -> bool {
+        match (&pattern.kind, &self.kind) {
+            (Kind::Symbol(placeholder), Kind::Symbol(symbol)) if placeholder.starts_with('?') => {
+                bindings
+                    .entry(placeholder.clone())
+                    .or_insert_with(|| symbol.clone())
+                    == symbol
+            }
+            (Kind::Symbol(expected), Kind::Symbol(symbol)) => expected == symbol,
+            (Kind::List(patterns), Kind::List(list)) => {
+                patterns.len() == list.len()
+                    && list
+                        .iter()
+                        .zip(patterns)
+                        .all(|(sexpr, pattern)| sexpr.matches(pattern, bindings))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// The acyclic list segment definitions of SL-COMP, which coincide with ls(in,out)
+const LIST_SEGMENTS: [&str; 2] = [
+    "(or (and (= ?in ?out) (_ emp ?loc ?data))
+         (exists ((?u ?loc)) (and (distinct ?in ?out) (sep (pto ?in (?c ?u)) (?ls ?u ?out)))))",
+    "(or (and (_ emp ?loc ?data) (= ?in ?out))
+         (exists ((?u ?loc)) (and (distinct ?in ?out) (sep (pto ?in (?c ?u)) (?ls ?u ?out)))))",
+];
+
+/// Constants and fields are kept as they are, so their names have to fit the input syntax
+fn unsupported_name(symbol: &str) -> String {
+    format!(
+        "{} is not supported as a name, which has to start with a letter followed by letters, digits and _",
+        symbol
+    )
+}
+
+/// The declarations of a benchmark that are needed to read its assertions
+#[derive(Default, Clone)]
+struct Declarations {
+    constants: Vec<String>,
+    /// The record constructors and their fields
+    constructors: BTreeMap<String, Vec<Field>>,
+    /// The list segment predicates and their link fields
+    segments: BTreeMap<String, Field>,
+}
+
+impl Declarations {
+    fn declare_constant(&mut self, name: &SExpr) -> Result<(), Error> {
+        match name.symbol() {
+            Some(symbol) if symbol.contains(FRESH_SEPARATOR) => Err(name.error(format!(
+                "Names containing {} are reserved for the prover",
+                FRESH_SEPARATOR
+            ))),
+            Some(symbol) if !is_name(symbol) => Err(name.error(unsupported_name(symbol))),
+            Some(symbol) => {
+                self.constants.push(symbol.to_string());
+                Ok(())
+            }
+            None => Err(name.error("Expected the name of a constant".to_string())),
+        }
+    }
+
+    /// Records the constructors of records, e.g. `((c_Dll_t (next RefDll_t) (prev RefDll_t)))`
+    fn declare_datatype(&mut self, constructors: &SExpr) -> Result<(), Error> {
+        for constructor in constructors.list()? {
+            if let Some((name, fields)) = constructor.application() {
+                let fields = fields
+                    .iter()
+                    .map(|field| match field.application() {
+                        Some((symbol, [_sort])) if is_name(symbol) => Ok(Field(symbol.to_string())),
+                        Some((symbol, [_sort])) => Err(field.error(unsupported_name(symbol))),
+                        _ => Err(field.error("Expected a field and its sort".to_string())),
+                    })
+                    .collect::<Result<_, _>>()?;
+                self.constructors.insert(name.to_string(), fields);
+            }
+        }
+        Ok(())
+    }
+
+    /// Accepts definitions of the form `(ls ((in Loc) (out Loc)) Bool body)`, whose records have a
+    /// single field that links the segment
+    fn define_predicate(&mut self, definition: &[SExpr], at: &SExpr) -> Result<(), Error> {
+        let unsupported = || at.error("Only list segment predicates are supported".to_string());
+        let (name, params, body) = match definition {
+            [name, params, _, body] => (name.symbol().ok_or_else(unsupported)?, params, body),
+            _ => return Err(unsupported()),
+        };
+        let params: Vec<&str> = params
+            .list()?
+            .iter()
+            .filter_map(|param| param.application().map(|(name, _)| name))
+            .collect();
+        let link = LIST_SEGMENTS.iter().find_map(|pattern| {
+            let pattern = &read_sexprs(pattern).expect("Patterns are well formed")[0];
+            let mut bindings = BTreeMap::new();
+            if !body.matches(pattern, &mut bindings)
+                || params != [&bindings["?in"][..], &bindings["?out"][..]]
+                || bindings["?ls"] != name
+            {
+                return None;
+            }
+            match self.constructors.get(&bindings["?c"]).map(Vec::as_slice) {
+                Some([field]) => Some(field.clone()),
+                _ => None,
+            }
+        });
+        match link {
+            Some(link) => {
+                self.segments.insert(name.to_string(), link);
+                Ok(())
+            }
+            None => Err(unsupported()),
+        }
+    }
+
+    fn expr(&self, term: &SExpr) -> Result<Expr, Error> {
+        match (term.symbol(), term.application()) {
+            (Some("nil"), _) => Ok(Expr::Nil),
+            (_, Some(("as", [nil, _]))) if nil.symbol() == Some("nil") => Ok(Expr::Nil),
+            (Some(name), _) if self.constants.iter().any(|constant| constant == name) => {
+                Ok(Expr::new_var(name))
+            }
+            _ => Err(term.error("Expected nil or a declared constant".to_string())),
+        }
+    }
+
+    /// Collects the pure and spatial atoms of nested conjunctions and separating conjunctions and
+    /// returns whether the term describes the heap, i.e. is no pure formula
+    fn atoms(
+        &self,
+        term: &SExpr,
+        pure: &mut Vec<Op>,
+        spatial: &mut Vec<AtomSpatial>,
+    ) -> Result<bool, Error> {
+        match term.application() {
+            Some(("and", args)) => self.conjunction(args, term, pure, spatial),
+            Some(("sep", args)) => {
+                for arg in args {
+                    if !self.atoms(arg, pure, spatial)? {
+                        return Err(arg.error(
+                            "Pure formulas inside sep are not supported, since they hold in every heap"
+                                .to_string(),
+                        ));
+                    }
+                }
+                Ok(true)
+            }
+            Some(("=", [l, r])) => {
+                pure.push(AtomEq(self.expr(l)?, self.expr(r)?));
+                Ok(false)
+            }
+            Some(("distinct", args)) => {
+                for (i, l) in args.iter().enumerate() {
+                    for r in &args[i + 1..] {
+                        pure.push(AtomNeq(self.expr(l)?, self.expr(r)?));
+                    }
+                }
+                Ok(false)
+            }
+            Some(("_", [emp, _, _])) if emp.symbol() == Some("emp") => Ok(true),
+            Some(("pto", [l, r])) => {
+                let record = match r.application() {
+                    Some((constructor, values)) if self.constructors.contains_key(constructor) => {
+                        let fields = &self.constructors[constructor];
+                        if fields.len() != values.len() {
+                            return Err(r.error(format!(
+                                "{} expects {} fields",
+                                constructor,
+                                fields.len()
+                            )));
+                        }
+                        let mut record = Record::default();
+                        for (field, value) in fields.iter().zip(values) {
+                            record.0.insert(field.clone(), self.expr(value)?);
+                        }
+                        record
+                    }
+                    _ => Record::next(self.expr(r)?),
+                };
+                spatial.push(PointsTo(self.expr(l)?, record));
+                Ok(true)
+            }
+            Some((name, [l, r])) if self.segments.contains_key(name) => {
+                let link = self.segments[name].clone();
+                spatial.push(LS(link, self.expr(l)?, self.expr(r)?));
+                Ok(true)
+            }
+            _ if term.symbol() == Some("true") => Ok(false),
+            _ => Err(term.error("Unsupported formula".to_string())),
+        }
+    }
+
+    /// The conjuncts share the heap, so at most one of them may describe it
+    fn conjunction<'a>(
+        &self,
+        conjuncts: impl IntoIterator<Item = &'a SExpr>,
+        at: &SExpr,
+        pure: &mut Vec<Op>,
+        spatial: &mut Vec<AtomSpatial>,
+    ) -> Result<bool, Error> {
+        let mut described = false;
+        for conjunct in conjuncts {
+            if self.atoms(conjunct, pure, spatial)? {
+                if described {
+                    return Err(at.error(
+                        "A conjunction of several spatial formulas is not supported".to_string(),
+                    ));
+                }
+                described = true;
+            }
+        }
+        Ok(described)
+    }
+
+    /// Reads the conjunction of the terms, which has to describe the heap
+    fn formula(&self, terms: &[&SExpr], at: &SExpr) -> Result<Formula, Error> {
+        let mut pure = Vec::new();
+        let mut spatial = Vec::new();
+        if !self.conjunction(terms.iter().copied(), at, &mut pure, &mut spatial)? {
+            return Err(at.error(
+                "Pure formulas are not supported, since they hold in every heap".to_string(),
+            ));
+        }
+        let pure = if pure.is_empty() {
+            Pure::True
+        } else {
+            Pure::And(pure)
+        };
+        let spatial = if spatial.is_empty() {
+            Spatial::Emp
+        } else {
+            Spatial::SepConj(spatial)
+        };
+        Ok(Formula(pure, spatial, Vec::new()))
+    }
+
+    /// Reads the negated assertion, which may quantify constants of its own,
+    /// e.g. `(exists ((u Loc)) (sep (pto x u) (ls u nil)))`
+    fn consequent(&self, term: &SExpr) -> Result<Formula, Error> {
+        let (bound, body) = match term.application() {
+            Some(("exists", [bound, body])) => (bound, body),
+            _ => return self.formula(&[term], term),
+        };
+        let mut scope = self.clone();
+        let mut existentials = Vec::new();
+        for binding in bound.list()? {
+            let name = match binding.list()? {
+                [name, _sort] => name,
+                _ => return Err(binding.error("Expected a name and its sort".to_string())),
+            };
+            let symbol = name.symbol().unwrap_or_default();
+            if self.constants.iter().any(|constant| constant == symbol) {
+                return Err(name.error(format!("{} is already a constant", symbol)));
+            }
+            scope.declare_constant(name)?;
+            existentials.push(Variable(symbol.to_string()));
+        }
+        let Formula(pure, spatial, _) = scope.formula(&[body], body)?;
+        Ok(Formula(pure, spatial, existentials))
+    }
+}
+
+/// Reads an SL-COMP benchmark of the `qf_shls_entl` division as an entailment
+pub fn parse(input: &str) -> Result<Entailment, Error> {
+    let mut declarations = Declarations::default();
+    let mut antecedent = Vec::new();
+    let mut consequent = Vec::new();
+    let commands = read_sexprs(input)?;
+    for command in &commands {
+        match command.application() {
+            Some(("set-logic", _))
+            | Some(("set-info", _))
+            | Some(("set-option", _))
+            | Some(("declare-sort", _))
+            | Some(("declare-heap", _))
+            | Some(("check-sat", _))
+            | Some(("get-model", _))
+            | Some(("exit", _)) => {}
+            Some(("declare-const", [name, _])) | Some(("declare-fun", [name, _, _])) => {
+                declarations.declare_constant(name)?
+            }
+            Some(("declare-datatype", [_, constructors])) => {
+                declarations.declare_datatype(constructors)?
+            }
+            Some(("declare-datatypes", [_, datatypes])) => {
+                for constructors in datatypes.list()? {
+                    declarations.declare_datatype(constructors)?;
+                }
+            }
+            Some(("define-fun-rec", definition)) | Some(("define-fun", definition)) => {
+                declarations.define_predicate(definition, command)?
+            }
+            Some(("assert", [assertion])) => match assertion.application() {
+                Some(("not", [negated])) => consequent.push(negated),
+                _ => antecedent.push(assertion),
+            },
+            _ => return Err(command.error("Unsupported command".to_string())),
+        }
+    }
+
+    match (antecedent.first(), consequent.as_slice()) {
+        (Some(first), [negated]) => Ok(Entailment {
+            antecedent: declarations.formula(&antecedent, first)?,
+            consequent: declarations.consequent(negated)?,
+        }),
+        (None, _) => Err(Error::Parse {
+            line: 1,
+            column: 1,
+            message: "Expected at least one assertion as antecedent".to_string(),
+        }),
+        _ => Err(Error::Parse {
+            line: 1,
+            column: 1,
+            message: "Expected exactly one negated assertion as consequent".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+    use crate::datastructures::{
+        AtomSpatial::{PointsTo, LS},
+        Entailment, Expr,
+        Expr::Nil,
+        Field, Formula,
+        Op::AtomNeq,
+        Pure::{And, True},
+        Record,
+        Spatial::SepConj,
+    };
+    use crate::error::Error;
+    use crate::prover::{prove, Verdict};
+
+    const BENCHMARK: &str = r#"(set-logic QF_SHLS)
+(set-info :source |
+  A small example in the style of SL-COMP (with "quotes" and (parentheses))
+|)
+(set-info :status unsat)
+
+(declare-sort RefSll_t 0)
+(declare-datatypes ((Sll_t 0)) (((c_Sll_t (next RefSll_t)))))
+(declare-heap (RefSll_t Sll_t))
+
+; the acyclic list segment
+(define-fun-rec ls ((in RefSll_t) (out RefSll_t)) Bool
+  (or (and (= in out) (_ emp RefSll_t Sll_t))
+      (exists ((u RefSll_t))
+        (and (distinct in out) (sep (pto in (c_Sll_t u)) (ls u out))))))
+
+(declare-const x0 RefSll_t)
+(declare-const x1 RefSll_t)
+
+(assert (and (distinct x0 x1) (sep (pto x0 (c_Sll_t x1)) (ls x1 (as nil RefSll_t)))))
+(assert (not (ls x0 (as nil RefSll_t))))
+(check-sat)
+"#;
+
+    #[test]
+    fn test_parse() -> Result<(), Error> {
+        let expected = Entailment {
+            antecedent: Formula(
+                And(vec![AtomNeq(Expr::new_var("x0"), Expr::new_var("x1"))]),
+                SepConj(vec![
+                    PointsTo(Expr::new_var("x0"), Record::next(Expr::new_var("x1"))),
+                    LS(Field::next(), Expr::new_var("x1"), Nil),
+                ]),
+                Vec::new(),
+            ),
+            consequent: Formula(
+                True,
+                SepConj(vec![LS(Field::next(), Expr::new_var("x0"), Nil)]),
+                Vec::new(),
+            ),
+        };
+        let entailment = parse(BENCHMARK)?;
+        assert_eq!(expected, entailment);
+        assert!(matches!(prove(&entailment)?, Verdict::Valid(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() {
+        let unknown = BENCHMARK.replace("(ls x1 (as", "(ls y (as");
+        match parse(&unknown) {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((20, 62), (line, column)),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+
+        let cyclic = BENCHMARK.replace("(and (distinct in out) ", "(and ");
+        match parse(&cyclic) {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((12, 1), (line, column)),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+
+        let shared_heap = BENCHMARK.replace(
+            "(sep (pto x0 (c_Sll_t x1)) (ls x1 (as nil RefSll_t)))",
+            "(pto x0 (c_Sll_t x1)) (pto x0 (c_Sll_t x1))",
+        );
+        match parse(&shared_heap) {
+            Err(Error::Parse { line, message, .. }) => {
+                assert_eq!(20, line);
+                assert!(message.contains("several spatial formulas"), "{}", message);
+            }
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+
+        let pure = BENCHMARK.replace("(not (ls x0 (as nil RefSll_t)))", "(not (distinct x0 x1))");
+        match parse(&pure) {
+            Err(Error::Parse { line, message, .. }) => {
+                assert_eq!(21, line);
+                assert!(
+                    message.contains("Pure formulas are not supported"),
+                    "{}",
+                    message
+                );
+            }
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+
+        let pure_part = BENCHMARK.replace("(ls x1 (as nil RefSll_t))", "(distinct x0 x1)");
+        match parse(&pure_part) {
+            Err(Error::Parse { message, .. }) => assert!(message.contains("inside sep")),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+
+        match parse("(assert (not true)) (check-sat") {
+            Err(Error::Parse { line, column, .. }) => assert_eq!((1, 31), (line, column)),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_records() -> Result<(), Error> {
+        let records = BENCHMARK
+            .replace(
+                "(((c_Sll_t (next RefSll_t)))))",
+                "(((c_Sll_t (next RefSll_t))) ((c_Node_t (next RefSll_t) (data RefSll_t)))))",
+            )
+            .replace("(Sll_t 0)", "(Sll_t 0) (Node_t 0)")
+            .replace("(pto x0 (c_Sll_t x1))", "(pto x0 (c_Node_t x1 x0))");
+        let entailment = parse(&records)?;
+        assert_eq!(
+            "And[Neq(x0,x1)]|SepConj[x0->[data:x0,next:x1],ls(x1,Nil)] |- True|SepConj[ls(x0,Nil)]",
+            entailment.to_string()
+        );
+        assert!(matches!(prove(&entailment)?, Verdict::Valid(_)));
+
+        let arity = records.replace("(c_Node_t x1 x0)", "(c_Node_t x1)");
+        match parse(&arity) {
+            Err(Error::Parse { message, .. }) => assert!(message.contains("expects 2 fields")),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_existentials() -> Result<(), Error> {
+        let exists = BENCHMARK.replace(
+            "(assert (not (ls x0 (as nil RefSll_t))))",
+            "(assert (not (exists ((v RefSll_t)) (sep (pto x0 (c_Sll_t v)) (ls v (as nil RefSll_t))))))",
+        );
+        let entailment = parse(&exists)?;
+        assert_eq!(
+            "And[Neq(x0,x1)]|SepConj[x0->x1,ls(x1,Nil)] |- exists v. True|SepConj[x0->v,ls(v,Nil)]",
+            entailment.to_string()
+        );
+        assert!(matches!(prove(&entailment)?, Verdict::Valid(_)));
+
+        let clash = exists.replace("((v RefSll_t))", "((x1 RefSll_t))");
+        match parse(&clash) {
+            Err(Error::Parse { message, .. }) => assert!(message.contains("already a constant")),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_names() -> Result<(), Error> {
+        let underscores = BENCHMARK
+            .replace("x0", "x_0")
+            .replace("x1", "x_1")
+            .replace("(next RefSll_t)", "(next_1 RefSll_t)");
+        let entailment = parse(&underscores)?;
+        assert_eq!(
+            "And[Neq(x_0,x_1)]|SepConj[x_0->[next_1:x_1],ls[next_1](x_1,Nil)] |- True|SepConj[ls[next_1](x_0,Nil)]",
+            entailment.to_string()
+        );
+        assert_eq!(entailment, crate::parser::parse(&entailment.to_string())?);
+
+        let dashes = BENCHMARK.replace("x0", "x-0");
+        match parse(&dashes) {
+            Err(Error::Parse { message, .. }) => assert!(message.contains("x-0 is not supported")),
+            other => panic!("Expected a parse error but got {:?}", other),
+        }
+
+        // Nil is parsed in any case and names are ASCII only
+        for symbol in ["nil", "NIL", "x\u{e9}"] {
+            match parse(&BENCHMARK.replace("x0", symbol)) {
+                Err(Error::Parse { message, .. }) => {
+                    assert!(message.contains(&format!("{} is not supported", symbol)))
+                }
+                other => panic!("Expected a parse error but got {:?}", other),
+            }
+        }
+        Ok(())
+    }
+}
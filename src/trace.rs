@@ -0,0 +1,164 @@
+use crate::{
+    datastructures::{Entailment, Expr, Variable},
+    printer::Unicode,
+};
+use std::io;
+
+/// How much of the proof search is reported while it runs. Every level includes the ones before.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum Verbosity {
+    Quiet,
+    /// Every goal visited and the rule that was applied to it
+    Goals,
+    /// Every rule whose predicate holds and whether it returned premisses. Rules whose predicate
+    /// always holds are only reported when applied.
+    Rules,
+    /// The premisses returned by the applied rules
+    Premisses,
+}
+
+/// Reports the steps of the proof search to a writer, e.g. stderr.
+/// Pretty traces are indented by depth and use the Unicode notation,
+/// plain traces start every line with the depth and use the input syntax.
+pub struct Trace<'a> {
+    pub verbosity: Verbosity,
+    pub pretty: bool,
+    out: &'a mut dyn io::Write,
+}
+
+impl<'a> Trace<'a> {
+    pub fn new(verbosity: Verbosity, pretty: bool, out: &'a mut dyn io::Write) -> Self {
+        Trace {
+            verbosity,
+            pretty,
+            out,
+        }
+    }
+
+    // A failing writer must not stop the proof search, hence errors are ignored
+    fn line(&mut self, depth: usize, level: Verbosity, message: &str) {
+        if self.verbosity < level {
+            return;
+        }
+        let _ = if self.pretty {
+            writeln!(self.out, "{:indent$}{}", "", message, indent = 2 * depth)
+        } else {
+            writeln!(self.out, "{} {}", depth, message)
+        };
+    }
+
+    fn show(&self, goal: &Entailment) -> String {
+        if self.pretty {
+            Unicode(goal).to_string()
+        } else {
+            goal.to_string()
+        }
+    }
+
+    pub fn goal(&mut self, depth: usize, goal: &Entailment) {
+        if self.verbosity >= Verbosity::Goals {
+            let message = format!("goal {}", self.show(goal));
+            self.line(depth, Verbosity::Goals, &message);
+        }
+    }
+
+    /// A rule whose predicate holds, together with the number of premisses it returned
+    pub fn rule(&mut self, depth: usize, rule: &str, premisses: Option<usize>) {
+        match premisses {
+            Some(n) => self.line(
+                depth,
+                Verbosity::Goals,
+                &format!("apply {} ({} premisses)", rule, n),
+            ),
+            None => self.line(
+                depth,
+                Verbosity::Rules,
+                &format!("skip {} (predicate holds, no premisses)", rule),
+            ),
+        }
+    }
+
+    pub fn premisses(&mut self, depth: usize, premisses: &[Entailment]) {
+        if self.verbosity >= Verbosity::Premisses {
+            for premiss in premisses {
+                let message = format!("premiss {}", self.show(premiss));
+                self.line(depth, Verbosity::Premisses, &message);
+            }
+        }
+    }
+
+    pub fn memo(&mut self, depth: usize) {
+        self.line(
+            depth,
+            Verbosity::Goals,
+            "reuse the proof of an equivalent goal",
+        );
+    }
+
+    pub fn stuck(&mut self, depth: usize) {
+        self.line(depth, Verbosity::Goals, "stuck, no rule applies");
+    }
+
+    /// A value tried for an existential of a stuck goal
+    pub fn witness(&mut self, depth: usize, var: &Variable, witness: &Expr) {
+        self.line(
+            depth,
+            Verbosity::Goals,
+            &format!("try {} as witness for {}", witness, var.0),
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Trace, Verbosity};
+    use crate::parser::parse;
+    use crate::prover::{prove_traced, Statistics};
+
+    fn trace(input: &str, verbosity: Verbosity, pretty: bool) -> Result<String, String> {
+        let goal = parse(input).map_err(|e| e.to_string())?;
+        let mut out = Vec::new();
+        let mut trace = Trace::new(verbosity, pretty, &mut out);
+        prove_traced(&goal, &mut Statistics::default(), &mut trace).map_err(|e| e.to_string())?;
+        String::from_utf8(out).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_trace() -> Result<(), String> {
+        let valid = "True|SepConj[x->Nil] |- True|SepConj[x->Nil]";
+        assert_eq!("", trace(valid, Verbosity::Quiet, false)?);
+        assert_eq!(
+            "0 goal True|SepConj[x->Nil] |- True|SepConj[x->Nil]
+0 apply NilNotLVal (1 premisses)
+1 goal And[Neq(x,Nil)]|SepConj[x->Nil] |- True|SepConj[x->Nil]
+1 apply Frame (1 premisses)
+2 goal And[Neq(x,Nil)]|SepConj[] |- True|SepConj[]
+2 apply Cleanup (1 premisses)
+3 goal And[Neq(x,Nil)]|Emp |- True|Emp
+3 apply Tautology (0 premisses)
+",
+            trace(valid, Verbosity::Goals, false)?
+        );
+
+        let pretty = trace(valid, Verbosity::Premisses, true)?;
+        let lines: Vec<&str> = pretty.lines().collect();
+        assert_eq!("goal true | x->nil  |-  true | x->nil", lines[0]);
+        assert_eq!("apply NilNotLVal (1 premisses)", lines[1]);
+        assert!(lines.contains(&"    skip Frame (predicate holds, no premisses)"));
+        assert!(!pretty.contains("skip Tautology") && !pretty.contains("skip Cleanup"));
+        assert!(lines.contains(&"  premiss x!=nil | emp  |-  true | emp"));
+
+        let invalid = trace("True|SepConj[x->y] |- True|Emp", Verbosity::Goals, true)?;
+        assert!(invalid.ends_with("stuck, no rule applies\n"));
+        let skipped = trace("True|SepConj[x->y] |- True|Emp", Verbosity::Rules, false)?;
+        assert!(skipped.ends_with(
+            "3 goal And[Neq(x,Nil)]|SepConj[x->Nil] |- True|Emp
+3 skip Frame (predicate holds, no premisses)
+3 skip NonEmptyPred (predicate holds, no premisses)
+3 skip UnfoldTree (predicate holds, no premisses)
+3 stuck, no rule applies
+"
+        ));
+        Ok(())
+    }
+}